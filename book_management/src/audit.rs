@@ -0,0 +1,568 @@
+// 审计日志：记录图书馆的每一次修改，可按 ISBN 或读者查询，也可以重放日志重建图书馆
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Deserialize, Serialize};
+use crate::{format_money, isbn, Book, BookUpdate, Condition, Library, LibraryError, LibraryResult, ManualClock, Metadata, Money, Patron, PatronStatus};
+
+// 没有设置操作者时记录的名字
+pub const DEFAULT_ACTOR: &str = "system";
+
+// 一次修改。事件中保存了重放所需的全部参数，其余字段用于查询和核对
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    BookAdded { book: Book },
+    BookRemoved { isbn: String },
+    // 修改后的完整书目信息，new_isbn 与 isbn 不同时表示更正了 ISBN
+    BookUpdated {
+        isbn: String,
+        new_isbn: String,
+        title: String,
+        author: String,
+        category: Option<String>,
+        #[serde(default)]
+        metadata: Metadata,
+    },
+    CopyAdded { isbn: String, copy_id: u32, shelf_location: String, condition: Condition },
+    PatronRegistered { patron: Patron },
+    PatronStatusChanged { patron: String, status: PatronStatus },
+    MembershipRenewed { patron: String, expires: NaiveDate },
+    Borrowed { isbn: String, patron: String, copy_id: u32, loan_id: u64, due: NaiveDate },
+    Returned { isbn: String, patron: String, copy_id: u32, fine: Money },
+    Renewed { isbn: String, patron: String, due: NaiveDate },
+    HoldPlaced { isbn: String, patron: String },
+    HoldCancelled { isbn: String, patron: String },
+    FinePaid { patron: String, amount: Money },
+    FineWaived { patron: String, amount: Money, reason: String },
+    // 副本放上预约书架。由归还、取消预约等修改引起，重放时随之重现，只通知订阅者，不写入审计日志
+    HoldShelved { isbn: String, copy_id: u32, patron: String, expires: NaiveDate },
+    // 预约超过取书期限被取消。取消的时间取决于何时清理，因此写入审计日志
+    HoldExpired { isbn: String, patron: String },
+}
+
+impl Event {
+    // 事件涉及的书籍；更正 ISBN 的事件同时涉及新旧两个 ISBN
+    pub fn involves_isbn(&self, target: &str) -> bool {
+        match self {
+            Event::BookAdded { book } => book.isbn == target,
+            Event::BookUpdated { isbn, new_isbn, .. } => isbn == target || new_isbn == target,
+            Event::BookRemoved { isbn }
+            | Event::CopyAdded { isbn, .. }
+            | Event::Borrowed { isbn, .. }
+            | Event::Returned { isbn, .. }
+            | Event::Renewed { isbn, .. }
+            | Event::HoldPlaced { isbn, .. }
+            | Event::HoldCancelled { isbn, .. }
+            | Event::HoldShelved { isbn, .. }
+            | Event::HoldExpired { isbn, .. } => isbn == target,
+            _ => false,
+        }
+    }
+
+    // 事件涉及的读者
+    pub fn patron(&self) -> Option<&str> {
+        match self {
+            Event::PatronRegistered { patron } => Some(&patron.id),
+            Event::PatronStatusChanged { patron, .. }
+            | Event::MembershipRenewed { patron, .. }
+            | Event::Borrowed { patron, .. }
+            | Event::Returned { patron, .. }
+            | Event::Renewed { patron, .. }
+            | Event::HoldPlaced { patron, .. }
+            | Event::HoldCancelled { patron, .. }
+            | Event::FinePaid { patron, .. }
+            | Event::FineWaived { patron, .. }
+            | Event::HoldShelved { patron, .. }
+            | Event::HoldExpired { patron, .. } => Some(patron),
+            _ => None,
+        }
+    }
+
+    // 是否写入审计日志
+    pub(crate) fn is_logged(&self) -> bool {
+        !matches!(self, Event::HoldShelved { .. })
+    }
+
+    // 在图书馆上重做这次修改
+    fn apply(&self, library: &mut Library) -> LibraryResult<()> {
+        match self.clone() {
+            Event::BookAdded { book } => library.add_book(book),
+            Event::BookRemoved { isbn } => library.remove_book(&isbn).map(drop),
+            Event::BookUpdated { isbn, new_isbn, title, author, category, metadata } => {
+                let update = BookUpdate {
+                    title: Some(title),
+                    author: Some(author),
+                    category: Some(category),
+                    isbn: Some(new_isbn),
+                    metadata: Some(metadata),
+                };
+                library.update_book(&isbn, update)
+            }
+            Event::CopyAdded { isbn, shelf_location, condition, .. } => {
+                library.add_copy(&isbn, shelf_location, condition).map(drop)
+            }
+            Event::PatronRegistered { patron } => library.register_patron(patron),
+            Event::PatronStatusChanged { patron, status } => library.set_patron_status(&patron, status),
+            Event::MembershipRenewed { patron, expires } => library.renew_membership(&patron, expires),
+            Event::Borrowed { isbn, patron, .. } => library.borrow_book(&isbn, &patron).map(drop),
+            Event::Returned { isbn, patron, .. } => library.return_book(&isbn, &patron).map(drop),
+            Event::Renewed { isbn, patron, .. } => library.renew_book(&isbn, &patron).map(drop),
+            Event::HoldPlaced { isbn, patron } => library.place_hold(&isbn, &patron).map(drop),
+            Event::HoldCancelled { isbn, patron } => library.cancel_hold(&isbn, &patron),
+            Event::FinePaid { patron, amount } => library.pay_fine(&patron, amount).map(drop),
+            Event::FineWaived { patron, amount, reason } => library.waive_fine(&patron, amount, &reason).map(drop),
+            Event::HoldExpired { isbn, patron } => library.expire_hold(&isbn, &patron),
+            // 引起它的修改重放时会自然重现
+            Event::HoldShelved { .. } => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::BookAdded { book } => {
+                write!(f, "添加书籍 '{}'（ISBN {}）", book.title, isbn::display(&book.isbn))
+            }
+            Event::BookRemoved { isbn } => write!(f, "移除书籍 {}", isbn::display(isbn)),
+            Event::BookUpdated { isbn, new_isbn, title, .. } if isbn != new_isbn => write!(
+                f,
+                "修改书目 '{}'，ISBN {} 更正为 {}",
+                title,
+                isbn::display(isbn),
+                isbn::display(new_isbn)
+            ),
+            Event::BookUpdated { isbn, title, .. } => write!(f, "修改书目 '{}'（ISBN {}）", title, isbn::display(isbn)),
+            Event::CopyAdded { isbn, copy_id, .. } => write!(f, "书籍 {} 新增副本 #{}", isbn::display(isbn), copy_id),
+            Event::PatronRegistered { patron } => write!(f, "登记读者 {}", patron),
+            Event::PatronStatusChanged { patron, status: PatronStatus::Active } => write!(f, "恢复读者 {} 的借阅资格", patron),
+            Event::PatronStatusChanged { patron, status: PatronStatus::Suspended } => write!(f, "暂停读者 {} 的借阅资格", patron),
+            Event::MembershipRenewed { patron, expires } => write!(f, "读者 {} 的会员续期至 {}", patron, expires),
+            Event::Borrowed { isbn, patron, copy_id, due, .. } => write!(
+                f,
+                "{} 借阅 {}（副本 #{}），应还日期 {}",
+                patron,
+                isbn::display(isbn),
+                copy_id,
+                due
+            ),
+            Event::Returned { isbn, patron, copy_id, fine } if *fine > 0 => write!(
+                f,
+                "{} 归还 {}（副本 #{}），罚款 {}",
+                patron,
+                isbn::display(isbn),
+                copy_id,
+                format_money(*fine)
+            ),
+            Event::Returned { isbn, patron, copy_id, .. } => {
+                write!(f, "{} 归还 {}（副本 #{}）", patron, isbn::display(isbn), copy_id)
+            }
+            Event::Renewed { isbn, patron, due } => {
+                write!(f, "{} 续借 {}，新的应还日期 {}", patron, isbn::display(isbn), due)
+            }
+            Event::HoldPlaced { isbn, patron } => write!(f, "{} 预约 {}", patron, isbn::display(isbn)),
+            Event::HoldCancelled { isbn, patron } => write!(f, "{} 取消预约 {}", patron, isbn::display(isbn)),
+            Event::FinePaid { patron, amount } => write!(f, "{} 缴纳罚款 {}", patron, format_money(*amount)),
+            Event::FineWaived { patron, amount, reason } => {
+                write!(f, "减免 {} 的罚款 {}（{}）", patron, format_money(*amount), reason)
+            }
+            Event::HoldShelved { isbn, copy_id, patron, expires } => write!(
+                f,
+                "{}（副本 #{}）放上预约书架，为 {} 保留至 {}",
+                isbn::display(isbn),
+                copy_id,
+                patron,
+                expires
+            ),
+            Event::HoldExpired { isbn, patron } => write!(f, "{} 对 {} 的预约超过取书期限", patron, isbn::display(isbn)),
+        }
+    }
+}
+
+// 审计日志中的一条记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub seq: u64, // 序号，从 1 开始递增
+    pub at: NaiveDateTime, // 按图书馆时钟记录的时间
+    pub actor: String, // 执行操作的人，例如馆员的工号
+    pub event: Event,
+}
+
+// 只追加的审计日志。打开文件时每条记录写为一行 JSON，并在追加后同步到磁盘
+#[derive(Debug, Default)]
+pub struct AuditLog {
+    path: Option<PathBuf>,
+    records: Vec<AuditRecord>,
+    actor: Option<String>,
+}
+
+impl AuditLog {
+    // 只保存在内存中的日志
+    pub fn in_memory() -> Self {
+        AuditLog::default()
+    }
+
+    // 打开日志文件并读入已有的记录，文件不存在时从空日志开始
+    pub fn open(path: impl Into<PathBuf>) -> LibraryResult<Self> {
+        let path = path.into();
+        let mut records = Vec::new();
+        if let Ok(file) = File::open(&path) {
+            let lines = BufReader::new(file).lines().collect::<Result<Vec<_>, _>>()?;
+            let last = lines.len().saturating_sub(1);
+            let mut valid_len = 0;
+            for (index, line) in lines.iter().enumerate() {
+                match serde_json::from_str(line) {
+                    Ok(record) => records.push(record),
+                    _ if line.trim().is_empty() => {}
+                    // 最后一行不完整说明上次追加时被中断，截掉它，以免之后的追加接在残行后面
+                    Err(_) if index == last => {
+                        OpenOptions::new().write(true).open(&path)?.set_len(valid_len)?;
+                        break;
+                    }
+                    Err(e) => {
+                        return Err(LibraryError::SerdeError(format!("审计日志第 {} 行: {}", index + 1, e)))
+                    }
+                }
+                valid_len += line.len() as u64 + 1;
+            }
+        }
+        Ok(AuditLog { path: Some(path), records, actor: None })
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    // 之后的记录都以该操作者的名义写入
+    pub fn set_actor(&mut self, actor: impl Into<String>) {
+        self.actor = Some(actor.into());
+    }
+
+    pub fn actor(&self) -> &str {
+        self.actor.as_deref().unwrap_or(DEFAULT_ACTOR)
+    }
+
+    // 追加一条记录，返回写入的记录
+    pub fn append(&mut self, at: NaiveDateTime, event: Event) -> LibraryResult<&AuditRecord> {
+        self.append_all(vec![(at, event)])?;
+        Ok(self.records.last().unwrap())
+    }
+
+    // 追加同一次修改产生的多条记录及其发生时间，一次写入文件；写入成功后才加入内存中的记录
+    fn append_all(&mut self, events: Vec<(NaiveDateTime, Event)>) -> LibraryResult<()> {
+        let first = self.records.last().map_or(0, |record| record.seq) + 1;
+        let actor = self.actor().to_string();
+        let records: Vec<AuditRecord> = (first..)
+            .zip(events)
+            .map(|(seq, (at, event))| AuditRecord { seq, at, actor: actor.clone(), event })
+            .collect();
+        if records.is_empty() {
+            return Ok(());
+        }
+        if let Some(path) = &self.path {
+            let mut lines = Vec::new();
+            for record in &records {
+                serde_json::to_writer(&mut lines, record)?;
+                lines.push(b'\n');
+            }
+            let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+            file.write_all(&lines)?;
+            file.sync_data()?;
+        }
+        self.records.extend(records);
+        Ok(())
+    }
+
+    // 所有记录，按写入顺序
+    pub fn records(&self) -> &[AuditRecord] {
+        &self.records
+    }
+
+    // 某本书的历史，接受任意写法的 ISBN
+    pub fn for_isbn(&self, isbn: &str) -> Vec<&AuditRecord> {
+        let isbn = isbn::normalize(isbn);
+        self.records.iter().filter(|record| record.event.involves_isbn(&isbn)).collect()
+    }
+
+    // 某位读者的历史
+    pub fn for_patron(&self, patron: &str) -> Vec<&AuditRecord> {
+        self.records.iter().filter(|record| record.event.patron() == Some(patron)).collect()
+    }
+
+    // 在给定的图书馆上按顺序重做所有记录，每条记录按其发生时间执行，
+    // 因此应还日期和罚款与当初一致。借阅规则等设置不在日志中，
+    // 需要由传入的图书馆提供；通常传入 Library::new()
+    pub fn replay(&self, mut library: Library) -> LibraryResult<Library> {
+        let clock = std::mem::take(&mut library.clock);
+        let audit = std::mem::take(&mut library.audit);
+        let subscribers = std::mem::take(&mut library.subscribers);
+        let replay_clock = Arc::new(ManualClock::new(NaiveDateTime::default()));
+        library.set_clock(replay_clock.clone());
+        for record in &self.records {
+            replay_clock.set(record.at);
+            record.event.apply(&mut library)?;
+        }
+        library.clock = clock;
+        library.audit = audit;
+        library.subscribers = subscribers;
+        Ok(library)
+    }
+}
+
+// 图书馆持有的审计日志句柄。日志属于运行时配置，不参与序列化和比较；
+// 克隆出的图书馆共用同一份日志
+#[derive(Clone, Default)]
+pub(crate) struct AuditHandle(Option<Arc<Mutex<AuditLog>>>);
+
+impl AuditHandle {
+    pub(crate) fn new(log: Arc<Mutex<AuditLog>>) -> Self {
+        AuditHandle(Some(log))
+    }
+
+    pub(crate) fn log(&self) -> Option<&Arc<Mutex<AuditLog>>> {
+        self.0.as_ref()
+    }
+
+    // 写入需要记录的事件，没有设置日志时什么也不做
+    pub(crate) fn record(&self, events: &[(NaiveDateTime, Event)]) -> LibraryResult<()> {
+        if let Some(log) = &self.0 {
+            let events = events.iter().filter(|(_, event)| event.is_logged()).cloned().collect();
+            log.lock().unwrap_or_else(|e| e.into_inner()).append_all(events)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for AuditHandle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = if self.0.is_some() { "attached" } else { "none" };
+        write!(f, "AuditHandle({})", state)
+    }
+}
+
+impl PartialEq for AuditHandle {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use crate::{store_contract, Clock, LibraryError, MembershipTier};
+
+    fn start() -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(10, 0, 0).unwrap()
+    }
+
+    // 带审计日志和手动时钟的图书馆
+    fn audited_library() -> (Library, Arc<Mutex<AuditLog>>, Arc<ManualClock>) {
+        let clock = Arc::new(ManualClock::new(start()));
+        let mut library = Library::with_clock(clock.clone());
+        let log = Arc::new(Mutex::new(AuditLog::in_memory()));
+        library.set_audit_log(log.clone());
+        (library, log, clock)
+    }
+
+    fn hobbit() -> Book {
+        Book::new("The Hobbit".to_string(), "J.R.R. Tolkien".to_string(), "978-0-618-64015-7".to_string())
+    }
+
+    #[test]
+    fn test_mutations_are_recorded_with_time_and_actor() {
+        let (mut library, log, clock) = audited_library();
+        library.add_book(hobbit()).unwrap();
+        log.lock().unwrap().set_actor("librarian-7");
+        store_contract::register(&mut library, "Alice", MembershipTier::Standard);
+        clock.advance(Duration::hours(2));
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        // 失败的操作不会记录
+        assert!(library.borrow_book("9780618640157", "Alice").is_err());
+        assert!(library.add_book(hobbit()).is_err());
+        library.return_book("9780618640157", "Alice").unwrap();
+
+        let log = log.lock().unwrap();
+        let records = log.records();
+        assert_eq!(records.len(), 4);
+        assert_eq!(records.iter().map(|r| r.seq).collect::<Vec<_>>(), vec![1, 2, 3, 4]);
+        assert_eq!(records[0].actor, DEFAULT_ACTOR);
+        assert_eq!(records[2].actor, "librarian-7");
+        assert_eq!(records[2].at, start() + Duration::hours(2));
+        assert_eq!(
+            records[2].event,
+            Event::Borrowed {
+                isbn: "9780618640157".to_string(),
+                patron: "Alice".to_string(),
+                copy_id: 1,
+                loan_id: 1,
+                due: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+            }
+        );
+        assert_eq!(records[2].event.to_string(), "Alice 借阅 978-0-618-64015-7（副本 #1），应还日期 2024-01-31");
+    }
+
+    #[test]
+    fn test_query_by_isbn_and_patron() {
+        let (mut library, log, _) = audited_library();
+        library.add_book(hobbit()).unwrap();
+        store_contract::register(&mut library, "Alice", MembershipTier::Standard);
+        store_contract::register(&mut library, "Bob", MembershipTier::Standard);
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        library.place_hold("9780618640157", "Bob").unwrap();
+        library.return_book("9780618640157", "Alice").unwrap();
+        let update = BookUpdate { isbn: Some("9787111213826".to_string()), ..BookUpdate::default() };
+        library.update_book("9780618640157", update).unwrap();
+
+        let log = log.lock().unwrap();
+        let seqs = |records: Vec<&AuditRecord>| records.iter().map(|r| r.seq).collect::<Vec<_>>();
+        // 任意写法的 ISBN 都能查到，更正 ISBN 的记录在新旧 ISBN 下都能查到
+        assert_eq!(seqs(log.for_isbn("0-618-64015-0")), vec![1, 4, 5, 6, 7]);
+        assert_eq!(seqs(log.for_isbn("9787111213826")), vec![7]);
+        assert_eq!(seqs(log.for_patron("Alice")), vec![2, 4, 6]);
+        assert_eq!(seqs(log.for_patron("Bob")), vec![3, 5]);
+        assert!(log.for_patron("Carol").is_empty());
+    }
+
+    #[test]
+    fn test_replay_rebuilds_library() {
+        let (mut library, log, clock) = audited_library();
+        let mut book = hobbit();
+        book.category = Some("小说".to_string());
+        library.add_book(book).unwrap();
+        library
+            .add_book(Book::new("Pride and Prejudice".to_string(), "Jane Austen".to_string(), "9780141439518".to_string()))
+            .unwrap();
+        library.add_copy("9780618640157", "B-1".to_string(), Condition::New).unwrap();
+        for id in ["Alice", "Bob", "Carol"] {
+            store_contract::register(&mut library, id, MembershipTier::Standard);
+        }
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        library.borrow_book("9780618640157", "Bob").unwrap();
+        library.place_hold("9780618640157", "Carol").unwrap();
+        clock.advance(Duration::days(20));
+        library.renew_book("9780618640157", "Bob").unwrap();
+        clock.advance(Duration::days(25));
+        library.return_book("9780618640157", "Alice").unwrap();
+        library.borrow_book("9780618640157", "Carol").unwrap();
+        library.pay_fine("Alice", 50).unwrap();
+        library.waive_fine("Alice", 25, "首次逾期").unwrap();
+        library.set_patron_status("Bob", PatronStatus::Suspended).unwrap();
+        library.renew_membership("Carol", NaiveDate::from_ymd_opt(2100, 1, 1).unwrap()).unwrap();
+        let update = BookUpdate {
+            title: Some("The Hobbit, or There and Back Again".to_string()),
+            isbn: Some("9787111213826".to_string()),
+            ..BookUpdate::default()
+        };
+        library.update_book("9780618640157", update).unwrap();
+        library.remove_book("9780141439518").unwrap();
+
+        let rebuilt = log.lock().unwrap().replay(Library::new()).unwrap();
+        assert_eq!(rebuilt, library);
+        assert_eq!(rebuilt.balance("Alice"), library.balance("Alice"));
+        // 重放不会写入日志，也不会改变传入图书馆的时钟
+        assert_eq!(log.lock().unwrap().records().len(), 18);
+        assert!(rebuilt.audit_log().is_none());
+        assert_ne!(rebuilt.today(), clock.now().date());
+    }
+
+    #[test]
+    fn test_replay_reproduces_expired_holds() {
+        let (mut library, log, clock) = audited_library();
+        library.add_book(hobbit()).unwrap();
+        for id in ["Alice", "Bob", "Carol"] {
+            store_contract::register(&mut library, id, MembershipTier::Standard);
+        }
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        library.place_hold("9780618640157", "Bob").unwrap();
+        library.place_hold("9780618640157", "Carol").unwrap();
+        clock.advance(Duration::days(3));
+        library.return_book("9780618640157", "Alice").unwrap();
+        clock.advance(Duration::days(10));
+        // 显式清理时 Bob 的预约过期，副本转给 Carol，保留期限从清理当天算起
+        let expired = library.expire_holds().unwrap();
+        assert_eq!(expired, vec![("9780618640157".to_string(), "Bob".to_string())]);
+        clock.advance(Duration::days(2));
+
+        let rebuilt = log.lock().unwrap().replay(Library::new()).unwrap();
+        assert_eq!(rebuilt, library);
+        let copy = &rebuilt.find_book_by_isbn("9780618640157").unwrap().copies[0];
+        let shelved = copy.on_hold_for.as_ref().unwrap();
+        assert_eq!(shelved.patron, "Carol");
+        assert_eq!(shelved.expires, (start() + Duration::days(13 + 7)).date());
+    }
+
+    #[test]
+    fn test_failed_write_rolls_back_mutation() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("library.json.audit");
+        let mut library = Library::with_clock(Arc::new(ManualClock::new(start())));
+        let log = Arc::new(Mutex::new(AuditLog::open(&path).unwrap()));
+        library.set_audit_log(log.clone());
+        library.add_book(hobbit()).unwrap();
+        store_contract::register(&mut library, "Alice", MembershipTier::Standard);
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        let notified = Arc::new(Mutex::new(0));
+        let counter = notified.clone();
+        library.subscribe(Arc::new(move |_: &Library, _: &Event| *counter.lock().unwrap() += 1));
+
+        // 日志文件换成目录后，之后的追加都会失败
+        std::fs::remove_file(&path).unwrap();
+        std::fs::create_dir(&path).unwrap();
+        let before = library.clone();
+        assert!(matches!(library.return_book("9780618640157", "Alice"), Err(LibraryError::IoError(_))));
+        assert!(matches!(library.renew_book("9780618640157", "Alice"), Err(LibraryError::IoError(_))));
+        let book = Book::new("Pride and Prejudice".to_string(), "Jane Austen".to_string(), "9780141439518".to_string());
+        assert!(matches!(library.add_book(book), Err(LibraryError::IoError(_))));
+
+        // 修改都已撤销，日志和订阅者也没有收到任何事件
+        assert_eq!(library, before);
+        assert_eq!(library.loans()[0].returned, None);
+        assert!(library.find_book_by_isbn("9780141439518").is_err());
+        assert_eq!(log.lock().unwrap().records().len(), 3);
+        assert_eq!(*notified.lock().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_replay_reports_inconsistent_log() {
+        let mut log = AuditLog::in_memory();
+        let event = Event::Borrowed {
+            isbn: "9780618640157".to_string(),
+            patron: "Alice".to_string(),
+            copy_id: 1,
+            loan_id: 1,
+            due: start().date(),
+        };
+        log.append(start(), event).unwrap();
+        assert_eq!(log.replay(Library::new()), Err(LibraryError::PatronNotFound));
+    }
+
+    #[test]
+    fn test_log_file_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("library.json.audit");
+        let mut log = AuditLog::open(&path).unwrap();
+        assert!(log.records().is_empty());
+        log.set_actor("alice");
+        log.append(start(), Event::BookAdded { book: hobbit() }).unwrap();
+        log.append(start(), Event::BookRemoved { isbn: "9780618640157".to_string() }).unwrap();
+
+        // 模拟追加到一半时崩溃
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(br#"{"seq":3,"at":"2024-"#).unwrap();
+        drop(file);
+
+        let mut log = AuditLog::open(&path).unwrap();
+        assert_eq!(log.records().len(), 2);
+        assert_eq!(log.records()[0].actor, "alice");
+        assert_eq!(log.append(start(), Event::BookAdded { book: hobbit() }).unwrap().seq, 3);
+        assert_eq!(AuditLog::open(&path).unwrap().records().len(), 3);
+
+        std::fs::write(&path, "not json\n{}\n").unwrap();
+        assert!(matches!(AuditLog::open(&path), Err(LibraryError::SerdeError(_))));
+    }
+}
@@ -0,0 +1,72 @@
+use book_management::persistence::{self, StorageKind, LIBRARY_DATA_FILE};
+use book_management::server::Server;
+use book_management::{AuditLog, LibraryError, SharedLibrary, StdoutSubscriber};
+use std::env;
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+
+const USAGE: &str = "用法: book_server [数据文件] [--addr 地址:端口]\n默认数据文件为 library_data.json，默认地址为 127.0.0.1:8080";
+
+fn main() -> ExitCode {
+    let mut catalog = PathBuf::from(LIBRARY_DATA_FILE);
+    let mut addr = "127.0.0.1:8080".to_string();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--addr" => match args.next() {
+                Some(value) => addr = value,
+                None => return usage("--addr 需要一个值"),
+            },
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return ExitCode::SUCCESS;
+            }
+            _ if arg.starts_with("--") => return usage(&format!("未知选项: {}", arg)),
+            _ => catalog = PathBuf::from(arg),
+        }
+    }
+
+    // 数据文件损坏时使用从备份恢复的数据
+    let mut storage = StorageKind::from_path(&catalog).open(&catalog);
+    let mut library = match storage.load() {
+        Ok(library) => library,
+        Err(LibraryError::RecoveredFromBackup { backup, cause, .. }) => {
+            eprintln!("数据文件损坏（{}），已从备份 {} 恢复。", cause, backup.display());
+            match storage.load() {
+                Ok(library) => library,
+                Err(e) => return fail(&e.to_string()),
+            }
+        }
+        Err(e) => return fail(&e.to_string()),
+    };
+
+    // 修改历史记录在数据文件旁的审计日志中
+    match AuditLog::open(persistence::audit_log_path(&catalog)) {
+        Ok(audit) => library.set_audit_log(Arc::new(Mutex::new(audit))),
+        Err(e) => return fail(&e.to_string()),
+    }
+    // 借还等事件打印到标准输出，作为服务日志
+    library.subscribe(Arc::new(StdoutSubscriber));
+
+    let server = match Server::bind(addr.as_str(), SharedLibrary::new(library)) {
+        Ok(server) => server.with_storage(storage),
+        Err(e) => return fail(&e.to_string()),
+    };
+    match server.local_addr() {
+        Some(local) => eprintln!("正在监听 http://{}，数据文件 {}", local, catalog.display()),
+        None => eprintln!("正在监听 {}，数据文件 {}", addr, catalog.display()),
+    }
+    server.run();
+    ExitCode::SUCCESS
+}
+
+fn usage(message: &str) -> ExitCode {
+    eprintln!("{}\n\n{}", message, USAGE);
+    ExitCode::from(2)
+}
+
+fn fail(message: &str) -> ExitCode {
+    eprintln!("错误: {}", message);
+    ExitCode::FAILURE
+}
@@ -0,0 +1,848 @@
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use chrono::{Duration, NaiveDate};
+use serde_json::{json, Value};
+use crate::interchange::{self, CatalogFormat, ImportReport};
+use crate::persistence::{PersistentLibrary, LIBRARY_DATA_FILE};
+use crate::report::{self, ReportFormat, ReportKind, ReportOptions};
+use crate::{isbn, metadata, Book, BookFilter, Facet, Isbn, BookStore, BookUpdate, LibraryError, LibraryResult, Loan, MembershipTier, Metadata, Patron, DEFAULT_FUZZY_THRESHOLD};
+
+pub const USAGE: &str = "\
+用法: book_management [--json] [--actor <操作者>] [<数据文件> [<命令> [参数...]]]
+不带命令时进入交互模式，数据文件默认为 library_data.json。
+每次修改都以操作者的名义记录在数据文件旁的 .audit 审计日志中。
+
+命令:
+  add <isbn> <标题> <作者> [--category <分类>] [<书目选项>...]   添加书籍
+  remove <isbn>                                  移除书籍
+  update <isbn> [--title <标题>] [--author <作者>] [--category <分类>] [--isbn <新 ISBN>]   修改书目信息
+  find <isbn> | find --author <作者>             查找书籍
+  search <关键词...> [--fuzzy]                   全文检索，--fuzzy 容忍拼写错误
+  borrow <isbn> <读者证号>                       借阅
+  return <isbn> <读者证号>                       归还
+  list [<筛选选项>...]                           列出书籍，可按书目信息筛选
+  browse category|subject|language|publisher|year|class   按分类、主题等分组浏览
+  patrons                                        列出所有读者
+  patrons add <读者证号> <姓名> [--contact <联系方式>] [--tier basic|standard|premium] [--expires <YYYY-MM-DD>]
+  loans [<读者证号>] [--overdue] [--all]         在借记录，--all 包括已归还的
+  import <文件> [--format json|csv|marc] [--dry-run]   导入书籍，有问题的记录跳过并逐条报告，
+                                                 --dry-run 只校验不导入；格式默认按扩展名判断
+  export <文件> [--format json|csv|marc]         导出所有书籍
+  history <isbn|读者证号>                        查看审计日志中的修改历史
+  report most-borrowed|utilization|top-patrons|overdue|monthly [--format text|csv|json]
+         [--from <YYYY-MM-DD>] [--to <YYYY-MM-DD>] [--limit <行数>]   流通报表
+  help                                           显示本帮助
+  quit                                           退出交互模式
+
+书目选项（--co-author 和 --subject 可重复）:
+  --co-author <作者> --publisher <出版者> --year <出版年> --edition <版次>
+  --language <语言> --subject <主题> --class <分类号，如 ddc:823.912 或 clc:I247.5>
+筛选选项:
+  --author <作者> --publisher <出版者> --year <年份|起始..结束> --edition <版次>
+  --language <语言> --subject <主题> --category <分类> --class <分类号前缀>";
+
+// 输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    #[default]
+    Text,
+    Json, // 供脚本使用的 JSON
+}
+
+// 一条命令
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Add { isbn: String, title: String, author: String, category: Option<String>, metadata: Metadata },
+    Remove { isbn: String },
+    Update { isbn: String, update: BookUpdate },
+    FindIsbn { isbn: String },
+    FindAuthor { author: String },
+    Search { query: String, fuzzy: bool },
+    Borrow { isbn: String, patron: String },
+    Return { isbn: String, patron: String },
+    List { filter: BookFilter },
+    Browse { facet: Facet },
+    Patrons,
+    AddPatron { id: String, name: String, contact: String, tier: MembershipTier, expires: Option<NaiveDate> },
+    Loans { patron: Option<String>, overdue: bool, all: bool },
+    Import { path: PathBuf, format: CatalogFormat, dry_run: bool },
+    Export { path: PathBuf, format: CatalogFormat },
+    History { key: String }, // ISBN 或读者证号
+    Report { kind: ReportKind, format: ReportFormat, options: ReportOptions },
+    Help,
+}
+
+// 一次命令行调用：数据文件、输出格式和要执行的命令（没有命令时进入交互模式）
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invocation {
+    pub catalog: PathBuf,
+    pub format: OutputFormat,
+    pub actor: Option<String>, // 写入审计日志的操作者
+    pub command: Option<Command>,
+}
+
+// 解析进程参数（不含程序名）
+pub fn parse_invocation(args: &[String]) -> Result<Invocation, String> {
+    let mut args = args.to_vec();
+    let format = if take_flag(&mut args, "--json") { OutputFormat::Json } else { OutputFormat::Text };
+    let actor = take_option(&mut args, "--actor")?;
+    if args.is_empty() {
+        return Ok(Invocation { catalog: PathBuf::from(LIBRARY_DATA_FILE), format, actor, command: None });
+    }
+    let catalog = PathBuf::from(args.remove(0));
+    let command = if args.is_empty() { None } else { Some(parse_command(&args)?) };
+    Ok(Invocation { catalog, format, actor, command })
+}
+
+// 解析一条命令，例如 ["borrow", "9780618640157", "Alice"]
+pub fn parse_command(args: &[String]) -> Result<Command, String> {
+    let (name, rest) = args.split_first().ok_or("缺少命令")?;
+    let mut rest = rest.to_vec();
+    let command = match name.as_str() {
+        "add" => {
+            let category = take_option(&mut rest, "--category")?;
+            let metadata = take_metadata(&mut rest)?;
+            let [isbn, title, author] = positional(rest, "add <isbn> <标题> <作者>")?;
+            Command::Add { isbn, title, author, category, metadata }
+        }
+        "remove" => {
+            let [isbn] = positional(rest, "remove <isbn>")?;
+            Command::Remove { isbn }
+        }
+        "update" => {
+            let update = BookUpdate {
+                title: take_option(&mut rest, "--title")?,
+                author: take_option(&mut rest, "--author")?,
+                category: take_option(&mut rest, "--category")?.map(|c| Some(c).filter(|c| !c.is_empty())),
+                isbn: take_option(&mut rest, "--isbn")?,
+                metadata: None,
+            };
+            let [isbn] = positional(rest, "update <isbn> [--title <标题>] [--author <作者>] [--category <分类>] [--isbn <新 ISBN>]")?;
+            Command::Update { isbn, update }
+        }
+        "find" => match take_option(&mut rest, "--author")? {
+            Some(author) => {
+                let [] = positional(rest, "find --author <作者>")?;
+                Command::FindAuthor { author }
+            }
+            None => {
+                let [isbn] = positional(rest, "find <isbn>")?;
+                Command::FindIsbn { isbn }
+            }
+        },
+        "search" => {
+            let fuzzy = take_flag(&mut rest, "--fuzzy");
+            if rest.is_empty() {
+                return Err("用法: search <关键词...> [--fuzzy]".to_string());
+            }
+            Command::Search { query: rest.join(" "), fuzzy }
+        }
+        "borrow" => {
+            let [isbn, patron] = positional(rest, "borrow <isbn> <读者证号>")?;
+            Command::Borrow { isbn, patron }
+        }
+        "return" => {
+            let [isbn, patron] = positional(rest, "return <isbn> <读者证号>")?;
+            Command::Return { isbn, patron }
+        }
+        "list" => {
+            let filter = take_filter(&mut rest)?;
+            let [] = positional(rest, "list [<筛选选项>...]")?;
+            Command::List { filter }
+        }
+        "browse" => {
+            let [facet] = positional(rest, "browse category|subject|language|publisher|year|class")?;
+            Command::Browse { facet: facet.parse().map_err(|_| format!("未知的浏览方式: {}", facet))? }
+        }
+        "patrons" if rest.first().map(String::as_str) == Some("add") => {
+            rest.remove(0);
+            let contact = take_option(&mut rest, "--contact")?.unwrap_or_default();
+            let tier = match take_option(&mut rest, "--tier")? {
+                Some(tier) => tier.parse()?,
+                None => MembershipTier::Standard,
+            };
+            let expires = take_option(&mut rest, "--expires")?.map(|date| parse_date(&date)).transpose()?;
+            let [id, name] = positional(rest, "patrons add <读者证号> <姓名>")?;
+            Command::AddPatron { id, name, contact, tier, expires }
+        }
+        "patrons" => {
+            let [] = positional(rest, "patrons")?;
+            Command::Patrons
+        }
+        "loans" => {
+            let overdue = take_flag(&mut rest, "--overdue");
+            let all = take_flag(&mut rest, "--all");
+            if rest.len() > 1 {
+                return Err("用法: loans [<读者证号>] [--overdue] [--all]".to_string());
+            }
+            Command::Loans { patron: rest.pop(), overdue, all }
+        }
+        "import" => {
+            let dry_run = take_flag(&mut rest, "--dry-run");
+            let format = take_option(&mut rest, "--format")?;
+            let [path] = positional(rest, "import <文件> [--format json|csv|marc] [--dry-run]")?;
+            let path = PathBuf::from(path);
+            Command::Import { format: catalog_format(format, &path)?, path, dry_run }
+        }
+        "export" => {
+            let format = take_option(&mut rest, "--format")?;
+            let [path] = positional(rest, "export <文件> [--format json|csv|marc]")?;
+            let path = PathBuf::from(path);
+            Command::Export { format: catalog_format(format, &path)?, path }
+        }
+        "history" => {
+            let [key] = positional(rest, "history <isbn|读者证号>")?;
+            Command::History { key }
+        }
+        "report" => {
+            let format = match take_option(&mut rest, "--format")? {
+                Some(format) => format.parse().map_err(|e: LibraryError| e.to_string())?,
+                None => ReportFormat::Text,
+            };
+            let options = ReportOptions {
+                from: take_option(&mut rest, "--from")?.map(|date| parse_date(&date)).transpose()?,
+                to: take_option(&mut rest, "--to")?.map(|date| parse_date(&date)).transpose()?,
+                limit: match take_option(&mut rest, "--limit")? {
+                    Some(limit) => Some(limit.parse().map_err(|_| format!("无效的行数: {}", limit))?),
+                    None => None,
+                },
+            };
+            let [kind] = positional(rest, "report most-borrowed|utilization|top-patrons|overdue|monthly")?;
+            let kind = kind.parse().map_err(|_| format!("未知的报表: {}", kind))?;
+            Command::Report { kind, format, options }
+        }
+        "help" => Command::Help,
+        other => return Err(format!("未知的命令: {}", other)),
+    };
+    Ok(command)
+}
+
+// 显式指定的格式优先，否则按扩展名判断
+fn catalog_format(format: Option<String>, path: &Path) -> Result<CatalogFormat, String> {
+    match format {
+        Some(format) => format.parse().map_err(|e: LibraryError| e.to_string()),
+        None => Ok(CatalogFormat::from_path(path)),
+    }
+}
+
+// 取出形如 --name 的开关
+fn take_flag(args: &mut Vec<String>, name: &str) -> bool {
+    let before = args.len();
+    args.retain(|arg| arg != name);
+    args.len() != before
+}
+
+// 取出形如 --name value 的选项
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    match args.iter().position(|arg| arg == name) {
+        Some(i) if i + 1 < args.len() => {
+            let value = args.remove(i + 1);
+            args.remove(i);
+            Ok(Some(value))
+        }
+        Some(_) => Err(format!("选项 {} 缺少参数", name)),
+        None => Ok(None),
+    }
+}
+
+// 取出可以重复出现的选项，例如多个 --subject
+fn take_options(args: &mut Vec<String>, name: &str) -> Result<Vec<String>, String> {
+    let mut values = Vec::new();
+    while let Some(value) = take_option(args, name)? {
+        values.push(value);
+    }
+    Ok(values)
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| format!("无效的日期: {}", date))
+}
+
+fn parse_year(year: &str) -> Result<i32, String> {
+    year.trim().parse().map_err(|_| format!("无效的年份: {}", year))
+}
+
+// 添加书籍时的书目选项
+fn take_metadata(args: &mut Vec<String>) -> Result<Metadata, String> {
+    Ok(Metadata {
+        co_authors: take_options(args, "--co-author")?,
+        publisher: take_option(args, "--publisher")?,
+        year: take_option(args, "--year")?.map(|year| parse_year(&year)).transpose()?,
+        edition: take_option(args, "--edition")?,
+        language: take_option(args, "--language")?,
+        subjects: take_options(args, "--subject")?,
+        classification: match take_option(args, "--class")? {
+            Some(class) => Some(class.parse().map_err(|e: LibraryError| e.to_string())?),
+            None => None,
+        },
+    })
+}
+
+// list 的筛选选项，--year 可以是单个年份或 起始..结束，两端都可以省略
+fn take_filter(args: &mut Vec<String>) -> Result<BookFilter, String> {
+    let (year_from, year_to) = match take_option(args, "--year")? {
+        Some(range) => match range.split_once("..") {
+            Some((from, to)) => {
+                let bound = |year: &str| if year.is_empty() { Ok(None) } else { parse_year(year).map(Some) };
+                (bound(from)?, bound(to)?)
+            }
+            None => {
+                let year = parse_year(&range)?;
+                (Some(year), Some(year))
+            }
+        },
+        None => (None, None),
+    };
+    Ok(BookFilter {
+        author: take_option(args, "--author")?,
+        publisher: take_option(args, "--publisher")?,
+        year_from,
+        year_to,
+        edition: take_option(args, "--edition")?,
+        language: take_option(args, "--language")?,
+        subject: take_option(args, "--subject")?,
+        category: take_option(args, "--category")?,
+        classification: take_option(args, "--class")?,
+    })
+}
+
+// 检查位置参数的个数
+fn positional<const N: usize>(args: Vec<String>, usage: &str) -> Result<[String; N], String> {
+    args.try_into().map_err(|_| format!("用法: {}", usage))
+}
+
+// 将交互模式输入的一行拆分为参数，支持用双引号包含空格
+pub fn split_line(line: &str) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    let mut current = String::new();
+    let mut in_quotes = false;
+    let mut has_arg = false;
+    for c in line.chars() {
+        match c {
+            '"' => {
+                in_quotes = !in_quotes;
+                has_arg = true;
+            }
+            c if c.is_whitespace() && !in_quotes => {
+                if has_arg {
+                    args.push(std::mem::take(&mut current));
+                    has_arg = false;
+                }
+            }
+            c => {
+                current.push(c);
+                has_arg = true;
+            }
+        }
+    }
+    if in_quotes {
+        return Err("引号不匹配".to_string());
+    }
+    if has_arg {
+        args.push(current);
+    }
+    Ok(args)
+}
+
+// 命令的执行结果，同时带有给人看的文本和给脚本用的 JSON
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reply {
+    pub text: String,
+    pub data: Value,
+}
+
+impl Reply {
+    fn message(text: String) -> Self {
+        let data = json!({ "message": text });
+        Reply { text, data }
+    }
+
+    pub fn render(&self, format: OutputFormat) -> String {
+        match format {
+            OutputFormat::Text => self.text.clone(),
+            OutputFormat::Json => self.data.to_string(),
+        }
+    }
+}
+
+fn book_line(book: &Book) -> String {
+    format!(
+        "- {}，作者：{}，ISBN：{}（可借 {}/{}）",
+        book.title,
+        book.author,
+        isbn::display(&book.isbn),
+        book.available_copies(),
+        book.total_copies()
+    )
+}
+
+fn loan_line(loan: &Loan) -> String {
+    let state = match loan.returned {
+        Some(returned) => format!("已于 {} 归还", returned.date()),
+        None => format!("应还日期 {}", loan.due),
+    };
+    format!("- #{} ISBN：{}，借阅者：{}，{}", loan.id, isbn::display(&loan.isbn), loan.borrower, state)
+}
+
+fn books_reply(mut books: Vec<Book>, empty: &str) -> Reply {
+    books.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.isbn.cmp(&b.isbn)));
+    let text = if books.is_empty() {
+        empty.to_string()
+    } else {
+        books.iter().map(book_line).collect::<Vec<_>>().join("\n")
+    };
+    Reply { text, data: json!(books) }
+}
+
+fn loans_reply(loans: Vec<&Loan>) -> Reply {
+    let text = if loans.is_empty() {
+        "没有借阅记录。".to_string()
+    } else {
+        loans.iter().map(|loan| loan_line(loan)).collect::<Vec<_>>().join("\n")
+    };
+    Reply { text, data: json!(loans) }
+}
+
+fn import_reply(report: &ImportReport, dry_run: bool) -> Reply {
+    let mut lines = vec![if dry_run {
+        format!("试运行：可导入 {} 本书籍，{} 条记录有问题。", report.imported, report.errors.len())
+    } else {
+        format!("已导入 {} 本书籍，跳过 {} 条记录。", report.imported, report.errors.len())
+    }];
+    for error in &report.errors {
+        match &error.isbn {
+            Some(isbn) => lines.push(format!("- 第 {} 行（{}）：{}", error.line, isbn, error.error)),
+            None => lines.push(format!("- 第 {} 行：{}", error.line, error.error)),
+        }
+    }
+    let errors: Vec<Value> = report
+        .errors
+        .iter()
+        .map(|e| json!({ "line": e.line, "isbn": e.isbn, "error": e.error.to_string() }))
+        .collect();
+    Reply {
+        text: lines.join("\n"),
+        data: json!({ "dry_run": dry_run, "imported": report.imported, "errors": errors }),
+    }
+}
+
+// 对绑定了存储的图书馆执行一条命令，修改会立即写回数据文件
+pub fn execute(store: &mut PersistentLibrary, command: Command) -> LibraryResult<Reply> {
+    let reply = match command {
+        Command::Add { isbn, title, author, category, metadata } => {
+            let mut book = Book::new(title, author, isbn);
+            book.category = category;
+            book.metadata = metadata;
+            store.add_book(book.clone())?;
+            let book = store.get_book(&book.isbn)?;
+            Reply { text: format!("已添加：{}", book_line(&book)), data: json!(book) }
+        }
+        Command::Remove { isbn } => {
+            let book = store.remove_book(&isbn)?;
+            Reply { text: format!("已移除书籍 '{}'。", book.title), data: json!(book) }
+        }
+        Command::Update { isbn, update } => {
+            let new_isbn = update.isbn.clone().unwrap_or_else(|| isbn.clone());
+            store.update_book(&isbn, update)?;
+            let book = store.get_book(&new_isbn)?;
+            Reply { text: format!("已更新：{}", book_line(&book)), data: json!(book) }
+        }
+        Command::FindIsbn { isbn } => {
+            let book = store.get_book(&isbn)?;
+            Reply { text: book_line(&book), data: json!(book) }
+        }
+        Command::FindAuthor { author } => books_reply(store.get_books_by_author(&author)?, "没有该作者的书籍。"),
+        Command::Search { query, fuzzy } => {
+            let library = store.library();
+            let results: Vec<(&Book, f64)> = if fuzzy {
+                library
+                    .fuzzy_search(&query, DEFAULT_FUZZY_THRESHOLD)
+                    .into_iter()
+                    .map(|m| (m.book, m.score))
+                    .collect()
+            } else {
+                library.search(&query).into_iter().map(|r| (r.book, r.score)).collect()
+            };
+            let text = if results.is_empty() {
+                "没有找到匹配的书籍。".to_string()
+            } else {
+                results
+                    .iter()
+                    .map(|(book, score)| format!("{}（得分 {:.2}）", book_line(book), score))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            let data = results.iter().map(|(book, score)| json!({ "book": book, "score": score })).collect();
+            Reply { text, data: Value::Array(data) }
+        }
+        Command::Borrow { isbn, patron } => {
+            let loan = store.borrow_book(&isbn, &patron)?;
+            Reply { text: format!("借阅成功，应还日期 {}。", loan.due), data: json!(loan) }
+        }
+        Command::Return { isbn, patron } => {
+            let receipt = store.return_book(&isbn, &patron)?;
+            let mut text = format!("已归还副本 #{}。", receipt.copy_id);
+            if receipt.fine > 0 {
+                text.push_str(&format!("逾期罚款 {}。", crate::format_money(receipt.fine)));
+            }
+            if let Some(hold_for) = &receipt.hold_for {
+                text.push_str(&format!("已为 {} 放上预约书架。", hold_for));
+            }
+            let data = json!({
+                "copy_id": receipt.copy_id,
+                "loan_id": receipt.loan_id,
+                "fine": receipt.fine,
+                "hold_for": receipt.hold_for,
+            });
+            Reply { text, data }
+        }
+        Command::List { filter } if filter.is_empty() => books_reply(store.all_books()?, "图书馆目前没有书籍。"),
+        Command::List { filter } => {
+            let books = store.all_books()?.into_iter().filter(|book| filter.matches(book)).collect();
+            books_reply(books, "没有符合条件的书籍。")
+        }
+        Command::Browse { facet } => {
+            let books = store.all_books()?;
+            let groups = metadata::browse(&books, facet);
+            let text = if groups.is_empty() {
+                "图书馆目前没有书籍。".to_string()
+            } else {
+                groups
+                    .iter()
+                    .map(|(key, books)| {
+                        let lines: Vec<String> = books.iter().map(|book| format!("  {}", book_line(book))).collect();
+                        format!("{}（{} 本）\n{}", key, books.len(), lines.join("\n"))
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            Reply { text, data: json!(groups) }
+        }
+        Command::Patrons => {
+            let patrons = store.library().patrons();
+            let text = if patrons.is_empty() {
+                "没有登记的读者。".to_string()
+            } else {
+                patrons.iter().map(|patron| format!("- {}", patron)).collect::<Vec<_>>().join("\n")
+            };
+            Reply { text, data: json!(patrons) }
+        }
+        Command::AddPatron { id, name, contact, tier, expires } => {
+            let expires = expires.unwrap_or_else(|| store.library().today() + Duration::days(365));
+            let patron = Patron::new(id, name, contact, tier, expires);
+            store.update(|library| library.register_patron(patron.clone()))?;
+            Reply { text: format!("已登记读者：{}", patron), data: json!(patron) }
+        }
+        Command::Loans { patron, overdue, all } => {
+            let library = store.library();
+            let today = library.today();
+            let loans = match &patron {
+                Some(patron) => library.loans_for(patron),
+                None => library.loans(),
+            };
+            let loans = loans
+                .into_iter()
+                .filter(|loan| all || loan.is_active())
+                .filter(|loan| !overdue || loan.is_overdue(today))
+                .collect();
+            loans_reply(loans)
+        }
+        Command::Import { path, format, dry_run } => {
+            let records = interchange::read_catalog(BufReader::new(File::open(&path)?), format)?;
+            let report = if dry_run {
+                interchange::dry_run(store.library(), records)
+            } else {
+                store.update(|library| Ok(interchange::import_records(library, records)))?
+            };
+            import_reply(&report, dry_run)
+        }
+        Command::Export { path, format } => {
+            interchange::write_catalog(BufWriter::new(File::create(&path)?), store, format)?;
+            let count = store.all_books()?.len();
+            Reply::message(format!("已导出 {} 本书籍到 {}。", count, path.display()))
+        }
+        Command::History { key } => {
+            let log = match store.library().audit_log() {
+                Some(log) => log.lock().unwrap_or_else(|e| e.into_inner()),
+                None => return Ok(Reply::message("未启用审计日志。".to_string())),
+            };
+            // 能解析为 ISBN 的按书籍查询，否则按读者查询
+            let records = if Isbn::parse(&key).is_ok() { log.for_isbn(&key) } else { log.for_patron(&key) };
+            let text = if records.is_empty() {
+                "没有相关的记录。".to_string()
+            } else {
+                records
+                    .iter()
+                    .map(|record| format!("- #{} {} [{}] {}", record.seq, record.at, record.actor, record.event))
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            Reply { text, data: json!(records) }
+        }
+        Command::Report { kind, format, options } => {
+            let report = report::generate(store.library(), kind, &options);
+            Reply { text: report.render(format)?, data: json!({ "title": report.title, "rows": report.to_json() }) }
+        }
+        Command::Help => Reply { text: USAGE.to_string(), data: json!({ "usage": USAGE }) },
+    };
+    Ok(reply)
+}
+
+// 按输出格式打印错误，JSON 模式下错误同样以 JSON 输出到标准输出
+pub fn render_error(message: &str, format: OutputFormat) -> String {
+    match format {
+        OutputFormat::Text => format!("错误：{}", message),
+        OutputFormat::Json => json!({ "error": message }).to_string(),
+    }
+}
+
+// 交互模式：逐行读取命令并执行，直到输入结束或 quit
+pub fn run_repl(
+    store: &mut PersistentLibrary,
+    format: OutputFormat,
+    input: impl BufRead,
+    mut output: impl Write,
+) -> io::Result<()> {
+    if format == OutputFormat::Text {
+        writeln!(output, "输入 help 查看命令，quit 退出。")?;
+    }
+    let mut lines = input.lines();
+    loop {
+        if format == OutputFormat::Text {
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+        let Some(line) = lines.next() else { break };
+        let args = match split_line(&line?) {
+            Ok(args) => args,
+            Err(message) => {
+                writeln!(output, "{}", render_error(&message, format))?;
+                continue;
+            }
+        };
+        match args.first().map(String::as_str) {
+            None => continue,
+            Some("quit") | Some("exit") => break,
+            Some(_) => {}
+        }
+        let result = parse_command(&args).and_then(|command| execute(store, command).map_err(|e| e.to_string()));
+        match result {
+            Ok(reply) => writeln!(output, "{}", reply.render(format))?,
+            Err(message) => writeln!(output, "{}", render_error(&message, format))?,
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::StorageKind;
+    use crate::AuditLog;
+
+    fn args(line: &str) -> Vec<String> {
+        split_line(line).unwrap()
+    }
+
+    fn open_store(dir: &tempfile::TempDir) -> PersistentLibrary {
+        PersistentLibrary::open(StorageKind::Json, dir.path().join("catalog.json")).unwrap()
+    }
+
+    #[test]
+    fn test_split_line_with_quotes() {
+        assert_eq!(
+            args(r#"add 9780618640157 "The Hobbit" "J.R.R. Tolkien""#),
+            vec!["add", "9780618640157", "The Hobbit", "J.R.R. Tolkien"]
+        );
+        assert_eq!(args(r#"find --author """#), vec!["find", "--author", ""]);
+        assert!(split_line(r#"add "unterminated"#).is_err());
+    }
+
+    #[test]
+    fn test_parse_invocation() {
+        let invocation = parse_invocation(&args("--json books.json borrow 9780618640157 Alice")).unwrap();
+        assert_eq!(invocation.catalog, PathBuf::from("books.json"));
+        assert_eq!(invocation.format, OutputFormat::Json);
+        assert_eq!(
+            invocation.command,
+            Some(Command::Borrow { isbn: "9780618640157".to_string(), patron: "Alice".to_string() })
+        );
+
+        let invocation = parse_invocation(&[]).unwrap();
+        assert_eq!(invocation.catalog, PathBuf::from(LIBRARY_DATA_FILE));
+        assert_eq!(invocation.command, None);
+    }
+
+    #[test]
+    fn test_parse_command_options_and_errors() {
+        assert_eq!(
+            parse_command(&args("add 9780618640157 Hobbit Tolkien --category 小说")).unwrap(),
+            Command::Add {
+                isbn: "9780618640157".to_string(),
+                title: "Hobbit".to_string(),
+                author: "Tolkien".to_string(),
+                category: Some("小说".to_string()),
+                metadata: Metadata::default(),
+            }
+        );
+        assert_eq!(
+            parse_command(&args("add 9780618640157 Hobbit Tolkien --subject 奇幻 --year 1937 --subject 冒险 --class 823.912"))
+                .unwrap(),
+            Command::Add {
+                isbn: "9780618640157".to_string(),
+                title: "Hobbit".to_string(),
+                author: "Tolkien".to_string(),
+                category: None,
+                metadata: Metadata {
+                    year: Some(1937),
+                    subjects: vec!["奇幻".to_string(), "冒险".to_string()],
+                    classification: Some("823.912".parse().unwrap()),
+                    ..Metadata::default()
+                },
+            }
+        );
+        assert_eq!(
+            parse_command(&args("list --year 1990.. --language en")).unwrap(),
+            Command::List {
+                filter: BookFilter { year_from: Some(1990), language: Some("en".to_string()), ..BookFilter::default() }
+            }
+        );
+        assert!(parse_command(&args("list --year nineteen")).is_err());
+        assert!(parse_command(&args("browse shelf")).is_err());
+        assert_eq!(
+            parse_command(&args("loans Alice --overdue")).unwrap(),
+            Command::Loans { patron: Some("Alice".to_string()), overdue: true, all: false }
+        );
+        assert!(parse_command(&args("borrow 9780618640157")).is_err());
+        assert!(parse_command(&args("patrons add Bob Bob --tier gold")).is_err());
+        assert!(parse_command(&args("frobnicate")).is_err());
+    }
+
+    #[test]
+    fn test_commands_persist_between_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = open_store(&dir);
+        execute(&mut store, parse_command(&args("add 9780618640157 \"The Hobbit\" Tolkien")).unwrap()).unwrap();
+        execute(&mut store, parse_command(&args("patrons add Alice Alice")).unwrap()).unwrap();
+        let reply = execute(&mut store, parse_command(&args("borrow 978-0-618-64015-7 Alice")).unwrap()).unwrap();
+        assert_eq!(reply.data["borrower"], "Alice");
+
+        // 再次打开数据文件时状态仍在，重复添加会报错而不会破坏数据
+        let mut store = open_store(&dir);
+        let reply = execute(&mut store, Command::Loans { patron: None, overdue: false, all: false }).unwrap();
+        assert_eq!(reply.data.as_array().unwrap().len(), 1);
+        assert!(execute(&mut store, parse_command(&args("add 9780618640157 Hobbit Tolkien")).unwrap()).is_err());
+        assert!(execute(&mut store, Command::Remove { isbn: "9780618640157".to_string() }).is_err());
+    }
+
+    #[test]
+    fn test_list_filter_and_browse() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = open_store(&dir);
+        let run = |store: &mut PersistentLibrary, line: &str| execute(store, parse_command(&args(line)).unwrap()).unwrap();
+        run(&mut store, "add 9780618640157 \"The Hobbit\" Tolkien --language en --subject 奇幻 --year 1937");
+        run(&mut store, "add 9787111213826 指环王 托尔金 --co-author 丁棣 --language zh --subject 奇幻 --subject 史诗");
+
+        let reply = run(&mut store, "list --author 丁棣");
+        assert_eq!(reply.data[0]["title"], "指环王");
+        assert_eq!(run(&mut store, "list --year ..1950").data.as_array().unwrap().len(), 1);
+        assert_eq!(run(&mut store, "list --publisher 译林出版社").text, "没有符合条件的书籍。");
+
+        let reply = run(&mut store, "browse subject");
+        assert_eq!(reply.data["奇幻"].as_array().unwrap().len(), 2);
+        assert_eq!(reply.data["史诗"][0]["isbn"], "9787111213826");
+        assert!(reply.text.starts_with("史诗（1 本）\n  - 指环王"));
+    }
+
+    #[test]
+    fn test_report_command() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = open_store(&dir);
+        let run = |store: &mut PersistentLibrary, line: &str| execute(store, parse_command(&args(line)).unwrap()).unwrap();
+        run(&mut store, "add 9780618640157 \"The Hobbit\" Tolkien");
+        run(&mut store, "patrons add Alice Alice");
+        run(&mut store, "borrow 9780618640157 Alice");
+
+        let reply = run(&mut store, "report most-borrowed --format csv --limit 5");
+        assert_eq!(reply.text, "isbn,title,author,loans\n9780618640157,The Hobbit,Tolkien,1\n");
+        assert_eq!(reply.data["rows"][0]["loans"], 1);
+        assert_eq!(run(&mut store, "report overdue").data["rows"].as_array().unwrap().len(), 0);
+        assert!(parse_command(&args("report weekly")).is_err());
+        assert!(parse_command(&args("report monthly --from 2024-13-01")).is_err());
+    }
+
+    #[test]
+    fn test_history_from_audit_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = open_store(&dir);
+        let history = |store: &mut PersistentLibrary, key: &str| {
+            execute(store, Command::History { key: key.to_string() }).unwrap()
+        };
+        assert_eq!(history(&mut store, "Alice").text, "未启用审计日志。");
+
+        let invocation = parse_invocation(&args("--actor desk-1 catalog.json list")).unwrap();
+        assert_eq!(invocation.actor.as_deref(), Some("desk-1"));
+        let mut log = AuditLog::open(crate::persistence::audit_log_path(&dir.path().join("catalog.json"))).unwrap();
+        log.set_actor("desk-1");
+        store.set_audit_log(std::sync::Arc::new(std::sync::Mutex::new(log)));
+        execute(&mut store, parse_command(&args("add 9780618640157 Hobbit Tolkien")).unwrap()).unwrap();
+        execute(&mut store, parse_command(&args("patrons add Alice Alice")).unwrap()).unwrap();
+        execute(&mut store, parse_command(&args("borrow 9780618640157 Alice")).unwrap()).unwrap();
+
+        let reply = history(&mut store, "0-618-64015-0");
+        assert_eq!(reply.data.as_array().unwrap().len(), 2);
+        assert_eq!(reply.data[1]["actor"], "desk-1");
+        assert_eq!(reply.data[1]["event"]["type"], "borrowed");
+        assert!(reply.text.contains("Alice 借阅 978-0-618-64015-7"));
+        assert_eq!(history(&mut store, "Alice").data.as_array().unwrap().len(), 2);
+        assert_eq!(history(&mut store, "Bob").text, "没有相关的记录。");
+        assert!(parse_command(&args("history")).is_err());
+    }
+
+    #[test]
+    fn test_export_then_import() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = open_store(&dir);
+        execute(&mut store, parse_command(&args("add 9780618640157 Hobbit Tolkien")).unwrap()).unwrap();
+        let export = dir.path().join("books.json");
+        execute(&mut store, parse_command(&args(&format!("export {}", export.display()))).unwrap()).unwrap();
+
+        let mut other = PersistentLibrary::open(StorageKind::Json, dir.path().join("other.json")).unwrap();
+        execute(&mut other, parse_command(&args("add 9780141439518 Pride Austen")).unwrap()).unwrap();
+        let import = |dry_run: &str| parse_command(&args(&format!("import {} {}", export.display(), dry_run))).unwrap();
+        let reply = execute(&mut other, import("--dry-run")).unwrap();
+        assert_eq!(reply.data["imported"], 1);
+        assert_eq!(other.all_books().unwrap().len(), 1);
+        let reply = execute(&mut other, import("")).unwrap();
+        assert_eq!(reply.data["imported"], 1);
+        let reply = execute(&mut other, import("")).unwrap();
+        assert_eq!(reply.data["imported"], 0);
+        assert_eq!(reply.data["errors"][0]["isbn"], "9780618640157");
+        assert_eq!(other.all_books().unwrap().len(), 2);
+
+        // CSV 按扩展名识别
+        let csv = dir.path().join("books.csv");
+        execute(&mut other, parse_command(&args(&format!("export {}", csv.display()))).unwrap()).unwrap();
+        let mut third = PersistentLibrary::open(StorageKind::Json, dir.path().join("third.json")).unwrap();
+        let reply = execute(&mut third, parse_command(&args(&format!("import {}", csv.display()))).unwrap()).unwrap();
+        assert_eq!(reply.data["imported"], 2);
+    }
+
+    #[test]
+    fn test_repl_continues_after_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = open_store(&dir);
+        let input = "add 9780618640157 Hobbit Tolkien\nfind 0000\n\nlist\nquit\nlist\n";
+        let mut output = Vec::new();
+        run_repl(&mut store, OutputFormat::Json, input.as_bytes(), &mut output).unwrap();
+        let lines: Vec<Value> = String::from_utf8(output)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[1]["error"].is_string());
+        assert_eq!(lines[2][0]["isbn"], "9780618640157");
+    }
+}
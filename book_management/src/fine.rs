@@ -0,0 +1,143 @@
+use std::collections::HashMap;
+use chrono::NaiveDateTime;
+use serde::{Serialize, Deserialize};
+
+// 金额统一以“分”为单位保存，避免浮点误差
+pub type Money = u64;
+
+// 将以分为单位的金额格式化为元，例如 150 -> "¥1.50"
+pub fn format_money(amount: Money) -> String {
+    format!("¥{}.{:02}", amount / 100, amount % 100)
+}
+
+// 逾期罚款规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FinePolicy {
+    pub daily_rate: Money, // 每逾期一天的罚款
+    pub grace_days: i64, // 宽限天数，宽限期内的逾期天数不计罚款
+    pub max_fine: Option<Money>, // 单次借阅的罚款上限
+    #[serde(default)]
+    pub category_rates: HashMap<String, Money>, // 按分类覆盖每日罚款
+    pub block_threshold: Money, // 欠款超过该金额时禁止继续借阅
+}
+
+impl Default for FinePolicy {
+    fn default() -> Self {
+        FinePolicy {
+            daily_rate: 10,
+            grace_days: 0,
+            max_fine: Some(2000),
+            category_rates: HashMap::new(),
+            block_threshold: 1000,
+        }
+    }
+}
+
+impl FinePolicy {
+    // 根据逾期天数和书籍分类计算罚款
+    pub fn calculate(&self, days_overdue: i64, category: Option<&str>) -> Money {
+        let chargeable = (days_overdue - self.grace_days).max(0) as Money;
+        let rate = category
+            .and_then(|category| self.category_rates.get(category))
+            .copied()
+            .unwrap_or(self.daily_rate);
+        let fine = chargeable * rate;
+        match self.max_fine {
+            Some(max) => fine.min(max),
+            None => fine,
+        }
+    }
+}
+
+// 账目类型
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum LedgerKind {
+    Fine { loan_id: u64 }, // 逾期罚款，增加欠款
+    Payment, // 缴费，减少欠款
+    Waiver { reason: String }, // 减免，减少欠款
+}
+
+// 借阅者账户中的一笔记录
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LedgerEntry {
+    pub borrower: String,
+    pub amount: Money,
+    pub kind: LedgerKind,
+    pub at: NaiveDateTime,
+}
+
+impl LedgerEntry {
+    // 对欠款的影响，罚款为正，缴费和减免为负
+    fn signed_amount(&self) -> i64 {
+        match self.kind {
+            LedgerKind::Fine { .. } => self.amount as i64,
+            LedgerKind::Payment | LedgerKind::Waiver { .. } => -(self.amount as i64),
+        }
+    }
+}
+
+// 所有借阅者的罚款账本，只追加记录，余额由记录累加得出
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Ledger {
+    entries: Vec<LedgerEntry>,
+}
+
+impl Ledger {
+    pub fn record(&mut self, entry: LedgerEntry) {
+        self.entries.push(entry);
+    }
+
+    // 借阅者当前的欠款
+    pub fn balance(&self, borrower: &str) -> Money {
+        let balance: i64 = self
+            .entries_for(borrower)
+            .iter()
+            .map(|entry| entry.signed_amount())
+            .sum();
+        balance.max(0) as Money
+    }
+
+    // 某位借阅者的全部账目，按记录顺序
+    pub fn entries_for(&self, borrower: &str) -> Vec<&LedgerEntry> {
+        self.entries.iter().filter(|entry| entry.borrower == borrower).collect()
+    }
+
+    pub fn entries(&self) -> &[LedgerEntry] {
+        &self.entries
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fine_with_grace_period_and_cap() {
+        let policy = FinePolicy {
+            daily_rate: 50,
+            grace_days: 2,
+            max_fine: Some(300),
+            category_rates: HashMap::new(),
+            block_threshold: 1000,
+        };
+        assert_eq!(policy.calculate(0, None), 0);
+        assert_eq!(policy.calculate(2, None), 0);
+        assert_eq!(policy.calculate(5, None), 150);
+        assert_eq!(policy.calculate(30, None), 300);
+    }
+
+    #[test]
+    fn test_fine_per_category_rate() {
+        let mut policy = FinePolicy::default();
+        policy.category_rates.insert("reference".to_string(), 100);
+        assert_eq!(policy.calculate(3, Some("reference")), 300);
+        assert_eq!(policy.calculate(3, Some("fiction")), 30);
+        assert_eq!(policy.calculate(3, None), 30);
+    }
+
+    #[test]
+    fn test_format_money() {
+        assert_eq!(format_money(0), "¥0.00");
+        assert_eq!(format_money(1505), "¥15.05");
+    }
+}
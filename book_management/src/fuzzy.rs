@@ -0,0 +1,183 @@
+use crate::search::fold;
+use crate::Book;
+
+// 默认的相似度阈值，低于该值的候选不返回
+pub const DEFAULT_FUZZY_THRESHOLD: f64 = 0.7;
+
+// 命中的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchField {
+    Title,
+    Author,
+}
+
+// 模糊检索的候选结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct FuzzyMatch<'a> {
+    pub book: &'a Book,
+    pub score: f64, // 相似度，0 到 1 之间，1 表示完全一致
+    pub field: MatchField,
+}
+
+// 编辑距离（允许相邻字符交换），按字符而不是字节计算
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut best = (rows[i - 1][j] + 1)
+                .min(rows[i][j - 1] + 1)
+                .min(rows[i - 1][j - 1] + cost);
+            // "Tolkein" 和 "Tolkien" 只差一次交换
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = best;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+// 基于编辑距离的相似度
+pub fn similarity(a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - edit_distance(a, b) as f64 / longest as f64
+}
+
+// 拆分后的人名：名的首字母和姓
+#[derive(Debug, Clone, PartialEq)]
+struct NameParts {
+    initials: Vec<char>,
+    surname: String,
+}
+
+// 解析人名，支持 "J.R.R. Tolkien"、"JRR Tolkien" 和 "Tolkien, J.R.R." 等写法
+fn parse_name(name: &str) -> NameParts {
+    let (given, surname) = match name.split_once(',') {
+        Some((surname, given)) => (given.to_string(), surname.to_string()),
+        None => {
+            let trimmed = name.trim();
+            match trimmed.rfind(|c: char| c.is_whitespace() || c == '.') {
+                Some(index) => (trimmed[..index].to_string(), trimmed[index + 1..].to_string()),
+                None => (String::new(), trimmed.to_string()),
+            }
+        }
+    };
+    let mut initials = Vec::new();
+    for word in given.split(|c: char| c.is_whitespace() || c == '.').filter(|word| !word.is_empty()) {
+        // 连写的大写缩写（如 JRR）每个字母都是首字母
+        if word.chars().count() <= 3 && word.chars().all(|c| c.is_uppercase()) {
+            initials.extend(fold(word).chars());
+        } else if let Some(first) = fold(word).chars().next() {
+            initials.push(first);
+        }
+    }
+    NameParts { initials, surname: fold(surname.trim()) }
+}
+
+// 人名相似度：以姓为主，双方都给出名时再比较首字母
+pub fn name_similarity(query: &str, name: &str) -> f64 {
+    let query = parse_name(query);
+    let name = parse_name(name);
+    let surname = similarity(&query.surname, &name.surname);
+    if query.initials.is_empty() || name.initials.is_empty() {
+        return surname;
+    }
+    let matched = query
+        .initials
+        .iter()
+        .zip(&name.initials)
+        .filter(|(a, b)| a == b)
+        .count();
+    let initials = matched as f64 / query.initials.len().max(name.initials.len()) as f64;
+    0.8 * surname + 0.2 * initials
+}
+
+// 标题相似度：整体比较和逐词比较取较高者，逐词比较时每个查询词取最接近的标题词
+pub fn title_similarity(query: &str, title: &str) -> f64 {
+    let query = fold(query);
+    let title = fold(title);
+    let whole = similarity(&query, &title);
+    let title_words: Vec<&str> = title.split_whitespace().collect();
+    let query_words: Vec<&str> = query.split_whitespace().collect();
+    if query_words.is_empty() || title_words.is_empty() {
+        return whole;
+    }
+    let per_word: f64 = query_words
+        .iter()
+        .map(|word| {
+            title_words
+                .iter()
+                .map(|candidate| similarity(word, candidate))
+                .fold(0.0, f64::max)
+        })
+        .sum::<f64>()
+        / query_words.len() as f64;
+    whole.max(per_word)
+}
+
+// 在书目中按标题和作者模糊匹配，返回相似度不低于阈值的候选，按相似度从高到低排序
+pub fn fuzzy_search<'a>(
+    books: impl IntoIterator<Item = &'a Book>,
+    query: &str,
+    threshold: f64,
+) -> Vec<FuzzyMatch<'a>> {
+    let mut matches: Vec<FuzzyMatch<'a>> = books
+        .into_iter()
+        .map(|book| {
+            // 合著者中最接近的一位
+            let author = book.authors().into_iter().map(|author| name_similarity(query, author)).fold(0.0, f64::max);
+            let title = title_similarity(query, &book.title);
+            if author >= title {
+                FuzzyMatch { book, score: author, field: MatchField::Author }
+            } else {
+                FuzzyMatch { book, score: title, field: MatchField::Title }
+            }
+        })
+        .filter(|candidate| candidate.score >= threshold)
+        .collect();
+    matches.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.book.title.cmp(&b.book.title)));
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("tolkein", "tolkien"), 1);
+        assert_eq!(edit_distance("指环王", "指环"), 1);
+    }
+
+    #[test]
+    fn test_name_forms_match() {
+        assert_eq!(name_similarity("JRR Tolkien", "J.R.R. Tolkien"), 1.0);
+        assert_eq!(name_similarity("Tolkien, J.R.R.", "J.R.R. Tolkien"), 1.0);
+        assert!(name_similarity("Tolkein", "J.R.R. Tolkien") > DEFAULT_FUZZY_THRESHOLD);
+        assert!(name_similarity("Austen", "J.R.R. Tolkien") < DEFAULT_FUZZY_THRESHOLD);
+    }
+
+    #[test]
+    fn test_wrong_initials_score_lower() {
+        let right = name_similarity("J. Austen", "Jane Austen");
+        let wrong = name_similarity("K. Austen", "Jane Austen");
+        assert!(right > wrong);
+    }
+
+    #[test]
+    fn test_title_similarity_tolerates_typos() {
+        assert!(title_similarity("Lord of the Rngs", "The Lord of the Rings") > 0.8);
+        assert!(title_similarity("Pride and Prejudice", "Rust in Action") < 0.5);
+    }
+}
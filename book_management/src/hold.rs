@@ -0,0 +1,34 @@
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Serialize, Deserialize};
+
+// 预约规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HoldPolicy {
+    pub pickup_days: i64, // 书籍放上预约书架后保留的天数
+}
+
+impl Default for HoldPolicy {
+    fn default() -> Self {
+        HoldPolicy { pickup_days: 7 }
+    }
+}
+
+// 预约队列中的一条预约
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hold {
+    pub patron: String, // 预约者
+    pub placed_at: NaiveDateTime, // 预约时间
+}
+
+// 已放上预约书架、等待预约者取书的副本状态
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShelvedHold {
+    pub patron: String, // 为谁保留
+    pub expires: NaiveDate, // 最后取书日期，过期后让给队列中的下一位
+}
+
+impl ShelvedHold {
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
+        today > self.expires
+    }
+}
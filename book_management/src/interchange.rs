@@ -0,0 +1,581 @@
+use std::collections::HashSet;
+use std::io::{BufRead, Read, Write};
+use std::path::Path;
+use std::str::FromStr;
+use crate::{Book, BookStore, ClassScheme, Classification, Isbn, LibraryError, LibraryResult, Metadata};
+
+// 批量导入导出支持的格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatalogFormat {
+    Json, // 书籍数组，与数据文件中的书籍格式相同，但不含借阅和预约状态
+    Csv, // 带表头的 CSV，列名可通过 CsvMapping 对应
+    Marc, // 简化的 MARC 行格式
+}
+
+impl CatalogFormat {
+    // 按扩展名选择格式，.csv 为 CSV，.mrk 和 .marc 为 MARC，其他按 JSON 处理
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("csv") => CatalogFormat::Csv,
+            Some("mrk") | Some("marc") => CatalogFormat::Marc,
+            _ => CatalogFormat::Json,
+        }
+    }
+}
+
+impl FromStr for CatalogFormat {
+    type Err = LibraryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(CatalogFormat::Json),
+            "csv" => Ok(CatalogFormat::Csv),
+            "marc" | "mrk" => Ok(CatalogFormat::Marc),
+            _ => Err(LibraryError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+// CSV 表头与书籍字段的对应关系，供应商的表格列名各不相同
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvMapping {
+    pub isbn: String,
+    pub title: String,
+    pub author: String,
+    pub category: Option<String>, // 没有分类列时为 None
+    // 以下为扩展信息的列，都是可选的；多个合著者或主题在同一格内用分号分隔
+    pub co_authors: Option<String>,
+    pub publisher: Option<String>,
+    pub year: Option<String>,
+    pub edition: Option<String>,
+    pub language: Option<String>,
+    pub subjects: Option<String>,
+    pub classification: Option<String>, // 形如 ddc:823.912 或 clc:I247.5
+}
+
+impl Default for CsvMapping {
+    fn default() -> Self {
+        CsvMapping {
+            isbn: "isbn".to_string(),
+            title: "title".to_string(),
+            author: "author".to_string(),
+            category: Some("category".to_string()),
+            co_authors: Some("co_authors".to_string()),
+            publisher: Some("publisher".to_string()),
+            year: Some("year".to_string()),
+            edition: Some("edition".to_string()),
+            language: Some("language".to_string()),
+            subjects: Some("subjects".to_string()),
+            classification: Some("classification".to_string()),
+        }
+    }
+}
+
+// CSV 单元格中多个值的分隔符
+const LIST_SEPARATOR: char = ';';
+
+fn split_list(value: Option<&str>) -> Vec<String> {
+    value
+        .map(|value| value.split(LIST_SEPARATOR).map(str::trim).filter(|v| !v.is_empty()).map(str::to_string).collect())
+        .unwrap_or_default()
+}
+
+fn parse_year(value: &str) -> LibraryResult<i32> {
+    value.trim().parse().map_err(|_| LibraryError::SerdeError(format!("无效的出版年: {}", value)))
+}
+
+// 分类号的文本形式，可以被 Classification 的 FromStr 解析回来
+fn classification_code(class: &Classification) -> String {
+    match class.scheme {
+        ClassScheme::Dewey => format!("ddc:{}", class.number),
+        ClassScheme::Clc => format!("clc:{}", class.number),
+    }
+}
+
+// 扩展信息在 CSV 中的列，与 CsvMapping 中的字段一一对应
+type MetadataValue = fn(&Metadata) -> String;
+type MetadataColumn = (fn(&CsvMapping) -> &Option<String>, MetadataValue);
+
+const METADATA_COLUMNS: [MetadataColumn; 7] = [
+    (|m| &m.co_authors, |meta| meta.co_authors.join(&LIST_SEPARATOR.to_string())),
+    (|m| &m.publisher, |meta| meta.publisher.clone().unwrap_or_default()),
+    (|m| &m.year, |meta| meta.year.map(|year| year.to_string()).unwrap_or_default()),
+    (|m| &m.edition, |meta| meta.edition.clone().unwrap_or_default()),
+    (|m| &m.language, |meta| meta.language.clone().unwrap_or_default()),
+    (|m| &m.subjects, |meta| meta.subjects.join(&LIST_SEPARATOR.to_string())),
+    (|m| &m.classification, |meta| meta.classification.as_ref().map(classification_code).unwrap_or_default()),
+];
+
+// 解析出的一条记录，line 为记录在文件中的起始行号（JSON 为记录序号）
+#[derive(Debug, PartialEq)]
+pub struct ParsedRecord {
+    pub line: usize,
+    pub book: LibraryResult<Book>,
+}
+
+// 某一行导入失败的原因
+#[derive(Debug, PartialEq)]
+pub struct RowError {
+    pub line: usize,
+    pub isbn: Option<String>,
+    pub error: LibraryError,
+}
+
+// 导入结果。试运行时 imported 为可以导入的记录数，图书馆不会被修改
+#[derive(Debug, Default, PartialEq)]
+pub struct ImportReport {
+    pub imported: usize,
+    pub errors: Vec<RowError>,
+}
+
+// 按 CSV 读取书籍，缺少必需的列时整体失败，单行的问题记在对应的记录里
+pub fn read_csv(reader: impl Read, mapping: &CsvMapping) -> LibraryResult<Vec<ParsedRecord>> {
+    let mut reader = csv::ReaderBuilder::new().flexible(true).trim(csv::Trim::All).from_reader(reader);
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| {
+        headers
+            .iter()
+            .position(|header| header.eq_ignore_ascii_case(name))
+            .ok_or_else(|| LibraryError::MissingField(name.to_string()))
+    };
+    let isbn = column(&mapping.isbn)?;
+    let title = column(&mapping.title)?;
+    let author = column(&mapping.author)?;
+    // 分类和扩展信息的列是可选的
+    let optional = |name: &Option<String>| name.as_deref().and_then(|name| column(name).ok());
+    let category = optional(&mapping.category);
+    let [co_authors, publisher, year, edition, language, subjects, classification] =
+        METADATA_COLUMNS.map(|(name, _)| optional(name(mapping)));
+
+    let mut records = Vec::new();
+    for result in reader.records() {
+        let record = match result {
+            Ok(record) => record,
+            Err(e) => {
+                let line = e.position().map_or(0, |position| position.line() as usize);
+                records.push(ParsedRecord { line, book: Err(e.into()) });
+                continue;
+            }
+        };
+        let line = record.position().map_or(0, |position| position.line() as usize);
+        let field = |index: usize, name: &str| match record.get(index) {
+            Some(value) if !value.is_empty() => Ok(value.to_string()),
+            _ => Err(LibraryError::MissingField(name.to_string())),
+        };
+        let text = |index: Option<usize>| index.and_then(|index| record.get(index)).filter(|v| !v.is_empty());
+        let book = field(isbn, &mapping.isbn).and_then(|isbn| {
+            let mut book = Book::new(field(title, &mapping.title)?, field(author, &mapping.author)?, isbn);
+            book.category = text(category).map(str::to_string);
+            book.metadata = Metadata {
+                co_authors: split_list(text(co_authors)),
+                publisher: text(publisher).map(str::to_string),
+                year: text(year).map(parse_year).transpose()?,
+                edition: text(edition).map(str::to_string),
+                language: text(language).map(str::to_string),
+                subjects: split_list(text(subjects)),
+                classification: text(classification).map(str::parse).transpose()?,
+            };
+            Ok(book)
+        });
+        records.push(ParsedRecord { line, book });
+    }
+    Ok(records)
+}
+
+// 按 CSV 写出书籍，列名取自 mapping
+pub fn write_csv<'a>(
+    writer: impl Write,
+    books: impl IntoIterator<Item = &'a Book>,
+    mapping: &CsvMapping,
+) -> LibraryResult<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    let columns: Vec<(&str, MetadataValue)> = METADATA_COLUMNS
+        .iter()
+        .filter_map(|(name, value)| name(mapping).as_deref().map(|name| (name, *value)))
+        .collect();
+    let mut header = vec![mapping.isbn.as_str(), mapping.title.as_str(), mapping.author.as_str()];
+    header.extend(mapping.category.as_deref());
+    header.extend(columns.iter().map(|(name, _)| *name));
+    writer.write_record(&header)?;
+    for book in books {
+        let mut row = vec![book.isbn.clone(), book.title.clone(), book.author.clone()];
+        if mapping.category.is_some() {
+            row.push(book.category.clone().unwrap_or_default());
+        }
+        row.extend(columns.iter().map(|(_, value)| value(&book.metadata)));
+        writer.write_record(&row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// 简化的 MARC 行格式：每行一个字段，形如 "=245  The Hobbit"，记录之间用空行分隔。
+// 使用的字段：020 ISBN，100 作者，245 标题，650 分类，700 合著者（可重复），250 版次，
+// 260 出版信息（$b 出版者，$c 出版年），041 语言，653 主题（可重复），082 杜威分类号，
+// 084 中图分类号；其他字段（如 LDR）忽略
+const TAG_ISBN: &str = "020";
+const TAG_AUTHOR: &str = "100";
+const TAG_TITLE: &str = "245";
+const TAG_CATEGORY: &str = "650";
+const TAG_CO_AUTHOR: &str = "700";
+const TAG_EDITION: &str = "250";
+const TAG_PUBLICATION: &str = "260";
+const TAG_LANGUAGE: &str = "041";
+const TAG_SUBJECT: &str = "653";
+const TAG_DEWEY: &str = "082";
+const TAG_CLC: &str = "084";
+
+pub fn read_marc(reader: impl BufRead) -> LibraryResult<Vec<ParsedRecord>> {
+    let mut records = Vec::new();
+    let mut fields: Vec<(String, String)> = Vec::new();
+    let mut start = 0;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            if !fields.is_empty() {
+                records.push(ParsedRecord { line: start, book: marc_record(&std::mem::take(&mut fields)) });
+            }
+            continue;
+        }
+        if fields.is_empty() {
+            start = i + 1;
+        }
+        let Some(rest) = line.strip_prefix('=') else {
+            records.push(ParsedRecord {
+                line: i + 1,
+                book: Err(LibraryError::SerdeError(format!("无法识别的 MARC 行: {}", line))),
+            });
+            continue;
+        };
+        let (tag, value) = rest.split_at(rest.len().min(3));
+        fields.push((tag.to_string(), value.trim().to_string()));
+    }
+    if !fields.is_empty() {
+        records.push(ParsedRecord { line: start, book: marc_record(&fields) });
+    }
+    Ok(records)
+}
+
+fn marc_record(fields: &[(String, String)]) -> LibraryResult<Book> {
+    let field = |tag: &str| {
+        fields
+            .iter()
+            .find(|(t, value)| t == tag && !value.is_empty())
+            .map(|(_, value)| value.clone())
+    };
+    let required = |tag: &str, name: &str| field(tag).ok_or_else(|| LibraryError::MissingField(name.to_string()));
+    let all = |tag: &str| {
+        fields
+            .iter()
+            .filter(|(t, value)| t == tag && !value.is_empty())
+            .map(|(_, value)| value.clone())
+            .collect::<Vec<_>>()
+    };
+    let isbn = required(TAG_ISBN, "isbn")?;
+    let mut book = Book::new(required(TAG_TITLE, "title")?, required(TAG_AUTHOR, "author")?, isbn);
+    book.category = field(TAG_CATEGORY);
+    let publication = field(TAG_PUBLICATION).unwrap_or_default();
+    let subfield = |code: char| {
+        publication
+            .split('$')
+            .find_map(|part| part.strip_prefix(code))
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    let classification = match (field(TAG_DEWEY), field(TAG_CLC)) {
+        (Some(number), _) => Some(format!("ddc:{}", number).parse()?),
+        (None, Some(number)) => Some(format!("clc:{}", number).parse()?),
+        (None, None) => None,
+    };
+    book.metadata = Metadata {
+        co_authors: all(TAG_CO_AUTHOR),
+        publisher: subfield('b').map(str::to_string),
+        year: subfield('c').map(parse_year).transpose()?,
+        edition: field(TAG_EDITION),
+        language: field(TAG_LANGUAGE),
+        subjects: all(TAG_SUBJECT),
+        classification,
+    };
+    Ok(book)
+}
+
+pub fn write_marc<'a>(mut writer: impl Write, books: impl IntoIterator<Item = &'a Book>) -> LibraryResult<()> {
+    for book in books {
+        writeln!(writer, "={}  {}", TAG_ISBN, book.isbn)?;
+        writeln!(writer, "={}  {}", TAG_AUTHOR, book.author)?;
+        writeln!(writer, "={}  {}", TAG_TITLE, book.title)?;
+        if let Some(category) = &book.category {
+            writeln!(writer, "={}  {}", TAG_CATEGORY, category)?;
+        }
+        let metadata = &book.metadata;
+        for co_author in &metadata.co_authors {
+            writeln!(writer, "={}  {}", TAG_CO_AUTHOR, co_author)?;
+        }
+        if let Some(edition) = &metadata.edition {
+            writeln!(writer, "={}  {}", TAG_EDITION, edition)?;
+        }
+        if metadata.publisher.is_some() || metadata.year.is_some() {
+            let mut publication = String::new();
+            if let Some(publisher) = &metadata.publisher {
+                publication.push_str(&format!("$b{}", publisher));
+            }
+            if let Some(year) = metadata.year {
+                publication.push_str(&format!("$c{}", year));
+            }
+            writeln!(writer, "={}  {}", TAG_PUBLICATION, publication)?;
+        }
+        if let Some(language) = &metadata.language {
+            writeln!(writer, "={}  {}", TAG_LANGUAGE, language)?;
+        }
+        for subject in &metadata.subjects {
+            writeln!(writer, "={}  {}", TAG_SUBJECT, subject)?;
+        }
+        if let Some(class) = &metadata.classification {
+            let tag = match class.scheme {
+                ClassScheme::Dewey => TAG_DEWEY,
+                ClassScheme::Clc => TAG_CLC,
+            };
+            writeln!(writer, "={}  {}", tag, class.number)?;
+        }
+        writeln!(writer)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+// 读取 JSON 书籍数组。JSON 的格式错误对整个文件生效，因此不区分行
+pub fn read_json(reader: impl Read) -> LibraryResult<Vec<ParsedRecord>> {
+    let books: Vec<Book> = serde_json::from_reader(reader)?;
+    Ok(books
+        .into_iter()
+        .enumerate()
+        .map(|(i, book)| ParsedRecord { line: i + 1, book: Ok(book) })
+        .collect())
+}
+
+// 写出 JSON 书籍数组，副本上的流通状态不导出
+pub fn write_json<'a>(mut writer: impl Write, books: impl IntoIterator<Item = &'a Book>) -> LibraryResult<()> {
+    let books: Vec<Book> = books.into_iter().cloned().map(without_circulation).collect();
+    serde_json::to_writer_pretty(&mut writer, &books)?;
+    writer.flush()?;
+    Ok(())
+}
+
+// 清除副本的借阅者和预约保留。这些是本馆的流通状态，
+// 在另一个图书馆里没有对应的借阅记录和预约队列
+fn without_circulation(mut book: Book) -> Book {
+    for copy in &mut book.copies {
+        copy.borrowed_by = None;
+        copy.on_hold_for = None;
+    }
+    book
+}
+
+// 按格式读取，CSV 使用默认的列名
+pub fn read_catalog(reader: impl BufRead, format: CatalogFormat) -> LibraryResult<Vec<ParsedRecord>> {
+    match format {
+        CatalogFormat::Json => read_json(reader),
+        CatalogFormat::Csv => read_csv(reader, &CsvMapping::default()),
+        CatalogFormat::Marc => read_marc(reader),
+    }
+}
+
+// 按格式写出，书籍按 ISBN 排序，方便比较两次导出的结果
+pub fn write_catalog<S: BookStore + ?Sized>(writer: impl Write, store: &S, format: CatalogFormat) -> LibraryResult<()> {
+    let books = store.all_books()?;
+    match format {
+        CatalogFormat::Json => write_json(writer, &books),
+        CatalogFormat::Csv => write_csv(writer, &books, &CsvMapping::default()),
+        CatalogFormat::Marc => write_marc(writer, &books),
+    }
+}
+
+// 将解析出的记录导入图书馆。每条记录单独校验（ISBN、重复、缺少字段），
+// 有问题的记录记入报告并跳过，不会中断整个导入
+pub fn import_records<S: BookStore + ?Sized>(store: &mut S, records: Vec<ParsedRecord>) -> ImportReport {
+    let mut report = ImportReport::default();
+    let mut seen = HashSet::new();
+    for record in records {
+        let line = record.line;
+        let result = check_record(store, &mut seen, record)
+            .and_then(|(key, book)| store.add_book(book).map_err(|error| RowError { line, isbn: Some(key), error }));
+        match result {
+            Ok(()) => report.imported += 1,
+            Err(error) => report.errors.push(error),
+        }
+    }
+    report
+}
+
+// 试运行：做与导入相同的校验并给出报告，但不修改图书馆
+pub fn dry_run<S: BookStore + ?Sized>(store: &S, records: Vec<ParsedRecord>) -> ImportReport {
+    let mut report = ImportReport::default();
+    let mut seen = HashSet::new();
+    for record in records {
+        match check_record(store, &mut seen, record) {
+            Ok(_) => report.imported += 1,
+            Err(error) => report.errors.push(error),
+        }
+    }
+    report
+}
+
+// 校验一条记录，通过时返回规范化的 ISBN 和书籍
+fn check_record<S: BookStore + ?Sized>(
+    store: &S,
+    seen: &mut HashSet<String>,
+    record: ParsedRecord,
+) -> Result<(String, Book), RowError> {
+    let line = record.line;
+    let book = record.book.map_err(|error| RowError { line, isbn: None, error })?;
+    let key = match Isbn::parse(&book.isbn) {
+        Ok(isbn) => isbn.as_str().to_string(),
+        Err(error) => return Err(RowError { line, isbn: Some(book.isbn), error }),
+    };
+    // 文件内部重复的 ISBN 同样算作重复
+    let error = match store.get_book(&key) {
+        Ok(_) => LibraryError::BookAlreadyExists,
+        Err(LibraryError::BookNotFound) if seen.insert(key.clone()) => return Ok((key, without_circulation(book))),
+        Err(LibraryError::BookNotFound) => LibraryError::BookAlreadyExists,
+        Err(error) => error,
+    };
+    Err(RowError { line, isbn: Some(key), error })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Library;
+
+    const SUPPLIER_CSV: &str = "\
+书名,作者,ISBN,类别
+The Hobbit,J.R.R. Tolkien,978-0-618-64015-7,小说
+Pride and Prejudice,Jane Austen,9780141439518,
+Broken,Nobody,978-0618640158,
+,Anonymous,9781617294556,
+The Hobbit (again),J.R.R. Tolkien,0618640150,
+";
+
+    fn supplier_mapping() -> CsvMapping {
+        CsvMapping {
+            isbn: "ISBN".to_string(),
+            title: "书名".to_string(),
+            author: "作者".to_string(),
+            category: Some("类别".to_string()),
+            ..CsvMapping::default()
+        }
+    }
+
+    #[test]
+    fn test_csv_with_header_mapping_reports_row_errors() {
+        let mut library = Library::new();
+        let records = read_csv(SUPPLIER_CSV.as_bytes(), &supplier_mapping()).unwrap();
+        assert_eq!(records.len(), 5);
+
+        // 试运行不修改图书馆，但报告与实际导入一致
+        let preview = dry_run(&library, read_csv(SUPPLIER_CSV.as_bytes(), &supplier_mapping()).unwrap());
+        assert!(library.all_books().unwrap().is_empty());
+
+        let report = import_records(&mut library, records);
+        assert_eq!(report, preview);
+        assert_eq!(report.imported, 2);
+        let errors: Vec<(usize, &LibraryError)> = report.errors.iter().map(|e| (e.line, &e.error)).collect();
+        assert_eq!(
+            errors,
+            vec![
+                (4, &LibraryError::InvalidIsbn("978-0618640158".to_string())),
+                (5, &LibraryError::MissingField("书名".to_string())),
+                (6, &LibraryError::BookAlreadyExists),
+            ]
+        );
+        let hobbit = library.find_book_by_isbn("9780618640157").unwrap();
+        assert_eq!(hobbit.category.as_deref(), Some("小说"));
+        assert_eq!(library.find_book_by_isbn("9780141439518").unwrap().category, None);
+    }
+
+    #[test]
+    fn test_csv_missing_column_fails_whole_file() {
+        let result = read_csv("title,author\nThe Hobbit,Tolkien\n".as_bytes(), &CsvMapping::default());
+        assert_eq!(result, Err(LibraryError::MissingField("isbn".to_string())));
+    }
+
+    #[test]
+    fn test_marc_records() {
+        let input = "\
+=LDR  00000nam
+=020  9780618640157
+=100  J.R.R. Tolkien
+=245  The Hobbit
+=650  小说
+
+=020  9780141439518
+=245  Pride and Prejudice
+";
+        let records = read_marc(input.as_bytes()).unwrap();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].line, 1);
+        assert_eq!(records[0].book.as_ref().unwrap().category.as_deref(), Some("小说"));
+        assert_eq!(records[1].line, 7);
+        assert_eq!(records[1].book, Err(LibraryError::MissingField("author".to_string())));
+    }
+
+    #[test]
+    fn test_json_leaves_circulation_state_behind() {
+        let mut library = Library::new();
+        library
+            .add_book(Book::new("The Hobbit".to_string(), "J.R.R. Tolkien".to_string(), "9780618640157".to_string()))
+            .unwrap();
+        crate::store_contract::register(&mut library, "Alice", crate::MembershipTier::Standard);
+        library.borrow_book("9780618640157", "Alice").unwrap();
+
+        let mut buffer = Vec::new();
+        write_catalog(&mut buffer, &library, CatalogFormat::Json).unwrap();
+        assert!(!String::from_utf8(buffer.clone()).unwrap().contains("Alice"));
+
+        // 旧版本导出的文件带有借阅者，导入时同样清除
+        let mut book = library.find_book_by_isbn("9780618640157").unwrap().clone();
+        book.copies[0].on_hold_for = Some(crate::ShelvedHold {
+            patron: "Bob".to_string(),
+            expires: chrono::NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+        });
+        let old_export = serde_json::to_vec(&[book]).unwrap();
+        for input in [buffer, old_export] {
+            let mut copy = Library::new();
+            let report = import_records(&mut copy, read_json(input.as_slice()).unwrap());
+            assert_eq!(report.imported, 1);
+            assert_eq!(copy.find_book_by_isbn("9780618640157").unwrap().available_copies(), 1);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_every_format() {
+        let mut library = Library::new();
+        let mut book = Book::new("指环王".to_string(), "托尔金".to_string(), "9787111213826".to_string());
+        book.category = Some("小说".to_string());
+        book.metadata = Metadata {
+            co_authors: vec!["丁棣".to_string(), "Alan Lee".to_string()],
+            publisher: Some("译林出版社".to_string()),
+            year: Some(2001),
+            edition: Some("第 1 版".to_string()),
+            language: Some("zh".to_string()),
+            subjects: vec!["奇幻".to_string(), "中土".to_string()],
+            classification: Some("I561.45".parse().unwrap()),
+        };
+        library.add_book(book).unwrap();
+        library
+            .add_book(Book::new("Jane Eyre, Vol. 1".to_string(), "Charlotte Brontë".to_string(), "9780141439518".to_string()))
+            .unwrap();
+
+        for format in [CatalogFormat::Json, CatalogFormat::Csv, CatalogFormat::Marc] {
+            let mut buffer = Vec::new();
+            write_catalog(&mut buffer, &library, format).unwrap();
+            let mut copy = Library::new();
+            let report = import_records(&mut copy, read_catalog(buffer.as_slice(), format).unwrap());
+            assert_eq!(report.imported, 2, "{:?}", format);
+            assert!(report.errors.is_empty(), "{:?}", format);
+            assert_eq!(copy.find_book_by_isbn("9787111213826"), library.find_book_by_isbn("9787111213826"));
+            assert_eq!(copy.find_book_by_isbn("9780141439518"), library.find_book_by_isbn("9780141439518"));
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use crate::LibraryError;
+
+// 经过校验的 ISBN，内部统一保存为不带连字符的 13 位数字。
+// ISBN-10 会被转换为以 978 开头的 ISBN-13
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Isbn(String);
+
+impl Isbn {
+    // 解析 ISBN-10 或 ISBN-13，允许连字符和空格，校验位不正确时返回错误
+    pub fn parse(input: &str) -> Result<Isbn, LibraryError> {
+        let invalid = || LibraryError::InvalidIsbn(input.to_string());
+        let compact: String = input
+            .chars()
+            .filter(|c| *c != '-' && !c.is_whitespace())
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+        match compact.len() {
+            10 if is_valid_isbn10(&compact) => {
+                let body = format!("978{}", &compact[..9]);
+                let check = isbn13_check_digit(&body);
+                Ok(Isbn(format!("{}{}", body, check)))
+            }
+            13 if is_valid_isbn13(&compact) => Ok(Isbn(compact)),
+            _ => Err(invalid()),
+        }
+    }
+
+    // 不带连字符的 13 位形式，用作图书馆中的键
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    // 转换为 ISBN-10，只有 978 开头的 ISBN 才有对应的 ISBN-10
+    pub fn to_isbn10(&self) -> Option<String> {
+        let body = self.0.strip_prefix("978")?;
+        let body = &body[..9];
+        let sum: u32 = body
+            .chars()
+            .zip((2..=10).rev())
+            .map(|(c, weight)| c.to_digit(10).unwrap() * weight)
+            .sum();
+        let check = (11 - sum % 11) % 11;
+        let check = if check == 10 { 'X' } else { char::from_digit(check, 10).unwrap() };
+        Some(format!("{}{}", body, check))
+    }
+
+    // 带连字符的显示形式，例如 978-0-618-64015-7
+    pub fn hyphenated(&self) -> String {
+        let (prefix, rest) = self.0.split_at(3);
+        let (body, check) = rest.split_at(9);
+        match split_group(prefix, body) {
+            Some((group, registrant, publication)) => {
+                format!("{}-{}-{}-{}-{}", prefix, group, registrant, publication, check)
+            }
+            // 不在已知范围内的组只区分前缀和校验位
+            None => format!("{}-{}-{}", prefix, body, check),
+        }
+    }
+}
+
+impl fmt::Display for Isbn {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.hyphenated())
+    }
+}
+
+impl FromStr for Isbn {
+    type Err = LibraryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Isbn::parse(s)
+    }
+}
+
+impl TryFrom<String> for Isbn {
+    type Error = LibraryError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Isbn::parse(&value)
+    }
+}
+
+impl From<Isbn> for String {
+    fn from(isbn: Isbn) -> Self {
+        isbn.0
+    }
+}
+
+// 将用户输入转换为图书馆中的键：合法的 ISBN 统一为 13 位数字，否则原样使用
+pub fn normalize(input: &str) -> String {
+    match Isbn::parse(input) {
+        Ok(isbn) => isbn.0,
+        Err(_) => input.trim().to_string(),
+    }
+}
+
+// 用于显示的 ISBN：合法的 ISBN 加上连字符，否则原样显示
+pub fn display(input: &str) -> String {
+    match Isbn::parse(input) {
+        Ok(isbn) => isbn.hyphenated(),
+        Err(_) => input.to_string(),
+    }
+}
+
+fn is_valid_isbn10(s: &str) -> bool {
+    let mut sum = 0;
+    for (i, c) in s.chars().enumerate() {
+        let value = match c {
+            'X' if i == 9 => 10,
+            _ => match c.to_digit(10) {
+                Some(d) => d,
+                None => return false,
+            },
+        };
+        sum += value * (10 - i as u32);
+    }
+    sum % 11 == 0
+}
+
+fn is_valid_isbn13(s: &str) -> bool {
+    if !s.chars().all(|c| c.is_ascii_digit()) || !(s.starts_with("978") || s.starts_with("979")) {
+        return false;
+    }
+    isbn13_check_digit(&s[..12]) == s.chars().last().unwrap()
+}
+
+// 根据前 12 位计算 ISBN-13 的校验位
+fn isbn13_check_digit(body: &str) -> char {
+    let sum: u32 = body
+        .chars()
+        .enumerate()
+        .map(|(i, c)| c.to_digit(10).unwrap() * if i % 2 == 0 { 1 } else { 3 })
+        .sum();
+    char::from_digit((10 - sum % 10) % 10, 10).unwrap()
+}
+
+// 常用语言区的出版者号范围：(起始, 结束, 出版者号位数)，按前 7 位比较
+const GROUP_0: &[(u32, u32, usize)] = &[
+    (0, 1999999, 2),
+    (2000000, 6999999, 3),
+    (7000000, 8499999, 4),
+    (8500000, 8999999, 5),
+    (9000000, 9499999, 6),
+    (9500000, 9999999, 7),
+];
+const GROUP_1: &[(u32, u32, usize)] = &[
+    (0, 999999, 2),
+    (1000000, 3999999, 3),
+    (4000000, 5499999, 4),
+    (5500000, 8697999, 5),
+    (8698000, 9989999, 6),
+    (9990000, 9999999, 7),
+];
+const GROUP_7: &[(u32, u32, usize)] = &[
+    (0, 999999, 2),
+    (1000000, 4999999, 3),
+    (5000000, 7999999, 4),
+    (8000000, 8999999, 5),
+    (9000000, 9999999, 6),
+];
+
+// 按简化的范围表拆分组号、出版者号和出版序号，只覆盖英语区（0、1）和中国（7）
+fn split_group<'a>(prefix: &str, body: &'a str) -> Option<(&'a str, &'a str, &'a str)> {
+    if prefix != "978" {
+        return None;
+    }
+    let (group, rest) = body.split_at(1);
+    let ranges = match group {
+        "0" => GROUP_0,
+        "1" => GROUP_1,
+        "7" => GROUP_7,
+        _ => return None,
+    };
+    let key: u32 = rest[..7].parse().ok()?;
+    let (_, _, len) = ranges.iter().find(|(start, end, _)| (*start..=*end).contains(&key))?;
+    let (registrant, publication) = rest.split_at(*len);
+    Some((group, registrant, publication))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_isbn13_with_and_without_hyphens() {
+        let a = Isbn::parse("978-0618640157").unwrap();
+        let b = Isbn::parse("9780618640157").unwrap();
+        assert_eq!(a, b);
+        assert_eq!(a.as_str(), "9780618640157");
+    }
+
+    #[test]
+    fn test_isbn10_converts_to_isbn13() {
+        let isbn = Isbn::parse("0-618-64015-0").unwrap();
+        assert_eq!(isbn.as_str(), "9780618640157");
+        assert_eq!(isbn.to_isbn10(), Some("0618640150".to_string()));
+        // 校验位为 X 的 ISBN-10
+        assert_eq!(Isbn::parse("080442957X").unwrap().as_str(), "9780804429573");
+    }
+
+    #[test]
+    fn test_invalid_check_digit_is_rejected() {
+        assert_eq!(
+            Isbn::parse("978-0618260274"),
+            Err(LibraryError::InvalidIsbn("978-0618260274".to_string()))
+        );
+        assert!(Isbn::parse("0-618-64015-1").is_err());
+        assert!(Isbn::parse("123-456").is_err());
+        assert!(Isbn::parse("97806182602X4").is_err());
+    }
+
+    #[test]
+    fn test_hyphenated_display() {
+        assert_eq!(Isbn::parse("9780618640157").unwrap().to_string(), "978-0-618-64015-7");
+        assert_eq!(Isbn::parse("9780141439518").unwrap().to_string(), "978-0-14-143951-8");
+        assert_eq!(Isbn::parse("9781617294556").unwrap().to_string(), "978-1-61729-455-6");
+        assert_eq!(Isbn::parse("9787111213826").unwrap().to_string(), "978-7-111-21382-6");
+        assert_eq!(Isbn::parse("9791032305690").unwrap().to_string(), "979-103230569-0");
+    }
+}
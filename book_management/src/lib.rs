@@ -1,29 +1,253 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use chrono::{Duration, NaiveDate, NaiveDateTime};
 use serde::{Serialize, Deserialize};
 use std::io;
 use std::fmt;
 use std::error::Error;
+use std::path::PathBuf;
 
-// 定义书籍结构体，添加借阅状态
-#[derive(Debug, Clone, Serialize, Deserialize)]
+pub mod audit;
+pub mod cli;
+pub mod fine;
+pub mod fuzzy;
+pub mod hold;
+pub mod interchange;
+pub mod isbn;
+pub mod loan;
+pub mod metadata;
+pub mod notify;
+pub mod patron;
+pub mod report;
+pub mod search;
+pub mod shared;
 
+pub use audit::{AuditLog, AuditRecord, Event};
+pub use fine::{format_money, FinePolicy, Ledger, LedgerEntry, LedgerKind, Money};
+pub use fuzzy::{FuzzyMatch, MatchField, DEFAULT_FUZZY_THRESHOLD};
+pub use hold::{Hold, HoldPolicy, ShelvedHold};
+pub use isbn::Isbn;
+pub use notify::{StdoutSubscriber, Subscriber};
+pub use metadata::{BookFilter, ClassScheme, Classification, Facet, Metadata};
+pub use loan::{Clock, Loan, LoanPolicy, ManualClock, ReturnReceipt, SharedClock, SystemClock};
+pub use patron::{MembershipTier, Patron, PatronStatus, TierLimits};
+pub use report::{Report, ReportFormat, ReportKind, ReportOptions};
+pub use search::{SearchIndex, SearchResult};
+pub use shared::SharedLibrary;
+
+// 副本的品相
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Condition {
+    New,
+    #[default]
+    Good,
+    Fair,
+    Poor,
+    Damaged,
+}
+
+// 一本书的某个实体副本，借阅状态记录在副本上
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BookCopy {
+    pub copy_id: u32, // 副本编号，在同一 ISBN 下唯一
+    pub shelf_location: String, // 书架位置
+    pub condition: Condition, // 品相
+    pub borrowed_by: Option<String>, // 借阅者
+    #[serde(default)]
+    pub on_hold_for: Option<ShelvedHold>, // 在预约书架上为某位预约者保留
+}
+
+impl BookCopy {
+    pub fn new(copy_id: u32, shelf_location: String, condition: Condition) -> Self {
+        BookCopy { copy_id, shelf_location, condition, borrowed_by: None, on_hold_for: None }
+    }
+
+    // 未借出且没有为任何人保留
+    pub fn is_available(&self) -> bool {
+        self.borrowed_by.is_none() && self.on_hold_for.is_none()
+    }
+
+    fn is_held_for(&self, patron: &str) -> bool {
+        self.on_hold_for.as_ref().is_some_and(|hold| hold.patron == patron)
+    }
+}
+
+// 定义书籍结构体：一条书目信息，下挂若干实体副本
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "BookRecord")]
 pub struct Book {
     pub title: String, // 书籍的标题
     pub author: String, // 书籍的作者
     pub isbn: String, // 书籍的唯一标识符
-    pub borrowed_by: Option<String>, // 借阅者
+    pub category: Option<String>, // 分类，用于按分类计算罚款
+    #[serde(flatten)]
+    pub metadata: Metadata, // 合著者、出版信息、主题和分类号等
+    pub copies: Vec<BookCopy>, // 馆藏副本
+}
+
+// 反序列化用的书籍记录，兼容没有副本信息的旧数据文件
+#[derive(Deserialize)]
+struct BookRecord {
+    title: String,
+    author: String,
+    isbn: String,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(flatten)]
+    metadata: Metadata, // 旧数据中没有扩展信息，各字段取默认值
+    copies: Option<Vec<BookCopy>>,
+    #[serde(default)]
+    borrowed_by: Option<String>, // 旧格式中书籍级别的借阅者
+}
+
+impl From<BookRecord> for Book {
+    fn from(record: BookRecord) -> Self {
+        // 旧数据中每个 ISBN 只有一本书，迁移为单副本
+        let copies = record.copies.unwrap_or_else(|| {
+            let mut copy = BookCopy::new(1, String::new(), Condition::default());
+            copy.borrowed_by = record.borrowed_by;
+            vec![copy]
+        });
+        Book {
+            title: record.title,
+            author: record.author,
+            isbn: record.isbn,
+            category: record.category,
+            metadata: record.metadata,
+            copies,
+        }
+    }
 }
 
 impl Book {
+    // 创建一本只有一个副本的书籍
     pub fn new(title: String, author: String, isbn: String) -> Self {
-        Book { title, author, isbn, borrowed_by: None }
+        let copies = vec![BookCopy::new(1, String::new(), Condition::default())];
+        Book { title, author, isbn, category: None, metadata: Metadata::default(), copies }
+    }
+
+    // 全部作者：第一作者在前，其后是合著者
+    pub fn authors(&self) -> Vec<&str> {
+        std::iter::once(self.author.as_str())
+            .chain(self.metadata.co_authors.iter().map(String::as_str))
+            .collect()
+    }
+
+    pub fn has_author(&self, author: &str) -> bool {
+        self.authors().contains(&author)
+    }
+
+    // 添加一个副本，返回新副本的编号
+    pub fn add_copy(&mut self, shelf_location: String, condition: Condition) -> u32 {
+        let copy_id = self.copies.iter().map(|copy| copy.copy_id).max().unwrap_or(0) + 1;
+        self.copies.push(BookCopy::new(copy_id, shelf_location, condition));
+        copy_id
+    }
+
+    pub fn copy(&self, copy_id: u32) -> Option<&BookCopy> {
+        self.copies.iter().find(|copy| copy.copy_id == copy_id)
+    }
+
+    // 副本总数
+    pub fn total_copies(&self) -> usize {
+        self.copies.len()
+    }
+
+    // 可借的副本数
+    pub fn available_copies(&self) -> usize {
+        self.copies.iter().filter(|copy| copy.is_available()).count()
     }
+
+    // 当前借阅了这本书的所有借阅者
+    pub fn borrowers(&self) -> Vec<&str> {
+        self.copies.iter().filter_map(|copy| copy.borrowed_by.as_deref()).collect()
+    }
+
+    pub fn is_borrowed_by(&self, borrower: &str) -> bool {
+        self.copies.iter().any(|copy| copy.borrowed_by.as_deref() == Some(borrower))
+    }
+}
+
+// 对书目信息的修改，为 None 的字段保持不变
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BookUpdate {
+    pub title: Option<String>,
+    pub author: Option<String>,
+    pub category: Option<Option<String>>, // Some(None) 表示清除分类
+    pub isbn: Option<String>, // 更正 ISBN，借阅记录和预约会随之迁移
+    pub metadata: Option<Metadata>, // 整体替换扩展信息
 }
 
 // 定义图书馆结构体
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Library {
+    #[serde(deserialize_with = "deserialize_books")]
     books: HashMap<String, Book>,
+    #[serde(default)]
+    loans: BTreeMap<u64, Loan>, // 借阅记录，包括已归还的历史
+    #[serde(default)]
+    next_loan_id: u64,
+    #[serde(default)]
+    loan_policy: LoanPolicy,
+    #[serde(default)]
+    fine_policy: FinePolicy,
+    #[serde(default)]
+    ledger: Ledger, // 借阅者的罚款账本
+    #[serde(default)]
+    holds: HashMap<String, VecDeque<Hold>>, // 每个 ISBN 的预约队列，先到先得
+    #[serde(default)]
+    hold_policy: HoldPolicy,
+    #[serde(default)]
+    patrons: HashMap<String, Patron>, // 读者证号 -> 读者信息
+    #[serde(default = "patron::default_tier_limits")]
+    tier_limits: BTreeMap<MembershipTier, TierLimits>,
+    #[serde(skip)]
+    clock: SharedClock,
+    #[serde(skip)]
+    index: search::LazyIndex, // 标题和作者的全文索引
+    #[serde(skip)]
+    audit: audit::AuditHandle, // 记录每次修改的审计日志
+    #[serde(skip)]
+    subscribers: notify::Subscribers, // 接收领域事件的订阅者
+    #[serde(skip)]
+    deferred: Option<Vec<(NaiveDateTime, Event)>>, // 推迟发布的事件，见 defer_events
+}
+
+// 反序列化书目表时统一 ISBN 的写法，兼容旧数据中带连字符的键
+fn deserialize_books<'de, D>(deserializer: D) -> Result<HashMap<String, Book>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let books = HashMap::<String, Book>::deserialize(deserializer)?;
+    Ok(books
+        .into_values()
+        .map(|mut book| {
+            book.isbn = isbn::normalize(&book.isbn);
+            (book.isbn.clone(), book)
+        })
+        .collect())
+}
+
+impl Default for Library {
+    fn default() -> Self {
+        Library {
+            books: HashMap::new(),
+            loans: BTreeMap::new(),
+            next_loan_id: 0,
+            loan_policy: LoanPolicy::default(),
+            fine_policy: FinePolicy::default(),
+            ledger: Ledger::default(),
+            holds: HashMap::new(),
+            hold_policy: HoldPolicy::default(),
+            patrons: HashMap::new(),
+            tier_limits: patron::default_tier_limits(),
+            clock: SharedClock::default(),
+            index: search::LazyIndex::default(),
+            audit: audit::AuditHandle::default(),
+            subscribers: notify::Subscribers::default(),
+            deferred: None,
+        }
+    }
 }
 
 // 定义一个 Result 类型，用于表示图书馆操作的结果
@@ -36,8 +260,33 @@ pub enum LibraryError {
     BookNotFound,
     BookAlreadyBorrowed,
     BookNotBorrowed,
+    RenewalLimitReached, // 已达到续借次数上限
+    UnpaidFines { balance: Money, threshold: Money }, // 欠款超过上限，不能继续借阅
+    InvalidAmount, // 缴费或减免金额无效（为零或超过欠款）
+    BookOnHold, // 书籍已为预约队列中的其他读者保留
+    BookHasHolds { count: usize }, // 书籍仍有预约，不能移除
+    BookAvailable, // 有可借的副本，无需预约
+    HoldAlreadyPlaced, // 已在预约队列中
+    HoldNotFound, // 没有找到该读者的预约
+    PatronAlreadyExists,
+    PatronNotFound, // 未登记的读者
+    PatronSuspended, // 读者已被暂停借阅资格
+    MembershipExpired, // 会员已过期
+    LoanLimitReached { limit: usize }, // 在借册数已达会员等级上限
+    InvalidIsbn(String), // ISBN 格式或校验位不正确
+    InvalidClassification(String), // 无法识别的分类号
     IoError(String),
     SerdeError(String), // 添加处理 serde_json 错误的变体
+    UnknownStorageKind(String), // 无法识别的存储后端名称
+    UnknownFormat(String), // 无法识别的导入导出格式
+    MissingField(String), // 导入的记录缺少必需的字段
+    DatabaseError(String), // SQLite 存储的错误
+    // 数据文件损坏，已从最新的可读备份恢复；恢复出的图书馆随错误一起返回
+    RecoveredFromBackup {
+        library: Box<Library>,
+        backup: PathBuf,
+        cause: String,
+    },
 }
 
 // 将 io::Error 转换为 LibraryError
@@ -52,6 +301,18 @@ impl From<serde_json::Error> for LibraryError {
         LibraryError::SerdeError(error.to_string())
     }
 }
+// CSV 的读写错误
+impl From<csv::Error> for LibraryError {
+    fn from(error: csv::Error) -> Self {
+        LibraryError::SerdeError(error.to_string())
+    }
+}
+// SQLite 的错误
+impl From<rusqlite::Error> for LibraryError {
+    fn from(error: rusqlite::Error) -> Self {
+        LibraryError::DatabaseError(error.to_string())
+    }
+}
 // 实现 Display trait
 impl fmt::Display for LibraryError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -60,8 +321,35 @@ impl fmt::Display for LibraryError {
             LibraryError::BookNotFound => write!(f, "未找到书籍"),
             LibraryError::BookAlreadyBorrowed => write!(f, "书籍已被借出"),
             LibraryError::BookNotBorrowed => write!(f, "书籍未被借出"),
+            LibraryError::RenewalLimitReached => write!(f, "已达到续借次数上限"),
+            LibraryError::UnpaidFines { balance, threshold } => write!(
+                f,
+                "未缴罚款 {} 超过上限 {}",
+                format_money(*balance),
+                format_money(*threshold)
+            ),
+            LibraryError::InvalidAmount => write!(f, "金额无效"),
+            LibraryError::BookOnHold => write!(f, "书籍已为其他读者预约保留"),
+            LibraryError::BookHasHolds { count } => write!(f, "书籍仍有 {} 条预约", count),
+            LibraryError::BookAvailable => write!(f, "书籍有可借副本，无需预约"),
+            LibraryError::HoldAlreadyPlaced => write!(f, "已预约该书籍"),
+            LibraryError::HoldNotFound => write!(f, "未找到预约"),
+            LibraryError::PatronAlreadyExists => write!(f, "读者已存在"),
+            LibraryError::PatronNotFound => write!(f, "未找到读者"),
+            LibraryError::PatronSuspended => write!(f, "读者已被暂停借阅"),
+            LibraryError::MembershipExpired => write!(f, "会员已过期"),
+            LibraryError::LoanLimitReached { limit } => write!(f, "在借册数已达上限 {} 册", limit),
+            LibraryError::InvalidIsbn(isbn) => write!(f, "无效的 ISBN: {}", isbn),
+            LibraryError::InvalidClassification(number) => write!(f, "无效的分类号: {}", number),
             LibraryError::IoError(e) => write!(f, "IO 错误: {}", e),
             LibraryError::SerdeError(e) => write!(f, "Serde 错误: {}", e),
+            LibraryError::UnknownStorageKind(kind) => write!(f, "未知的存储后端: {}", kind),
+            LibraryError::UnknownFormat(format) => write!(f, "未知的格式: {}", format),
+            LibraryError::MissingField(field) => write!(f, "缺少字段: {}", field),
+            LibraryError::DatabaseError(e) => write!(f, "数据库错误: {}", e),
+            LibraryError::RecoveredFromBackup { backup, cause, .. } => {
+                write!(f, "数据文件损坏（{}），已从备份 {} 恢复", cause, backup.display())
+            }
         }
     }
 }
@@ -71,101 +359,703 @@ impl Error for LibraryError {}
 // 实现 Library 结构体的方法
 impl Library {
     pub fn new() -> Self {
-        Library { books: HashMap::new() }
+        Library::default()
+    }
+
+    // 使用指定的时钟创建图书馆
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        let mut library = Library::new();
+        library.set_clock(clock);
+        library
+    }
+
+    // 替换时钟，例如从文件加载后注入测试用的时钟
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.clock = SharedClock::new(clock);
+    }
+
+    // 按图书馆时钟的当天日期
+    pub fn today(&self) -> NaiveDate {
+        self.clock.today()
+    }
+
+    // 之后的每次修改都会写入该审计日志
+    pub fn set_audit_log(&mut self, log: Arc<Mutex<AuditLog>>) {
+        self.audit = audit::AuditHandle::new(log);
+    }
+
+    pub fn audit_log(&self) -> Option<&Arc<Mutex<AuditLog>>> {
+        self.audit.log()
+    }
+
+    // 注册订阅者，之后的每次修改完成后都会通知它，例如
+    // library.subscribe(Arc::new(StdoutSubscriber)) 打印提示语
+    pub fn subscribe(&mut self, subscriber: Arc<dyn Subscriber>) {
+        self.subscribers.push(subscriber);
+    }
+
+    // 修改前的快照。只有设置了审计日志时写入才可能失败，才需要快照
+    fn snapshot(&self) -> Option<Library> {
+        self.audit.log().map(|_| self.clone())
+    }
+
+    // 以当前时间把一次修改产生的事件写入审计日志，写入成功后再依次通知订阅者。
+    // 写入失败时恢复到修改前的快照，内存中不会留下日志里没有的修改。
+    // 推迟发布时只把事件留到 publish_deferred
+    fn commit(&mut self, snapshot: Option<Library>, events: Vec<Event>) -> LibraryResult<()> {
+        let at = self.clock.now();
+        let events: Vec<(NaiveDateTime, Event)> = events.into_iter().map(|event| (at, event)).collect();
+        if let Some(deferred) = &mut self.deferred {
+            deferred.extend(events);
+            return Ok(());
+        }
+        if let Err(e) = self.publish(&events) {
+            if let Some(snapshot) = snapshot {
+                *self = snapshot;
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    fn publish(&self, events: &[(NaiveDateTime, Event)]) -> LibraryResult<()> {
+        self.audit.record(events)?;
+        for (_, event) in events {
+            self.subscribers.notify(self, event);
+        }
+        Ok(())
+    }
+
+    // 之后的修改先不写审计日志、不通知订阅者。存储层借此先把修改写回存储，
+    // 写回成功后再调用 publish_deferred；写回失败时恢复到调用前的副本，推迟的事件随之丢弃
+    pub(crate) fn defer_events(&mut self) {
+        self.deferred.get_or_insert_with(Vec::new);
+    }
+
+    // 发布推迟的事件并恢复为立即发布。写入审计日志失败时返回错误，调用方负责撤销修改
+    pub(crate) fn publish_deferred(&mut self) -> LibraryResult<()> {
+        let events = self.deferred.take().unwrap_or_default();
+        self.publish(&events)
+    }
+
+    pub fn loan_policy(&self) -> &LoanPolicy {
+        &self.loan_policy
+    }
+
+    pub fn set_loan_policy(&mut self, policy: LoanPolicy) {
+        self.loan_policy = policy;
+    }
+
+    pub fn fine_policy(&self) -> &FinePolicy {
+        &self.fine_policy
+    }
+
+    pub fn set_fine_policy(&mut self, policy: FinePolicy) {
+        self.fine_policy = policy;
+    }
+
+    pub fn hold_policy(&self) -> &HoldPolicy {
+        &self.hold_policy
+    }
+
+    pub fn set_hold_policy(&mut self, policy: HoldPolicy) {
+        self.hold_policy = policy;
+    }
+
+    pub fn tier_limits(&self, tier: MembershipTier) -> Option<&TierLimits> {
+        self.tier_limits.get(&tier)
+    }
+
+    pub fn set_tier_limits(&mut self, tier: MembershipTier, limits: TierLimits) {
+        self.tier_limits.insert(tier, limits);
+    }
+
+    // 登记读者
+    pub fn register_patron(&mut self, patron: Patron) -> LibraryResult<()> {
+        if self.patrons.contains_key(&patron.id) {
+            return Err(LibraryError::PatronAlreadyExists);
+        }
+        let snapshot = self.snapshot();
+        self.patrons.insert(patron.id.clone(), patron.clone());
+        self.commit(snapshot, vec![Event::PatronRegistered { patron }])
+    }
+
+    pub fn get_patron(&self, id: &str) -> LibraryResult<&Patron> {
+        self.patrons.get(id).ok_or(LibraryError::PatronNotFound)
+    }
+
+    // 所有读者，按读者证号排序
+    pub fn patrons(&self) -> Vec<&Patron> {
+        let mut patrons: Vec<&Patron> = self.patrons.values().collect();
+        patrons.sort_by(|a, b| a.id.cmp(&b.id));
+        patrons
+    }
+
+    // 修改读者状态，例如暂停或恢复借阅资格
+    pub fn set_patron_status(&mut self, id: &str, status: PatronStatus) -> LibraryResult<()> {
+        let snapshot = self.snapshot();
+        let patron = self.patrons.get_mut(id).ok_or(LibraryError::PatronNotFound)?;
+        patron.status = status;
+        self.commit(snapshot, vec![Event::PatronStatusChanged { patron: id.to_string(), status }])
+    }
+
+    // 续期会员
+    pub fn renew_membership(&mut self, id: &str, expires: NaiveDate) -> LibraryResult<()> {
+        let snapshot = self.snapshot();
+        let patron = self.patrons.get_mut(id).ok_or(LibraryError::PatronNotFound)?;
+        patron.expires = expires;
+        self.commit(snapshot, vec![Event::MembershipRenewed { patron: id.to_string(), expires }])
+    }
+
+    // 检查读者能否借阅：已登记、未被暂停、会员未过期、在借册数未达上限
+    fn check_patron_can_borrow(&self, id: &str) -> LibraryResult<&Patron> {
+        let patron = self.get_patron(id)?;
+        if patron.status == PatronStatus::Suspended {
+            return Err(LibraryError::PatronSuspended);
+        }
+        if patron.is_expired(self.clock.today()) {
+            return Err(LibraryError::MembershipExpired);
+        }
+        if let Some(limits) = self.tier_limits.get(&patron.tier) {
+            let on_loan = self.loans.values().filter(|loan| loan.is_active() && loan.borrower == id).count();
+            if on_loan >= limits.max_loans {
+                return Err(LibraryError::LoanLimitReached { limit: limits.max_loans });
+            }
+        }
+        Ok(patron)
+    }
+
+    // 读者的借期：会员等级有单独设置时使用该设置，否则使用默认借期
+    fn loan_period_for(&self, id: &str) -> i64 {
+        self.patrons
+            .get(id)
+            .and_then(|patron| self.tier_limits.get(&patron.tier))
+            .and_then(|limits| limits.loan_period_days)
+            .unwrap_or(self.loan_policy.loan_period_days)
     }
 
     // 添加书籍，返回 LibraryResult
-    pub fn add_book(&mut self, book: Book) -> LibraryResult<()> {
+    pub fn add_book(&mut self, mut book: Book) -> LibraryResult<()> {
+        // 校验 ISBN，并统一为不带连字符的 13 位形式作为键
+        book.isbn = Isbn::parse(&book.isbn)?.as_str().to_string();
         // 检查书籍是否已存在
         if self.books.contains_key(&book.isbn) {
             Err(LibraryError::BookAlreadyExists)
         } else {
+            let snapshot = self.snapshot();
+            if let Some(index) = self.index.get_mut() {
+                index.insert(&book);
+            }
             self.books.insert(book.isbn.clone(), book.clone());
-            println!("书籍 '{}' 已添加到图书馆。", book.title);
-            Ok(())
+            self.commit(snapshot, vec![Event::BookAdded { book }])
+        }
+    }
+    // 从图书馆移除书籍，仍有副本借出或有预约时不能移除
+    pub fn remove_book(&mut self, isbn: &str) -> LibraryResult<Book> {
+        let isbn = &isbn::normalize(isbn);
+        let book = self.books.get(isbn).ok_or(LibraryError::BookNotFound)?;
+        if !book.borrowers().is_empty() {
+            return Err(LibraryError::BookAlreadyBorrowed);
+        }
+        let shelved = book.copies.iter().filter(|copy| copy.on_hold_for.is_some()).count();
+        let queued = self.holds.get(isbn).map_or(0, VecDeque::len);
+        if shelved + queued > 0 {
+            return Err(LibraryError::BookHasHolds { count: shelved + queued });
+        }
+        let snapshot = self.snapshot();
+        if let Some(index) = self.index.get_mut() {
+            index.remove(isbn);
         }
+        self.holds.remove(isbn);
+        let book = self.books.remove(isbn).unwrap();
+        self.commit(snapshot, vec![Event::BookRemoved { isbn: isbn.clone() }])?;
+        Ok(book)
     }
+
+    // 修改书目信息。更正 ISBN 时会校验新 ISBN，并把借阅记录和预约队列迁移到新的键下
+    pub fn update_book(&mut self, isbn: &str, update: BookUpdate) -> LibraryResult<()> {
+        let isbn = &isbn::normalize(isbn);
+        if !self.books.contains_key(isbn) {
+            return Err(LibraryError::BookNotFound);
+        }
+        let new_isbn = match &update.isbn {
+            Some(new_isbn) => Isbn::parse(new_isbn)?.as_str().to_string(),
+            None => isbn.clone(),
+        };
+        if new_isbn != *isbn && self.books.contains_key(&new_isbn) {
+            return Err(LibraryError::BookAlreadyExists);
+        }
+
+        let snapshot = self.snapshot();
+        let mut book = self.books.remove(isbn).unwrap();
+        if let Some(title) = update.title {
+            book.title = title;
+        }
+        if let Some(author) = update.author {
+            book.author = author;
+        }
+        if let Some(category) = update.category {
+            book.category = category;
+        }
+        if let Some(metadata) = update.metadata {
+            book.metadata = metadata;
+        }
+        if new_isbn != *isbn {
+            book.isbn = new_isbn.clone();
+            for loan in self.loans.values_mut().filter(|loan| loan.isbn == *isbn) {
+                loan.isbn = new_isbn.clone();
+            }
+            if let Some(queue) = self.holds.remove(isbn) {
+                self.holds.insert(new_isbn.clone(), queue);
+            }
+        }
+        if let Some(index) = self.index.get_mut() {
+            index.remove(isbn);
+            index.insert(&book);
+        }
+        let event = Event::BookUpdated {
+            isbn: isbn.clone(),
+            new_isbn: new_isbn.clone(),
+            title: book.title.clone(),
+            author: book.author.clone(),
+            category: book.category.clone(),
+            metadata: book.metadata.clone(),
+        };
+        self.books.insert(new_isbn, book);
+        self.commit(snapshot, vec![event])
+    }
+
     // 通过 ISBN 查找书籍，返回 LibraryResult
     pub fn find_book_by_isbn(&self, isbn: &str) -> LibraryResult<&Book> {
+        let isbn = &isbn::normalize(isbn);
         self.books.get(isbn).ok_or(LibraryError::BookNotFound)
     }
 
-    // 通过作者查找书籍
+    // 通过作者查找书籍，合著者也算
     pub fn find_books_by_author(&self, author: &str) -> Vec<&Book> {
-        self.books.values().filter(|book| book.author == author).collect() // 返回所有作者匹配的书籍
+        self.books.values().filter(|book| book.has_author(author)).collect() // 返回所有作者匹配的书籍
+    }
+
+    // 按书目信息筛选，结果按标题排序
+    pub fn filter_books(&self, filter: &BookFilter) -> Vec<&Book> {
+        let mut books: Vec<&Book> = self.books.values().filter(|book| filter.matches(book)).collect();
+        books.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.isbn.cmp(&b.isbn)));
+        books
+    }
+
+    // 按分类、主题、语言等分组浏览，没有对应信息的书籍归入 metadata::UNCLASSIFIED
+    pub fn browse(&self, facet: Facet) -> BTreeMap<String, Vec<&Book>> {
+        metadata::browse(self.books.values(), facet)
+    }
+
+    // 在标题和作者中全文检索，按相关度排序。词之间默认为 AND，
+    // 支持 OR 和以 * 结尾的前缀匹配，例如 "tolk* OR 指环王"
+    pub fn search(&self, query: &str) -> Vec<SearchResult<'_>> {
+        self.index
+            .get_or_build(self.books.values())
+            .search(query)
+            .into_iter()
+            .filter_map(|(isbn, score)| self.books.get(&isbn).map(|book| SearchResult { book, score }))
+            .collect()
+    }
+
+    // 容忍拼写错误的模糊检索，同时比较标题和作者，作者名支持
+    // "JRR Tolkien"、"Tolkien, J.R.R." 等写法。返回相似度不低于 threshold 的候选，
+    // 按相似度从高到低排序，threshold 一般取 DEFAULT_FUZZY_THRESHOLD
+    pub fn fuzzy_search(&self, query: &str, threshold: f64) -> Vec<FuzzyMatch<'_>> {
+        fuzzy::fuzzy_search(self.books.values(), query, threshold)
     }
 
-    // 列出所有书籍
-    pub fn list_all_books(&self) {
+    // 所有书籍的清单文本，按标题排序
+    pub fn format_books(&self) -> String {
         if self.books.is_empty() {
-            println!("图书馆目前没有书籍。");
-        } else {
-            println!("图书馆现有书籍: ");
-            for book in self.books.values() {
-                let status = if book.borrowed_by.is_some() { "（已借出）" } else { "" };
-                println!("- {}，作者：{}，ISBN：{}{}", book.title, book.author, book.isbn, status);
-            }
+            return "图书馆目前没有书籍。".to_string();
         }
+        let mut books: Vec<&Book> = self.books.values().collect();
+        books.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.isbn.cmp(&b.isbn)));
+        let mut text = "图书馆现有书籍: ".to_string();
+        for book in books {
+            text.push_str(&format!(
+                "\n- {}，作者：{}，ISBN：{}（可借 {}/{}）",
+                book.title,
+                book.author,
+                isbn::display(&book.isbn),
+                book.available_copies(),
+                book.total_copies()
+            ));
+        }
+        text
+    }
+
+    // 为已有书籍添加一个副本，返回副本编号
+    pub fn add_copy(&mut self, isbn: &str, shelf_location: String, condition: Condition) -> LibraryResult<u32> {
+        let isbn = &isbn::normalize(isbn);
+        let snapshot = self.snapshot();
+        let book = self.books.get_mut(isbn).ok_or(LibraryError::BookNotFound)?;
+        let copy_id = book.add_copy(shelf_location.clone(), condition);
+        let mut events = vec![Event::CopyAdded { isbn: isbn.clone(), copy_id, shelf_location, condition }];
+        // 有人在排队时，新副本直接放上预约书架
+        self.shelve_for_next_hold(isbn, copy_id, &mut events);
+        self.commit(snapshot, events)?;
+        Ok(copy_id)
     }
 
-    // 借阅书籍
-    pub fn borrow_book(&mut self, isbn: &str, borrower: &str) -> LibraryResult<()> {
+    // 借阅书籍：任选一个可借的副本，返回新建的借阅记录。
+    // 有预约时，只有预约队列的第一位（或预约书架上保留给他的）读者可以借阅
+    pub fn borrow_book(&mut self, isbn: &str, borrower: &str) -> LibraryResult<Loan> {
+        let isbn = &isbn::normalize(isbn);
+        self.check_patron_can_borrow(borrower)?;
+        // 欠款超过上限的借阅者不能继续借阅
+        let balance = self.ledger.balance(borrower);
+        if balance > self.fine_policy.block_threshold {
+            return Err(LibraryError::UnpaidFines { balance, threshold: self.fine_policy.block_threshold });
+        }
+        if !self.books.contains_key(isbn) {
+            return Err(LibraryError::BookNotFound);
+        }
+        self.expire_holds_for(isbn)?;
+        let queue = self.holds.get(isbn);
+        let queue_empty = queue.is_none_or(|queue| queue.is_empty());
+        let is_head = queue.and_then(|queue| queue.front()).is_some_and(|hold| hold.patron == borrower);
+        let now = self.clock.now();
+        let due = now.date() + Duration::days(self.loan_period_for(borrower));
+        let snapshot = self.snapshot();
         // 获取书籍的可变引用
-        match self.books.get_mut(isbn) {
-            // 如果书籍存在，检查是否已被借阅
-            Some(book) => {
-                // 如果书籍已被借阅，返回错误
-                if book.borrowed_by.is_some() {
-                    Err(LibraryError::BookAlreadyBorrowed)
-                } else {
-                    // 如果书籍未被借阅，设置借阅者
-                    book.borrowed_by = Some(borrower.to_string());
-                    println!("书籍 '{}' 已被 {} 借阅。", book.title, borrower);
-                    Ok(())
-                }
+        let book = self.books.get_mut(isbn).ok_or(LibraryError::BookNotFound)?;
+        // 优先借出预约书架上为该读者保留的副本，否则查找一个未被借阅的副本
+        let held = book.copies.iter().position(|copy| copy.is_held_for(borrower));
+        let free = book.copies.iter().position(|copy| copy.is_available());
+        let index = match (held, free) {
+            (Some(index), _) => index,
+            (None, Some(index)) if queue_empty || is_head => index,
+            // 预约生效期间，其他读者不能借走
+            (None, Some(_)) => return Err(LibraryError::BookOnHold),
+            (None, None) if book.copies.iter().any(|copy| copy.on_hold_for.is_some()) => {
+                return Err(LibraryError::BookOnHold)
+            }
+            // 如果所有副本都已被借阅，返回错误
+            (None, None) => return Err(LibraryError::BookAlreadyBorrowed),
+        };
+        let copy = &mut book.copies[index];
+        copy.on_hold_for = None;
+        copy.borrowed_by = Some(borrower.to_string());
+        let copy_id = copy.copy_id;
+        if held.is_none() && is_head {
+            if let Some(queue) = self.holds.get_mut(isbn) {
+                queue.pop_front();
             }
-            // 如果书籍不存在，返回错误
-            None => Err(LibraryError::BookNotFound),
         }
+        self.next_loan_id += 1;
+        let loan = Loan {
+            id: self.next_loan_id,
+            isbn: isbn.to_string(),
+            copy_id,
+            borrower: borrower.to_string(),
+            checked_out: now,
+            due,
+            renewals: 0,
+            returned: None,
+        };
+        self.loans.insert(loan.id, loan.clone());
+        let event = Event::Borrowed {
+            isbn: loan.isbn.clone(),
+            patron: loan.borrower.clone(),
+            copy_id,
+            loan_id: loan.id,
+            due,
+        };
+        self.commit(snapshot, vec![event])?;
+        Ok(loan)
     }
 
-    // 归还书籍
-    pub fn return_book(&mut self, isbn: &str) -> LibraryResult<()> {
+    // 归还书籍：归还该借阅者借走的副本，逾期时按罚款规则计入借阅者账户
+    pub fn return_book(&mut self, isbn: &str, borrower: &str) -> LibraryResult<ReturnReceipt> {
+        let isbn = &isbn::normalize(isbn);
+        let now = self.clock.now();
+        let snapshot = self.snapshot();
         // 获取书籍的可变引用
-        match self.books.get_mut(isbn) {
-            // 如果书籍存在，检查是否已被归还
+        let (copy_id, category) = match self.books.get_mut(isbn) {
+            // 如果书籍存在，查找该借阅者借走的副本
             Some(book) => {
-                // 如果书籍已被归还，设置 借阅者为 None
-                if book.borrowed_by.is_some() {
-                    println!("书籍 '{}' 已归还。", book.title);
-                    book.borrowed_by = None;
-                    Ok(())
-                } else {
-                    Err(LibraryError::BookNotBorrowed)
+                match book.copies.iter_mut().find(|copy| copy.borrowed_by.as_deref() == Some(borrower)) {
+                    Some(copy) => {
+                        copy.borrowed_by = None;
+                        (copy.copy_id, book.category.clone())
+                    }
+                    None => return Err(LibraryError::BookNotBorrowed),
                 }
             }
-            None => Err(LibraryError::BookNotFound),
+            None => return Err(LibraryError::BookNotFound),
+        };
+        let mut receipt = ReturnReceipt { copy_id, loan_id: None, fine: 0, hold_for: None };
+        // 旧数据中的借阅没有借阅记录，只需要更新副本状态
+        if let Some(loan) = self.active_loan_mut(isbn, copy_id) {
+            loan.returned = Some(now);
+            receipt.loan_id = Some(loan.id);
+            let days_overdue = loan.days_overdue(now.date());
+            receipt.fine = self.fine_policy.calculate(days_overdue, category.as_deref());
+        }
+        if let (Some(loan_id), fine) = (receipt.loan_id, receipt.fine) {
+            if fine > 0 {
+                self.ledger.record(LedgerEntry {
+                    borrower: borrower.to_string(),
+                    amount: fine,
+                    kind: LedgerKind::Fine { loan_id },
+                    at: now,
+                });
+            }
+        }
+        let mut events = vec![Event::Returned {
+            isbn: isbn.clone(),
+            patron: borrower.to_string(),
+            copy_id,
+            fine: receipt.fine,
+        }];
+        // 先归还，再把副本放上预约书架
+        receipt.hold_for = self.shelve_for_next_hold(isbn, copy_id, &mut events);
+        self.commit(snapshot, events)?;
+        Ok(receipt)
+    }
+
+    // 预约书籍，返回在队列中的位置（从 1 开始）
+    pub fn place_hold(&mut self, isbn: &str, patron: &str) -> LibraryResult<usize> {
+        let isbn = &isbn::normalize(isbn);
+        self.get_patron(patron)?;
+        if !self.books.contains_key(isbn) {
+            return Err(LibraryError::BookNotFound);
+        }
+        self.expire_holds_for(isbn)?;
+        let book = &self.books[isbn];
+        if book.copies.iter().any(|copy| copy.is_held_for(patron)) {
+            return Err(LibraryError::HoldAlreadyPlaced);
+        }
+        let queue = self.holds.get(isbn);
+        if queue.is_some_and(|queue| queue.iter().any(|hold| hold.patron == patron)) {
+            return Err(LibraryError::HoldAlreadyPlaced);
+        }
+        if queue.is_none_or(|queue| queue.is_empty()) && book.available_copies() > 0 {
+            return Err(LibraryError::BookAvailable);
+        }
+        let snapshot = self.snapshot();
+        let queue = self.holds.entry(isbn.to_string()).or_default();
+        queue.push_back(Hold { patron: patron.to_string(), placed_at: self.clock.now() });
+        let position = queue.len();
+        self.commit(snapshot, vec![Event::HoldPlaced { isbn: isbn.clone(), patron: patron.to_string() }])?;
+        Ok(position)
+    }
+
+    // 取消预约；如果书已在预约书架上，则让给队列中的下一位
+    pub fn cancel_hold(&mut self, isbn: &str, patron: &str) -> LibraryResult<()> {
+        let isbn = &isbn::normalize(isbn);
+        let snapshot = self.snapshot();
+        let book = self.books.get_mut(isbn).ok_or(LibraryError::BookNotFound)?;
+        let mut events = vec![Event::HoldCancelled { isbn: isbn.clone(), patron: patron.to_string() }];
+        if let Some(copy) = book.copies.iter_mut().find(|copy| copy.is_held_for(patron)) {
+            copy.on_hold_for = None;
+            let copy_id = copy.copy_id;
+            self.shelve_for_next_hold(isbn, copy_id, &mut events);
+            return self.commit(snapshot, events);
+        }
+        let queue = self.holds.get_mut(isbn).ok_or(LibraryError::HoldNotFound)?;
+        let position = queue
+            .iter()
+            .position(|hold| hold.patron == patron)
+            .ok_or(LibraryError::HoldNotFound)?;
+        queue.remove(position);
+        self.commit(snapshot, events)
+    }
+
+    // 某本书的预约队列
+    pub fn holds_for(&self, isbn: &str) -> Vec<&Hold> {
+        let isbn = &isbn::normalize(isbn);
+        self.holds.get(isbn).map(|queue| queue.iter().collect()).unwrap_or_default()
+    }
+
+    // 读者在预约队列中的位置（从 1 开始）
+    pub fn hold_position(&self, isbn: &str, patron: &str) -> Option<usize> {
+        let isbn = &isbn::normalize(isbn);
+        self.holds.get(isbn)?.iter().position(|hold| hold.patron == patron).map(|index| index + 1)
+    }
+
+    // 清理所有过期未取的预约，返回被取消的 (ISBN, 读者)
+    pub fn expire_holds(&mut self) -> LibraryResult<Vec<(String, String)>> {
+        let isbns: Vec<String> = self.books.keys().cloned().collect();
+        let mut expired = Vec::new();
+        for isbn in isbns {
+            for patron in self.expire_holds_for(&isbn)? {
+                expired.push((isbn.clone(), patron));
+            }
+        }
+        Ok(expired)
+    }
+
+    // 取消某本书所有过期未取的预约，返回过期的读者
+    fn expire_holds_for(&mut self, isbn: &str) -> LibraryResult<Vec<String>> {
+        let today = self.clock.today();
+        let expired: Vec<String> = self
+            .books
+            .get(isbn)
+            .map(|book| {
+                book.copies
+                    .iter()
+                    .filter_map(|copy| copy.on_hold_for.as_ref())
+                    .filter(|hold| hold.is_expired(today))
+                    .map(|hold| hold.patron.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        for patron in &expired {
+            self.expire_hold(isbn, patron)?;
+        }
+        Ok(expired)
+    }
+
+    // 取消为某位读者保留的副本，副本让给队列中的下一位。每次过期都写入审计日志，
+    // 重放时按记录的时间取消，重新上架的保留期限与当初一致
+    pub(crate) fn expire_hold(&mut self, isbn: &str, patron: &str) -> LibraryResult<()> {
+        let snapshot = self.snapshot();
+        let book = self.books.get_mut(isbn).ok_or(LibraryError::BookNotFound)?;
+        let copy = book.copies.iter_mut().find(|copy| copy.is_held_for(patron)).ok_or(LibraryError::HoldNotFound)?;
+        copy.on_hold_for = None;
+        let copy_id = copy.copy_id;
+        let mut events = vec![Event::HoldExpired { isbn: isbn.to_string(), patron: patron.to_string() }];
+        self.shelve_for_next_hold(isbn, copy_id, &mut events);
+        self.commit(snapshot, events)
+    }
+
+    // 将空闲的副本放上预约书架，保留给队列中的第一位，返回该读者
+    fn shelve_for_next_hold(&mut self, isbn: &str, copy_id: u32, events: &mut Vec<Event>) -> Option<String> {
+        let hold = self.holds.get_mut(isbn)?.pop_front()?;
+        let expires = self.clock.today() + Duration::days(self.hold_policy.pickup_days);
+        let book = self.books.get_mut(isbn)?;
+        let copy = book.copies.iter_mut().find(|copy| copy.copy_id == copy_id)?;
+        copy.on_hold_for = Some(ShelvedHold { patron: hold.patron.clone(), expires });
+        events.push(Event::HoldShelved { isbn: isbn.to_string(), copy_id, patron: hold.patron.clone(), expires });
+        Some(hold.patron)
+    }
+
+    // 借阅者当前的欠款
+    pub fn balance(&self, borrower: &str) -> Money {
+        self.ledger.balance(borrower)
+    }
+
+    pub fn ledger(&self) -> &Ledger {
+        &self.ledger
+    }
+
+    // 缴纳罚款，返回剩余欠款
+    pub fn pay_fine(&mut self, borrower: &str, amount: Money) -> LibraryResult<Money> {
+        self.settle(borrower, amount, LedgerKind::Payment)
+    }
+
+    // 减免罚款，返回剩余欠款
+    pub fn waive_fine(&mut self, borrower: &str, amount: Money, reason: &str) -> LibraryResult<Money> {
+        self.settle(borrower, amount, LedgerKind::Waiver { reason: reason.to_string() })
+    }
+
+    fn settle(&mut self, borrower: &str, amount: Money, kind: LedgerKind) -> LibraryResult<Money> {
+        let balance = self.ledger.balance(borrower);
+        if amount == 0 || amount > balance {
+            return Err(LibraryError::InvalidAmount);
+        }
+        let event = match &kind {
+            LedgerKind::Waiver { reason } => {
+                Event::FineWaived { patron: borrower.to_string(), amount, reason: reason.clone() }
+            }
+            _ => Event::FinePaid { patron: borrower.to_string(), amount },
+        };
+        let snapshot = self.snapshot();
+        self.ledger.record(LedgerEntry {
+            borrower: borrower.to_string(),
+            amount,
+            kind,
+            at: self.clock.now(),
+        });
+        self.commit(snapshot, vec![event])?;
+        Ok(balance - amount)
+    }
+
+    // 续借：从今天或原应还日期（取较晚者）起重新计算借期，返回新的应还日期
+    pub fn renew_book(&mut self, isbn: &str, borrower: &str) -> LibraryResult<NaiveDate> {
+        let isbn = &isbn::normalize(isbn);
+        let today = self.clock.today();
+        let max_renewals = self.loan_policy.max_renewals;
+        let loan_period_days = self.loan_period_for(borrower);
+        if !self.books.contains_key(isbn) {
+            return Err(LibraryError::BookNotFound);
         }
+        let snapshot = self.snapshot();
+        let loan = self
+            .loans
+            .values_mut()
+            .find(|loan| loan.is_active() && loan.isbn == *isbn && loan.borrower == borrower)
+            .ok_or(LibraryError::BookNotBorrowed)?;
+        if loan.renewals >= max_renewals {
+            return Err(LibraryError::RenewalLimitReached);
+        }
+        loan.renewals += 1;
+        loan.due = loan.due.max(today) + Duration::days(loan_period_days);
+        let due = loan.due;
+        self.commit(snapshot, vec![Event::Renewed { isbn: isbn.clone(), patron: borrower.to_string(), due }])?;
+        Ok(due)
+    }
+
+    fn active_loan_mut(&mut self, isbn: &str, copy_id: u32) -> Option<&mut Loan> {
+        self.loans
+            .values_mut()
+            .find(|loan| loan.is_active() && loan.isbn == isbn && loan.copy_id == copy_id)
+    }
+
+    // 所有借阅记录，按借阅编号排序
+    pub fn loans(&self) -> Vec<&Loan> {
+        self.loans.values().collect()
     }
+
+    // 尚未归还的借阅
+    pub fn active_loans(&self) -> Vec<&Loan> {
+        self.loans.values().filter(|loan| loan.is_active()).collect()
+    }
+
+    // 某位借阅者的全部借阅记录
+    pub fn loans_for(&self, borrower: &str) -> Vec<&Loan> {
+        self.loans.values().filter(|loan| loan.borrower == borrower).collect()
+    }
+
+    // 截至给定日期逾期未还的借阅
+    pub fn overdue_loans(&self, as_of: NaiveDate) -> Vec<&Loan> {
+        self.loans.values().filter(|loan| loan.is_overdue(as_of)).collect()
+    }
+
     // 查找被特定用户借阅的书籍
     pub fn find_borrowed_by(&self, borrower: &str) -> Vec<&Book> {
         self.books
             .values()
-            .filter(|book| book.borrowed_by == Some(borrower.to_string()))
+            .filter(|book| book.is_borrowed_by(borrower))
             .collect()
     }    
 }
 
-// 定义一个 Trait 用于抽象不同的图书存储方式
+// 定义一个 Trait 用于抽象不同的图书存储方式。
+// 查询返回书籍的副本而不是引用，这样数据库等外部存储也能实现
 pub trait BookStore{
     // 添加书籍
     fn add_book(&mut self, book: Book) -> LibraryResult<()>;
+    // 移除书籍，返回被移除的书籍
+    fn remove_book(&mut self, isbn: &str) -> LibraryResult<Book>;
+    // 修改书目信息
+    fn update_book(&mut self, isbn: &str, update: BookUpdate) -> LibraryResult<()>;
     // 通过 ISBN 查找书籍
-    fn get_book(&self, isbn: &str) -> LibraryResult<&Book>;
-    // 通过作者查找书籍
-    fn get_books_by_author(&self, author: &str) -> Vec<&Book>;
-    // 列出所有书籍
-    fn all_books(&self) -> Vec<&Book>;
+    fn get_book(&self, isbn: &str) -> LibraryResult<Book>;
+    // 通过作者查找书籍，按 ISBN 排序
+    fn get_books_by_author(&self, author: &str) -> LibraryResult<Vec<Book>>;
+    // 列出所有书籍，按 ISBN 排序
+    fn all_books(&self) -> LibraryResult<Vec<Book>>;
+    // 登记读者，借阅前读者必须已登记
+    fn register_patron(&mut self, patron: Patron) -> LibraryResult<()>;
+    // 借阅书籍，返回借阅记录
+    fn borrow_book(&mut self, isbn: &str, borrower: &str) -> LibraryResult<Loan>;
+    // 归还书籍，返回归还回执
+    fn return_book(&mut self, isbn: &str, borrower: &str) -> LibraryResult<ReturnReceipt>;
 }
 
 // 实现 BookStore Trait 的默认方法
@@ -174,50 +1064,54 @@ impl BookStore for Library {
         Library::add_book(self, book)
     }
 
-    fn get_book(&self, isbn: &str) -> LibraryResult<&Book> {
-        Library::find_book_by_isbn(self, isbn)
+    fn remove_book(&mut self, isbn: &str) -> LibraryResult<Book> {
+        Library::remove_book(self, isbn)
     }
 
-    fn get_books_by_author(&self, author: &str) -> Vec<&Book> {
-        Library::find_books_by_author(self, author)
+    fn update_book(&mut self, isbn: &str, update: BookUpdate) -> LibraryResult<()> {
+        Library::update_book(self, isbn, update)
     }
 
-    fn all_books(&self) -> Vec<&Book> {
-        self.books.values().collect()
+    fn get_book(&self, isbn: &str) -> LibraryResult<Book> {
+        Library::find_book_by_isbn(self, isbn).cloned()
     }
-}
 
-// 定义一个模块用于图书数据的持久化
-pub mod persistence {
-    use std::fs::File;
-    use std::io::{BufReader, BufWriter};
-    use super::*;
+    fn get_books_by_author(&self, author: &str) -> LibraryResult<Vec<Book>> {
+        Ok(sorted_by_isbn(Library::find_books_by_author(self, author)))
+    }
 
-    const LIBRARY_DATA_FILE: &str = "library_data.json";
+    fn all_books(&self) -> LibraryResult<Vec<Book>> {
+        Ok(sorted_by_isbn(self.books.values().collect()))
+    }
 
-    pub fn save_library_to_file(library: &Library) -> LibraryResult<()> {
-        let file = File::create(LIBRARY_DATA_FILE)?;
-        let writer = BufWriter::new(file);
-        serde_json::to_writer_pretty(writer, library)
-            .map_err(|e| LibraryError::SerdeError(e.to_string()))?; // 显式转换
-        Ok(())
+    fn register_patron(&mut self, patron: Patron) -> LibraryResult<()> {
+        Library::register_patron(self, patron)
     }
 
-    pub fn load_library_from_file() -> LibraryResult<Library> {
-        match File::open(LIBRARY_DATA_FILE) {
-            Ok(file) => {
-                let reader = BufReader::new(file);
-                serde_json::from_reader(reader)
-                    .map_err(|e| LibraryError::SerdeError(e.to_string())) // 显式转换
-            }
-            Err(_) => {
-                println!("未找到图书馆数据文件，创建一个新的图书馆。");
-                Ok(Library::new())
-            }
-        }
+    fn borrow_book(&mut self, isbn: &str, borrower: &str) -> LibraryResult<Loan> {
+        Library::borrow_book(self, isbn, borrower)
+    }
+
+    fn return_book(&mut self, isbn: &str, borrower: &str) -> LibraryResult<ReturnReceipt> {
+        Library::return_book(self, isbn, borrower)
     }
 }
 
+fn sorted_by_isbn(mut books: Vec<&Book>) -> Vec<Book> {
+    books.sort_by(|a, b| a.isbn.cmp(&b.isbn));
+    books.into_iter().cloned().collect()
+}
+
+// 定义一个模块用于图书数据的持久化
+pub mod persistence;
+// 基于 SQLite 的图书存储
+pub mod sqlite;
+// 对外提供 JSON 接口的 HTTP 服务
+pub mod server;
+
+#[cfg(test)]
+mod store_contract;
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -225,15 +1119,15 @@ mod tests {
     #[test]
     fn test_add_and_find_book() {
         let mut library = Library::new();
-        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "123-456".to_string());
+        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "9780618640157".to_string());
         library.add_book(book1.clone()).unwrap();
-        assert_eq!(library.find_book_by_isbn("123-456").unwrap().title, "Rust Programming");
+        assert_eq!(library.find_book_by_isbn("9780618640157").unwrap().title, "Rust Programming");
     }
 
     #[test]
     fn test_add_duplicate_book() {
         let mut library = Library::new();
-        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "123-456".to_string());
+        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "9780618640157".to_string());
         library.add_book(book1.clone()).unwrap();
         let result = library.add_book(book1);
         assert_eq!(result, Err(LibraryError::BookAlreadyExists));
@@ -242,30 +1136,523 @@ mod tests {
     #[test]
     fn test_borrow_and_return_book() {
         let mut library = Library::new();
-        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "123-456".to_string());
+        store_contract::register(&mut library, "Alice", MembershipTier::Standard);
+        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "9780618640157".to_string());
         library.add_book(book1.clone()).unwrap();
 
-        library.borrow_book("123-456", "Alice").unwrap();
-        assert_eq!(library.find_book_by_isbn("123-456").unwrap().borrowed_by, Some("Alice".to_string()));
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        assert_eq!(library.find_book_by_isbn("9780618640157").unwrap().borrowers(), vec!["Alice"]);
 
-        library.return_book("123-456").unwrap();
-        assert_eq!(library.find_book_by_isbn("123-456").unwrap().borrowed_by, None);
+        library.return_book("9780618640157", "Alice").unwrap();
+        assert!(library.find_book_by_isbn("9780618640157").unwrap().borrowers().is_empty());
     }
 
     #[test]
     fn test_borrow_nonexistent_book() {
         let mut library = Library::new();
+        store_contract::register(&mut library, "Alice", MembershipTier::Standard);
         let result = library.borrow_book("nonexistent", "Alice");
-        assert_eq!(result, Err(LibraryError::BookNotFound));
+        assert_eq!(result.err(), Some(LibraryError::BookNotFound));
     }
 
     #[test]
     fn test_borrow_already_borrowed_book() {
         let mut library = Library::new();
-        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "123-456".to_string());
+        store_contract::register_all(&mut library, &["Alice", "Bob"]);
+        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "9780618640157".to_string());
         library.add_book(book1.clone()).unwrap();
-        library.borrow_book("123-456", "Alice").unwrap();
-        let result = library.borrow_book("123-456", "Bob");
-        assert_eq!(result, Err(LibraryError::BookAlreadyBorrowed));
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        let result = library.borrow_book("9780618640157", "Bob");
+        assert_eq!(result.err(), Some(LibraryError::BookAlreadyBorrowed));
+    }
+
+    #[test]
+    fn test_borrow_picks_any_available_copy() {
+        let mut library = Library::new();
+        store_contract::register_all(&mut library, &["Alice", "Bob", "Carol", "Dave"]);
+        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "9780618640157".to_string());
+        library.add_book(book1).unwrap();
+        library.add_copy("9780618640157", "A-01".to_string(), Condition::New).unwrap();
+        library.add_copy("9780618640157", "A-02".to_string(), Condition::Fair).unwrap();
+
+        let first = library.borrow_book("9780618640157", "Alice").unwrap().copy_id;
+        let second = library.borrow_book("9780618640157", "Bob").unwrap().copy_id;
+        assert_ne!(first, second);
+
+        let book = library.find_book_by_isbn("9780618640157").unwrap();
+        assert_eq!((book.available_copies(), book.total_copies()), (1, 3));
+
+        library.borrow_book("9780618640157", "Carol").unwrap();
+        assert_eq!(library.borrow_book("9780618640157", "Dave").err(), Some(LibraryError::BookAlreadyBorrowed));
+
+        // 归还的是 Bob 借走的那个副本
+        assert_eq!(library.return_book("9780618640157", "Bob").unwrap().copy_id, second);
+        assert_eq!(library.return_book("9780618640157", "Bob"), Err(LibraryError::BookNotBorrowed));
+        assert_eq!(library.find_book_by_isbn("9780618640157").unwrap().available_copies(), 1);
+    }
+
+    #[test]
+    fn test_add_copy_to_missing_book() {
+        let mut library = Library::new();
+        let result = library.add_copy("nonexistent", "A-01".to_string(), Condition::Good);
+        assert_eq!(result, Err(LibraryError::BookNotFound));
+    }
+
+    #[test]
+    fn test_legacy_book_migrates_to_single_copy() {
+        let json = r#"{"books": {"978-0618640157": {
+            "title": "Rust Programming", "author": "John Doe",
+            "isbn": "978-0618640157", "borrowed_by": "Alice"
+        }}}"#;
+        let library: Library = serde_json::from_str(json).unwrap();
+        let book = library.find_book_by_isbn("9780618640157").unwrap();
+        assert_eq!(book.total_copies(), 1);
+        assert_eq!(book.copies[0].copy_id, 1);
+        assert_eq!(book.borrowers(), vec!["Alice"]);
+    }
+
+    #[test]
+    fn test_metadata_defaults_and_filters() {
+        // 没有扩展信息的旧数据照常读取，序列化时也不会写出空字段
+        let json = r#"{"books": {"9780618640157": {
+            "title": "The Hobbit", "author": "J.R.R. Tolkien", "isbn": "9780618640157", "copies": []
+        }}}"#;
+        let mut library: Library = serde_json::from_str(json).unwrap();
+        let hobbit = library.find_book_by_isbn("9780618640157").unwrap();
+        assert_eq!(hobbit.metadata, Metadata::default());
+        let saved = serde_json::to_value(hobbit).unwrap();
+        assert!(saved.get("co_authors").is_none() && saved.get("classification").is_none());
+
+        let mut lotr = Book::new("指环王".to_string(), "托尔金".to_string(), "9787111213826".to_string());
+        lotr.metadata = Metadata {
+            co_authors: vec!["丁棣".to_string()],
+            year: Some(2001),
+            language: Some("zh".to_string()),
+            subjects: vec!["Fantasy".to_string()],
+            classification: Some("I561.45".parse().unwrap()),
+            ..Metadata::default()
+        };
+        library.add_book(lotr).unwrap();
+        assert_eq!(library.find_books_by_author("丁棣").len(), 1);
+
+        let filter = |filter: BookFilter| {
+            library.filter_books(&filter).iter().map(|book| book.title.clone()).collect::<Vec<_>>()
+        };
+        assert_eq!(filter(BookFilter::default()), vec!["The Hobbit", "指环王"]);
+        assert_eq!(filter(BookFilter { subject: Some("fantasy".to_string()), ..BookFilter::default() }), vec!["指环王"]);
+        assert_eq!(filter(BookFilter { year_to: Some(2000), ..BookFilter::default() }), Vec::<String>::new());
+        assert_eq!(filter(BookFilter { classification: Some("i5".to_string()), ..BookFilter::default() }), vec!["指环王"]);
+
+        let groups = library.browse(Facet::Language);
+        assert_eq!(groups["zh"][0].title, "指环王");
+        assert_eq!(groups[metadata::UNCLASSIFIED][0].title, "The Hobbit");
+    }
+
+    fn clock_at(y: i32, m: u32, d: u32) -> Arc<ManualClock> {
+        let now = NaiveDate::from_ymd_opt(y, m, d).unwrap().and_hms_opt(10, 0, 0).unwrap();
+        Arc::new(ManualClock::new(now))
+    }
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_loan_records_due_date_and_return_time() {
+        let clock = clock_at(2024, 1, 1);
+        let mut library = Library::with_clock(clock.clone());
+        store_contract::register(&mut library, "Alice", MembershipTier::Standard);
+        library.set_loan_policy(LoanPolicy { loan_period_days: 14, max_renewals: 1 });
+        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "9780618640157".to_string());
+        library.add_book(book1).unwrap();
+
+        let loan = library.borrow_book("9780618640157", "Alice").unwrap();
+        assert_eq!(loan.due, date(2024, 1, 15));
+        assert_eq!(loan.checked_out, clock.now());
+
+        clock.advance(Duration::days(3));
+        library.return_book("9780618640157", "Alice").unwrap();
+        let history = library.loans_for("Alice");
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].returned, Some(clock.now()));
+        assert!(library.active_loans().is_empty());
+    }
+
+    #[test]
+    fn test_renew_book_respects_limit() {
+        let clock = clock_at(2024, 1, 1);
+        let mut library = Library::with_clock(clock.clone());
+        store_contract::register_all(&mut library, &["Alice", "Bob"]);
+        library.set_loan_policy(LoanPolicy { loan_period_days: 14, max_renewals: 2 });
+        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "9780618640157".to_string());
+        library.add_book(book1).unwrap();
+        library.borrow_book("9780618640157", "Alice").unwrap();
+
+        // 提前续借从原应还日期起算，逾期后续借从当天起算
+        assert_eq!(library.renew_book("9780618640157", "Alice"), Ok(date(2024, 1, 29)));
+        clock.set(date(2024, 2, 10).and_hms_opt(9, 0, 0).unwrap());
+        assert_eq!(library.renew_book("9780618640157", "Alice"), Ok(date(2024, 2, 24)));
+        assert_eq!(library.renew_book("9780618640157", "Alice"), Err(LibraryError::RenewalLimitReached));
+        assert_eq!(library.renew_book("9780618640157", "Bob"), Err(LibraryError::BookNotBorrowed));
+        assert_eq!(library.active_loans()[0].renewals, 2);
+    }
+
+    #[test]
+    fn test_overdue_loans_as_of_date() {
+        let clock = clock_at(2024, 1, 1);
+        let mut library = Library::with_clock(clock.clone());
+        store_contract::register_all(&mut library, &["Alice", "Bob"]);
+        library.set_loan_policy(LoanPolicy { loan_period_days: 10, max_renewals: 0 });
+        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "9780618640157".to_string());
+        let book2 = Book::new("Pride and Prejudice".to_string(), "Jane Austen".to_string(), "9780141439518".to_string());
+        library.add_book(book1).unwrap();
+        library.add_book(book2).unwrap();
+
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        clock.advance(Duration::days(5));
+        library.borrow_book("9780141439518", "Bob").unwrap();
+
+        assert!(library.overdue_loans(date(2024, 1, 11)).is_empty());
+        let overdue = library.overdue_loans(date(2024, 1, 12));
+        assert_eq!(overdue.len(), 1);
+        assert_eq!(overdue[0].borrower, "Alice");
+        assert_eq!(library.overdue_loans(date(2024, 1, 20)).len(), 2);
+
+        library.return_book("9780618640157", "Alice").unwrap();
+        assert_eq!(library.overdue_loans(date(2024, 1, 20)).len(), 1);
+    }
+
+    #[test]
+    fn test_late_return_charges_fine_by_category() {
+        let clock = clock_at(2024, 1, 1);
+        let mut library = Library::with_clock(clock.clone());
+        store_contract::register(&mut library, "Alice", MembershipTier::Standard);
+        library.set_loan_policy(LoanPolicy { loan_period_days: 10, max_renewals: 0 });
+        let mut policy = FinePolicy { daily_rate: 20, grace_days: 1, max_fine: Some(500), ..FinePolicy::default() };
+        policy.category_rates.insert("reference".to_string(), 100);
+        library.set_fine_policy(policy);
+        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "9780618640157".to_string());
+        let mut book2 = Book::new("Oxford Dictionary".to_string(), "Oxford".to_string(), "9780141439518".to_string());
+        book2.category = Some("reference".to_string());
+        library.add_book(book1).unwrap();
+        library.add_book(book2).unwrap();
+
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        library.borrow_book("9780141439518", "Alice").unwrap();
+        clock.advance(Duration::days(14)); // 逾期 4 天，扣除 1 天宽限
+
+        assert_eq!(library.return_book("9780618640157", "Alice").unwrap().fine, 60);
+        assert_eq!(library.return_book("9780141439518", "Alice").unwrap().fine, 300);
+        assert_eq!(library.balance("Alice"), 360);
+        assert_eq!(library.ledger().entries_for("Alice").len(), 2);
+    }
+
+    #[test]
+    fn test_on_time_return_has_no_fine() {
+        let clock = clock_at(2024, 1, 1);
+        let mut library = Library::with_clock(clock.clone());
+        store_contract::register(&mut library, "Alice", MembershipTier::Standard);
+        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "9780618640157".to_string());
+        library.add_book(book1).unwrap();
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        clock.advance(Duration::days(5));
+        assert_eq!(library.return_book("9780618640157", "Alice").unwrap().fine, 0);
+        assert!(library.ledger().entries().is_empty());
+    }
+
+    #[test]
+    fn test_unpaid_fines_block_borrowing_until_paid() {
+        let clock = clock_at(2024, 1, 1);
+        let mut library = Library::with_clock(clock.clone());
+        store_contract::register_all(&mut library, &["Alice", "Bob"]);
+        library.set_loan_policy(LoanPolicy { loan_period_days: 10, max_renewals: 0 });
+        library.set_fine_policy(FinePolicy {
+            daily_rate: 100,
+            grace_days: 0,
+            max_fine: None,
+            block_threshold: 500,
+            ..FinePolicy::default()
+        });
+        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "9780618640157".to_string());
+        library.add_book(book1).unwrap();
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        clock.advance(Duration::days(20));
+        library.return_book("9780618640157", "Alice").unwrap();
+
+        assert_eq!(
+            library.borrow_book("9780618640157", "Alice").err(),
+            Some(LibraryError::UnpaidFines { balance: 1000, threshold: 500 })
+        );
+        assert_eq!(library.pay_fine("Alice", 2000), Err(LibraryError::InvalidAmount));
+        assert_eq!(library.pay_fine("Alice", 300), Ok(700));
+        assert_eq!(library.waive_fine("Alice", 200, "首次逾期"), Ok(500));
+        // 欠款等于上限时仍可借阅
+        assert!(library.borrow_book("9780618640157", "Alice").is_ok());
+        assert_eq!(library.pay_fine("Bob", 100), Err(LibraryError::InvalidAmount));
+    }
+
+    fn hold_library(clock: Arc<ManualClock>) -> Library {
+        let mut library = Library::with_clock(clock);
+        library.set_hold_policy(HoldPolicy { pickup_days: 3 });
+        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "9780618640157".to_string());
+        library.add_book(book1).unwrap();
+        library
+    }
+
+    #[test]
+    fn test_hold_queue_serves_patrons_in_order() {
+        let clock = clock_at(2024, 1, 1);
+        let mut library = hold_library(clock.clone());
+        store_contract::register_all(&mut library, &["Alice", "Bob", "Carol", "Dave"]);
+        let before = library.clone();
+        assert_eq!(library.place_hold("9780618640157", "Bob"), Err(LibraryError::BookAvailable));
+        // 失败的预约不会留下空的预约队列
+        assert_eq!(library, before);
+
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        assert_eq!(library.place_hold("9780618640157", "Bob"), Ok(1));
+        assert_eq!(library.place_hold("9780618640157", "Carol"), Ok(2));
+        assert_eq!(library.place_hold("9780618640157", "Bob"), Err(LibraryError::HoldAlreadyPlaced));
+
+        let receipt = library.return_book("9780618640157", "Alice").unwrap();
+        assert_eq!(receipt.hold_for, Some("Bob".to_string()));
+        let copy = &library.find_book_by_isbn("9780618640157").unwrap().copies[0];
+        assert_eq!(copy.on_hold_for.as_ref().unwrap().expires, date(2024, 1, 4));
+        assert_eq!(library.hold_position("9780618640157", "Carol"), Some(1));
+
+        // 预约生效期间，不是队首的读者不能借走
+        assert_eq!(library.borrow_book("9780618640157", "Carol").err(), Some(LibraryError::BookOnHold));
+        assert_eq!(library.borrow_book("9780618640157", "Dave").err(), Some(LibraryError::BookOnHold));
+        assert_eq!(library.borrow_book("9780618640157", "Bob").unwrap().borrower, "Bob");
+
+        let receipt = library.return_book("9780618640157", "Bob").unwrap();
+        assert_eq!(receipt.hold_for, Some("Carol".to_string()));
+        assert!(library.holds_for("9780618640157").is_empty());
+    }
+
+    #[test]
+    fn test_expired_hold_passes_to_next_patron() {
+        let clock = clock_at(2024, 1, 1);
+        let mut library = hold_library(clock.clone());
+        store_contract::register_all(&mut library, &["Alice", "Bob", "Carol"]);
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        library.place_hold("9780618640157", "Bob").unwrap();
+        library.place_hold("9780618640157", "Carol").unwrap();
+        library.return_book("9780618640157", "Alice").unwrap();
+
+        clock.advance(Duration::days(4));
+        assert_eq!(library.expire_holds().unwrap(), vec![("9780618640157".to_string(), "Bob".to_string())]);
+        assert_eq!(library.borrow_book("9780618640157", "Bob").err(), Some(LibraryError::BookOnHold));
+        assert!(library.borrow_book("9780618640157", "Carol").is_ok());
+    }
+
+    #[test]
+    fn test_cancel_hold() {
+        let clock = clock_at(2024, 1, 1);
+        let mut library = hold_library(clock.clone());
+        store_contract::register_all(&mut library, &["Alice", "Bob", "Carol", "Dave"]);
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        library.place_hold("9780618640157", "Bob").unwrap();
+        library.place_hold("9780618640157", "Carol").unwrap();
+
+        assert_eq!(library.cancel_hold("9780618640157", "Dave"), Err(LibraryError::HoldNotFound));
+        library.cancel_hold("9780618640157", "Bob").unwrap();
+        assert_eq!(library.hold_position("9780618640157", "Carol"), Some(1));
+
+        library.place_hold("9780618640157", "Bob").unwrap();
+        library.return_book("9780618640157", "Alice").unwrap();
+        // 放上预约书架后取消，书让给下一位
+        library.cancel_hold("9780618640157", "Carol").unwrap();
+        let copy = &library.find_book_by_isbn("9780618640157").unwrap().copies[0];
+        assert_eq!(copy.on_hold_for.as_ref().unwrap().patron, "Bob");
+    }
+
+    #[test]
+    fn test_new_copy_goes_to_hold_queue() {
+        let clock = clock_at(2024, 1, 1);
+        let mut library = hold_library(clock.clone());
+        store_contract::register_all(&mut library, &["Alice", "Bob", "Carol"]);
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        library.place_hold("9780618640157", "Bob").unwrap();
+
+        library.add_copy("9780618640157", "A-02".to_string(), Condition::New).unwrap();
+        assert_eq!(library.find_book_by_isbn("9780618640157").unwrap().available_copies(), 0);
+        assert_eq!(library.borrow_book("9780618640157", "Carol").err(), Some(LibraryError::BookOnHold));
+        assert_eq!(library.borrow_book("9780618640157", "Bob").unwrap().copy_id, 2);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_borrow_rejects_ineligible_patrons() {
+        let clock = clock_at(2024, 6, 1);
+        let mut library = Library::with_clock(clock.clone());
+        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "9780618640157".to_string());
+        library.add_book(book1).unwrap();
+        let expires = date(2024, 12, 31);
+        for id in ["Alice", "Bob"] {
+            let patron = Patron::new(id.to_string(), id.to_string(), String::new(), MembershipTier::Basic, expires);
+            library.register_patron(patron).unwrap();
+        }
+        let duplicate = Patron::new("Alice".to_string(), "Alice".to_string(), String::new(), MembershipTier::Basic, expires);
+        assert_eq!(library.register_patron(duplicate), Err(LibraryError::PatronAlreadyExists));
+
+        assert_eq!(library.borrow_book("9780618640157", "Mallory").err(), Some(LibraryError::PatronNotFound));
+
+        library.set_patron_status("Bob", PatronStatus::Suspended).unwrap();
+        assert_eq!(library.borrow_book("9780618640157", "Bob").err(), Some(LibraryError::PatronSuspended));
+        library.set_patron_status("Bob", PatronStatus::Active).unwrap();
+
+        clock.set(date(2025, 1, 1).and_hms_opt(9, 0, 0).unwrap());
+        assert_eq!(library.borrow_book("9780618640157", "Alice").err(), Some(LibraryError::MembershipExpired));
+        library.renew_membership("Alice", date(2025, 12, 31)).unwrap();
+        assert!(library.borrow_book("9780618640157", "Alice").is_ok());
+    }
+
+    #[test]
+    fn test_tier_limits_loans_and_loan_length() {
+        let clock = clock_at(2024, 1, 1);
+        let mut library = Library::with_clock(clock.clone());
+        library.set_tier_limits(MembershipTier::Basic, TierLimits { max_loans: 2, loan_period_days: Some(7) });
+        for isbn in ["9780618640157", "9780141439518", "9781617294556"] {
+            let book = Book::new(format!("Book {}", isbn), "John Doe".to_string(), isbn.to_string());
+            library.add_book(book).unwrap();
+        }
+        store_contract::register(&mut library, "Alice", MembershipTier::Basic);
+        store_contract::register(&mut library, "Bob", MembershipTier::Premium);
+
+        assert_eq!(library.borrow_book("9780618640157", "Alice").unwrap().due, date(2024, 1, 8));
+        library.borrow_book("9780141439518", "Alice").unwrap();
+        assert_eq!(library.borrow_book("9781617294556", "Alice").err(), Some(LibraryError::LoanLimitReached { limit: 2 }));
+        assert_eq!(library.borrow_book("9781617294556", "Bob").unwrap().due, date(2024, 3, 1));
+
+        // 归还后名额释放
+        library.return_book("9780618640157", "Alice").unwrap();
+        library.return_book("9781617294556", "Bob").unwrap();
+        assert!(library.borrow_book("9781617294556", "Alice").is_ok());
+    }
+
+    #[test]
+    fn test_add_book_rejects_invalid_isbn() {
+        let mut library = Library::new();
+        let book = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "123-456".to_string());
+        assert_eq!(library.add_book(book), Err(LibraryError::InvalidIsbn("123-456".to_string())));
+    }
+
+    #[test]
+    fn test_isbn_forms_share_one_key() {
+        let mut library = Library::new();
+        let book1 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "978-0618640157".to_string());
+        library.add_book(book1).unwrap();
+        // 同一本书的不同写法（包括 ISBN-10）视为重复
+        let book2 = Book::new("Rust Programming".to_string(), "John Doe".to_string(), "0618640150".to_string());
+        assert_eq!(library.add_book(book2), Err(LibraryError::BookAlreadyExists));
+
+        assert_eq!(library.find_book_by_isbn("9780618640157").unwrap().isbn, "9780618640157");
+        assert!(library.find_book_by_isbn("978-0-618-64015-7").is_ok());
+        assert!(library.find_book_by_isbn("0-618-64015-0").is_ok());
+    }
+
+    #[test]
+    fn test_search_index_follows_added_books() {
+        let mut library = Library::new();
+        let book1 = Book::new("The Hobbit".to_string(), "J.R.R. Tolkien".to_string(), "9780618640157".to_string());
+        library.add_book(book1).unwrap();
+        assert_eq!(library.search("hobbit").len(), 1);
+
+        // 索引建立之后新增的书也能被检索到
+        let book2 = Book::new("指环王".to_string(), "托尔金".to_string(), "9787111213826".to_string());
+        library.add_book(book2).unwrap();
+        let results = library.search("指环 OR hobbit");
+        assert_eq!(results.len(), 2);
+        assert_eq!(library.search("王")[0].book.isbn, "9787111213826");
+    }
+
+    #[test]
+    fn test_fuzzy_search_ranks_candidates() {
+        let mut library = Library::new();
+        let book1 = Book::new("The Hobbit".to_string(), "J.R.R. Tolkien".to_string(), "9780618640157".to_string());
+        let book2 = Book::new("Pride and Prejudice".to_string(), "Jane Austen".to_string(), "9780141439518".to_string());
+        library.add_book(book1).unwrap();
+        library.add_book(book2).unwrap();
+
+        let results = library.fuzzy_search("Tolkein", DEFAULT_FUZZY_THRESHOLD);
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].book.isbn, "9780618640157");
+        assert_eq!(results[0].field, MatchField::Author);
+
+        assert_eq!(library.fuzzy_search("Tolkien, J.R.R.", 0.99)[0].score, 1.0);
+        assert_eq!(library.fuzzy_search("Prejudise", DEFAULT_FUZZY_THRESHOLD)[0].field, MatchField::Title);
+        // 阈值越低，返回的候选越多
+        assert_eq!(library.fuzzy_search("Hobit", 0.0).len(), 2);
+    }
+
+    #[test]
+    fn test_remove_book_checks_loans_and_holds() {
+        let clock = clock_at(2024, 1, 1);
+        let mut library = hold_library(clock);
+        store_contract::register_all(&mut library, &["Alice", "Bob"]);
+        assert_eq!(library.search("rust").len(), 1);
+
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        assert_eq!(library.remove_book("9780618640157"), Err(LibraryError::BookAlreadyBorrowed));
+
+        // 归还后书被放上预约书架，仍不能移除
+        library.place_hold("9780618640157", "Bob").unwrap();
+        library.return_book("9780618640157", "Alice").unwrap();
+        assert_eq!(library.remove_book("9780618640157"), Err(LibraryError::BookHasHolds { count: 1 }));
+
+        library.borrow_book("9780618640157", "Bob").unwrap();
+        library.return_book("9780618640157", "Bob").unwrap();
+        let removed = library.remove_book("978-0-618-64015-7").unwrap();
+        assert_eq!(removed.title, "Rust Programming");
+        assert_eq!(library.find_book_by_isbn("9780618640157"), Err(LibraryError::BookNotFound));
+        assert!(library.search("rust").is_empty());
+        assert_eq!(library.remove_book("9780618640157"), Err(LibraryError::BookNotFound));
+    }
+
+    #[test]
+    fn test_update_book_metadata_and_isbn() {
+        let mut library = Library::new();
+        store_contract::register(&mut library, "Alice", MembershipTier::Standard);
+        let book1 = Book::new("The Hobit".to_string(), "Tolkien".to_string(), "9780618640157".to_string());
+        let book2 = Book::new("Pride and Prejudice".to_string(), "Jane Austen".to_string(), "9780141439518".to_string());
+        library.add_book(book1).unwrap();
+        library.add_book(book2).unwrap();
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        assert_eq!(library.search("hobit").len(), 1);
+
+        let update = BookUpdate {
+            title: Some("The Hobbit".to_string()),
+            author: Some("J.R.R. Tolkien".to_string()),
+            ..BookUpdate::default()
+        };
+        library.update_book("0-618-64015-0", update).unwrap();
+        let book = library.find_book_by_isbn("9780618640157").unwrap();
+        assert_eq!(book.title, "The Hobbit");
+        assert_eq!(book.author, "J.R.R. Tolkien");
+        assert!(library.search("hobit").is_empty());
+        assert_eq!(library.search("hobbit").len(), 1);
+
+        // 更正 ISBN：校验新值，不能与已有书籍冲突，借阅记录随之迁移
+        let to = |isbn: &str| BookUpdate { isbn: Some(isbn.to_string()), ..BookUpdate::default() };
+        assert_eq!(
+            library.update_book("9780618640157", to("978-0618640158")),
+            Err(LibraryError::InvalidIsbn("978-0618640158".to_string()))
+        );
+        assert_eq!(library.update_book("9780618640157", to("9780141439518")), Err(LibraryError::BookAlreadyExists));
+        library.update_book("9780618640157", to("978-7-111-21382-6")).unwrap();
+        assert_eq!(library.find_book_by_isbn("9780618640157"), Err(LibraryError::BookNotFound));
+        assert_eq!(library.find_book_by_isbn("9787111213826").unwrap().isbn, "9787111213826");
+        assert_eq!(library.loans_for("Alice")[0].isbn, "9787111213826");
+        assert_eq!(library.search("hobbit")[0].book.isbn, "9787111213826");
+        library.return_book("9787111213826", "Alice").unwrap();
+        assert_eq!(
+            library.update_book("9780000000000", BookUpdate::default()),
+            Err(LibraryError::BookNotFound)
+        );
+    }
+
+    #[test]
+    fn test_library_passes_store_contract() {
+        store_contract::run(Library::with_clock);
+    }
+}
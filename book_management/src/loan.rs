@@ -0,0 +1,172 @@
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime};
+use serde::{Serialize, Deserialize};
+use crate::fine::Money;
+
+// 定义一个 Trait 用于抽象时间来源，便于在测试中注入固定的时间
+pub trait Clock: Send + Sync {
+    fn now(&self) -> NaiveDateTime;
+
+    fn today(&self) -> NaiveDate {
+        self.now().date()
+    }
+}
+
+// 使用系统本地时间的时钟
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> NaiveDateTime {
+        Local::now().naive_local()
+    }
+}
+
+// 手动控制的时钟，只有调用 set 或 advance 时才会改变
+#[derive(Debug)]
+pub struct ManualClock {
+    now: Mutex<NaiveDateTime>,
+}
+
+impl ManualClock {
+    pub fn new(now: NaiveDateTime) -> Self {
+        ManualClock { now: Mutex::new(now) }
+    }
+
+    pub fn set(&self, now: NaiveDateTime) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> NaiveDateTime {
+        *self.now.lock().unwrap()
+    }
+}
+
+// 图书馆持有的时钟句柄。时钟属于运行时配置，不参与序列化和比较
+#[derive(Clone)]
+pub struct SharedClock(Arc<dyn Clock>);
+
+impl SharedClock {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        SharedClock(clock)
+    }
+
+    pub fn now(&self) -> NaiveDateTime {
+        self.0.now()
+    }
+
+    pub fn today(&self) -> NaiveDate {
+        self.0.today()
+    }
+}
+
+impl Default for SharedClock {
+    fn default() -> Self {
+        SharedClock(Arc::new(SystemClock))
+    }
+}
+
+impl fmt::Debug for SharedClock {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SharedClock({})", self.now())
+    }
+}
+
+impl PartialEq for SharedClock {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+// 借阅规则
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoanPolicy {
+    pub loan_period_days: i64, // 每次借阅或续借的天数
+    pub max_renewals: u32, // 最多续借次数
+}
+
+impl Default for LoanPolicy {
+    fn default() -> Self {
+        LoanPolicy { loan_period_days: 30, max_renewals: 2 }
+    }
+}
+
+// 一次借阅记录，归还后保留作为历史
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Loan {
+    pub id: u64,
+    pub isbn: String,
+    pub copy_id: u32,
+    pub borrower: String,
+    pub checked_out: NaiveDateTime, // 借出时间
+    pub due: NaiveDate, // 应还日期，当天归还不算逾期
+    pub renewals: u32, // 已续借次数
+    pub returned: Option<NaiveDateTime>, // 归还时间
+}
+
+impl Loan {
+    pub fn is_active(&self) -> bool {
+        self.returned.is_none()
+    }
+
+    // 在给定日期是否处于逾期未还状态
+    pub fn is_overdue(&self, as_of: NaiveDate) -> bool {
+        self.is_active() && as_of > self.due
+    }
+
+    // 截至给定日期（或实际归还日期）逾期的天数
+    pub fn days_overdue(&self, as_of: NaiveDate) -> i64 {
+        let end = self.returned.map(|returned| returned.date()).unwrap_or(as_of);
+        (end - self.due).num_days().max(0)
+    }
+}
+
+// 归还书籍的结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReturnReceipt {
+    pub copy_id: u32, // 归还的副本编号
+    pub loan_id: Option<u64>, // 对应的借阅记录，旧数据中的借阅没有记录
+    pub fine: Money, // 本次产生的逾期罚款
+    pub hold_for: Option<String>, // 书被放上预约书架时，为其保留的读者
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_loan_overdue() {
+        let loan = Loan {
+            id: 1,
+            isbn: "123-456".to_string(),
+            copy_id: 1,
+            borrower: "Alice".to_string(),
+            checked_out: date(2024, 1, 1).and_hms_opt(10, 0, 0).unwrap(),
+            due: date(2024, 1, 31),
+            renewals: 0,
+            returned: None,
+        };
+        assert!(!loan.is_overdue(date(2024, 1, 31)));
+        assert!(loan.is_overdue(date(2024, 2, 1)));
+        assert_eq!(loan.days_overdue(date(2024, 2, 3)), 3);
+        assert_eq!(loan.days_overdue(date(2024, 1, 15)), 0);
+    }
+
+    #[test]
+    fn test_manual_clock() {
+        let clock = ManualClock::new(date(2024, 1, 1).and_hms_opt(0, 0, 0).unwrap());
+        clock.advance(Duration::days(2));
+        assert_eq!(clock.today(), date(2024, 1, 3));
+    }
+}
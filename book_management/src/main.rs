@@ -1,44 +1,65 @@
-use book_management::{Book, persistence};
-use std::error::Error;
+use book_management::cli::{self, OutputFormat};
+use book_management::persistence::{self, PersistentLibrary, StorageKind};
+use book_management::{AuditLog, LibraryError};
+use std::env;
+use std::io;
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
 
-fn main() -> Result<(), Box<dyn Error>> {
-    // 尝试从文件加载图书馆数据
-    let mut library = persistence::load_library_from_file()?;
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let invocation = match cli::parse_invocation(&args) {
+        Ok(invocation) => invocation,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, cli::USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    let format = invocation.format;
 
-    // 创建一些书籍
-    let book1 = Book::new("The Lord of the Rings".to_string(), "J.R.R. Tolkien".to_string(), "978-0618260274".to_string());
-    let book2 = Book::new("Pride and Prejudice".to_string(), "Jane Austen".to_string(), "978-0141439518".to_string());
-    let book3 = Book::new("Rust in Action".to_string(), "Tim McNamara".to_string(), "978-1617294639".to_string());
+    // 后端按数据文件的扩展名选择，数据文件损坏时使用从备份恢复的数据
+    let kind = StorageKind::from_path(&invocation.catalog);
+    let mut store = match PersistentLibrary::open(kind, &invocation.catalog) {
+        Ok(store) => store,
+        Err(LibraryError::RecoveredFromBackup { backup, cause, .. }) => {
+            eprintln!("数据文件损坏（{}），已从备份 {} 恢复。", cause, backup.display());
+            match PersistentLibrary::open(kind, &invocation.catalog) {
+                Ok(store) => store,
+                Err(e) => return fail(&e.to_string(), format),
+            }
+        }
+        Err(e) => return fail(&e.to_string(), format),
+    };
 
-    // 将书籍添加到图书馆
-    library.add_book(book1)?;
-    library.add_book(book2)?;
-    library.add_book(book3)?;
-
-    println!("\n--- 图书馆信息 ---");
-    library.list_all_books();
-
-    // 查找书籍
-    println!("\n--- 通过 ISBN 查找 ---");
-    match library.find_book_by_isbn("978-0141439518") {
-        Ok(book) => println!("找到书籍：{}，作者：{}", book.title, book.author),
-        Err(_) => println!("未找到该 ISBN 的书籍。"),
+    // 修改历史记录在数据文件旁的审计日志中
+    let mut audit = match AuditLog::open(persistence::audit_log_path(&invocation.catalog)) {
+        Ok(audit) => audit,
+        Err(e) => return fail(&e.to_string(), format),
+    };
+    if let Some(actor) = &invocation.actor {
+        audit.set_actor(actor.as_str());
     }
+    store.set_audit_log(Arc::new(Mutex::new(audit)));
 
-    // 借阅和归还书籍
-    println!("\n--- 借阅和归还 ---");
-    library.borrow_book("978-0618260274", "Alice")?;
-    library.list_all_books();
-    library.return_book("978-0618260274")?;
-    library.list_all_books();
-
-    println!("\n--- Alice 借阅的书籍 ---");
-    for book in library.find_borrowed_by("Alice") {
-        println!("- {}", book.title);
+    match invocation.command {
+        Some(command) => match cli::execute(&mut store, command) {
+            Ok(reply) => {
+                println!("{}", reply.render(format));
+                ExitCode::SUCCESS
+            }
+            Err(e) => fail(&e.to_string(), format),
+        },
+        None => match cli::run_repl(&mut store, format, io::stdin().lock(), io::stdout()) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => fail(&e.to_string(), format),
+        },
     }
+}
 
-    // 保存图书馆数据到文件
-    persistence::save_library_to_file(&library)?;
-
-    Ok(())
-}
\ No newline at end of file
+fn fail(message: &str, format: OutputFormat) -> ExitCode {
+    match format {
+        OutputFormat::Text => eprintln!("{}", cli::render_error(message, format)),
+        OutputFormat::Json => println!("{}", cli::render_error(message, format)),
+    }
+    ExitCode::FAILURE
+}
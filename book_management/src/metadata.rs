@@ -0,0 +1,348 @@
+// 书目的扩展信息：合著者、出版信息、语言、主题和分类号，以及按这些信息筛选和分组浏览
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+use crate::search::fold;
+use crate::{Book, LibraryError};
+
+// 分组浏览时，没有对应信息的书籍归入这一组
+pub const UNCLASSIFIED: &str = "未分类";
+
+// 分类法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ClassScheme {
+    Dewey, // 杜威十进分类法，例如 823.912
+    Clc, // 中国图书馆分类法，例如 I247.5
+}
+
+impl fmt::Display for ClassScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ClassScheme::Dewey => write!(f, "DDC"),
+            ClassScheme::Clc => write!(f, "CLC"),
+        }
+    }
+}
+
+// 分类号
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "ClassificationRecord")]
+pub struct Classification {
+    pub scheme: ClassScheme,
+    pub number: String,
+}
+
+// 反序列化用的分类号记录，与解析文本时做相同的校验，
+// 手工修改过的数据文件不会带入无效的分类号
+#[derive(Deserialize)]
+struct ClassificationRecord {
+    scheme: ClassScheme,
+    number: String,
+}
+
+impl TryFrom<ClassificationRecord> for Classification {
+    type Error = LibraryError;
+
+    fn try_from(record: ClassificationRecord) -> Result<Self, Self::Error> {
+        let prefix = match record.scheme {
+            ClassScheme::Dewey => "ddc",
+            ClassScheme::Clc => "clc",
+        };
+        format!("{}:{}", prefix, record.number).parse()
+    }
+}
+
+impl Classification {
+    // 大类：杜威分类取百位，例如 823.912 -> 800；中图分类取首字母，例如 I247.5 -> I
+    pub fn main_class(&self) -> String {
+        let first = self.number.chars().next().map(String::from).unwrap_or_default();
+        match self.scheme {
+            ClassScheme::Dewey => format!("{}00", first),
+            ClassScheme::Clc => first,
+        }
+    }
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.scheme, self.number)
+    }
+}
+
+// 解析分类号。可以带 ddc:、dewey: 或 clc: 前缀，否则以数字开头的按杜威分类、
+// 以字母开头的按中图分类处理
+impl FromStr for Classification {
+    type Err = LibraryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || LibraryError::InvalidClassification(s.to_string());
+        let text = s.trim();
+        let (scheme, number) = match text.split_once(':') {
+            Some((prefix, number)) => match prefix.trim().to_ascii_lowercase().as_str() {
+                "ddc" | "dewey" => (Some(ClassScheme::Dewey), number.trim()),
+                "clc" => (Some(ClassScheme::Clc), number.trim()),
+                _ => return Err(invalid()),
+            },
+            None => (None, text),
+        };
+        let first = number.chars().next().ok_or_else(invalid)?;
+        let scheme = scheme.unwrap_or(if first.is_ascii_digit() { ClassScheme::Dewey } else { ClassScheme::Clc });
+        let valid = match scheme {
+            // 整数部分为三位数字，小数点后也只能是数字
+            ClassScheme::Dewey => {
+                let (whole, fraction) = number.split_once('.').unwrap_or((number, "0"));
+                whole.len() == 3
+                    && whole.chars().all(|c| c.is_ascii_digit())
+                    && !fraction.is_empty()
+                    && fraction.chars().all(|c| c.is_ascii_digit())
+            }
+            // 以大写字母开头，其余为字母、数字和中图分类常用的符号
+            ClassScheme::Clc => {
+                first.is_ascii_uppercase()
+                    && number.chars().all(|c| c.is_ascii_alphanumeric() || ".-=/:()\"<>+".contains(c))
+            }
+        };
+        if !valid {
+            return Err(invalid());
+        }
+        Ok(Classification { scheme, number: number.to_string() })
+    }
+}
+
+// 书目的扩展信息，所有字段都可以为空，旧数据文件中没有这些字段
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub co_authors: Vec<String>, // 第一作者之外的作者，按署名顺序
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub publisher: Option<String>, // 出版者
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub year: Option<i32>, // 出版年
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub edition: Option<String>, // 版次，例如 "第 2 版"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>, // 语言代码，例如 zh、en
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub subjects: Vec<String>, // 主题标签
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub classification: Option<Classification>, // 杜威或中图分类号
+}
+
+// 书目筛选条件，为 None 的条件不限制。文本条件忽略大小写和重音符号，
+// 分类号按前缀匹配，例如 "I24" 匹配 I247.5
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BookFilter {
+    pub author: Option<String>, // 任一作者
+    pub publisher: Option<String>,
+    pub year_from: Option<i32>, // 出版年下限（含）
+    pub year_to: Option<i32>, // 出版年上限（含）
+    pub edition: Option<String>,
+    pub language: Option<String>,
+    pub subject: Option<String>, // 任一主题
+    pub category: Option<String>,
+    pub classification: Option<String>,
+}
+
+impl BookFilter {
+    pub fn is_empty(&self) -> bool {
+        *self == BookFilter::default()
+    }
+
+    pub fn matches(&self, book: &Book) -> bool {
+        let metadata = &book.metadata;
+        let same = |wanted: &Option<String>, value: Option<&str>| match wanted {
+            Some(wanted) => value.is_some_and(|value| fold(value) == fold(wanted)),
+            None => true,
+        };
+        let any = |wanted: &Option<String>, values: Vec<&str>| match wanted {
+            Some(wanted) => values.into_iter().any(|value| fold(value) == fold(wanted)),
+            None => true,
+        };
+        let year_in_range = match (self.year_from, self.year_to, metadata.year) {
+            (None, None, _) => true,
+            (_, _, None) => false,
+            (from, to, Some(year)) => from.is_none_or(|from| year >= from) && to.is_none_or(|to| year <= to),
+        };
+        let class_matches = match (&self.classification, &metadata.classification) {
+            (None, _) => true,
+            (Some(_), None) => false,
+            (Some(prefix), Some(class)) => class.number.to_ascii_uppercase().starts_with(&prefix.to_ascii_uppercase()),
+        };
+        any(&self.author, book.authors())
+            && same(&self.publisher, metadata.publisher.as_deref())
+            && year_in_range
+            && same(&self.edition, metadata.edition.as_deref())
+            && same(&self.language, metadata.language.as_deref())
+            && any(&self.subject, metadata.subjects.iter().map(String::as_str).collect())
+            && same(&self.category, book.category.as_deref())
+            && class_matches
+    }
+}
+
+// 分组浏览的依据
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Facet {
+    Category, // 分类（用于计算罚款的那个分类）
+    Subject, // 主题标签，一本书可以出现在多个组中
+    Language,
+    Publisher,
+    Year,
+    Class, // 分类号的大类
+}
+
+impl Facet {
+    // 书籍所在的组
+    fn keys(self, book: &Book) -> Vec<String> {
+        let metadata = &book.metadata;
+        let keys: Vec<String> = match self {
+            Facet::Category => book.category.iter().cloned().collect(),
+            Facet::Subject => metadata.subjects.clone(),
+            Facet::Language => metadata.language.iter().cloned().collect(),
+            Facet::Publisher => metadata.publisher.iter().cloned().collect(),
+            Facet::Year => metadata.year.iter().map(i32::to_string).collect(),
+            Facet::Class => metadata
+                .classification
+                .iter()
+                .map(|class| format!("{} {}", class.scheme, class.main_class()))
+                .collect(),
+        };
+        if keys.is_empty() {
+            vec![UNCLASSIFIED.to_string()]
+        } else {
+            keys
+        }
+    }
+}
+
+impl FromStr for Facet {
+    type Err = LibraryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "category" => Ok(Facet::Category),
+            "subject" => Ok(Facet::Subject),
+            "language" => Ok(Facet::Language),
+            "publisher" => Ok(Facet::Publisher),
+            "year" => Ok(Facet::Year),
+            "class" | "classification" => Ok(Facet::Class),
+            _ => Err(LibraryError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+// 按 facet 分组，每组内按标题排序
+pub fn browse<'a>(books: impl IntoIterator<Item = &'a Book>, facet: Facet) -> BTreeMap<String, Vec<&'a Book>> {
+    let mut groups: BTreeMap<String, Vec<&Book>> = BTreeMap::new();
+    for book in books {
+        for key in facet.keys(book) {
+            groups.entry(key).or_default().push(book);
+        }
+    }
+    for books in groups.values_mut() {
+        books.sort_by(|a, b| a.title.cmp(&b.title).then_with(|| a.isbn.cmp(&b.isbn)));
+        books.dedup_by(|a, b| a.isbn == b.isbn);
+    }
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(title: &str, isbn: &str, metadata: Metadata) -> Book {
+        let mut book = Book::new(title.to_string(), "Author".to_string(), isbn.to_string());
+        book.metadata = metadata;
+        book
+    }
+
+    #[test]
+    fn test_parse_classification() {
+        let dewey: Classification = "823.912".parse().unwrap();
+        assert_eq!(dewey, Classification { scheme: ClassScheme::Dewey, number: "823.912".to_string() });
+        assert_eq!(dewey.main_class(), "800");
+        let clc: Classification = "I247.5".parse().unwrap();
+        assert_eq!(clc.scheme, ClassScheme::Clc);
+        assert_eq!(clc.main_class(), "I");
+        assert_eq!(clc.to_string(), "CLC I247.5");
+        assert_eq!("clc: TP312".parse::<Classification>().unwrap().number, "TP312");
+        assert_eq!("ddc:005".parse::<Classification>().unwrap().scheme, ClassScheme::Dewey);
+        for invalid in ["", "82", "823.", "8a3", "dewey:I247", "i247", "udc:821"] {
+            assert_eq!(
+                invalid.parse::<Classification>(),
+                Err(LibraryError::InvalidClassification(invalid.to_string())),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn test_deserialize_validates_classification() {
+        let parsed: Classification = serde_json::from_str(r#"{"scheme": "Clc", "number": "I247.5"}"#).unwrap();
+        assert_eq!(parsed.main_class(), "I");
+        for invalid in [r#"{"scheme": "Clc", "number": ""}"#, r#"{"scheme": "Dewey", "number": "中23"}"#] {
+            assert!(serde_json::from_str::<Classification>(invalid).is_err(), "{}", invalid);
+        }
+        // 直接构造的无效分类号也不会让分组浏览崩溃
+        let odd = Classification { scheme: ClassScheme::Dewey, number: "中23".to_string() };
+        assert_eq!(odd.main_class(), "中00");
+        assert_eq!(Classification { scheme: ClassScheme::Clc, number: String::new() }.main_class(), "");
+    }
+
+    #[test]
+    fn test_filter_by_metadata() {
+        let mut hobbit = book(
+            "The Hobbit",
+            "9780618640157",
+            Metadata {
+                publisher: Some("Houghton Mifflin".to_string()),
+                year: Some(1937),
+                language: Some("en".to_string()),
+                subjects: vec!["Fantasy".to_string(), "Dragons".to_string()],
+                classification: Some("823.912".parse().unwrap()),
+                ..Metadata::default()
+            },
+        );
+        hobbit.metadata.co_authors = vec!["Christopher Tolkien".to_string()];
+        let plain = book("Plain", "9780141439518", Metadata::default());
+
+        let filter = |f: BookFilter| [&hobbit, &plain].into_iter().filter(|b| f.matches(b)).count();
+        assert_eq!(filter(BookFilter::default()), 2);
+        assert!(BookFilter::default().is_empty());
+        assert_eq!(filter(BookFilter { author: Some("christopher tolkien".to_string()), ..Default::default() }), 1);
+        assert_eq!(filter(BookFilter { author: Some("Author".to_string()), ..Default::default() }), 2);
+        assert_eq!(filter(BookFilter { subject: Some("dragons".to_string()), ..Default::default() }), 1);
+        assert_eq!(filter(BookFilter { year_from: Some(1900), year_to: Some(1937), ..Default::default() }), 1);
+        assert_eq!(filter(BookFilter { year_from: Some(1938), ..Default::default() }), 0);
+        assert_eq!(filter(BookFilter { classification: Some("82".to_string()), ..Default::default() }), 1);
+        assert_eq!(filter(BookFilter { language: Some("zh".to_string()), ..Default::default() }), 0);
+        let both = BookFilter {
+            publisher: Some("houghton mifflin".to_string()),
+            language: Some("EN".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(filter(both), 1);
+    }
+
+    #[test]
+    fn test_browse_groups_books() {
+        let fantasy = |title: &str, isbn: &str, subjects: &[&str]| {
+            let subjects = subjects.iter().map(|s| s.to_string()).collect();
+            book(title, isbn, Metadata { subjects, ..Metadata::default() })
+        };
+        let books = [
+            fantasy("The Hobbit", "9780618640157", &["Fantasy", "Dragons"]),
+            fantasy("Earthsea", "9780141439518", &["Fantasy"]),
+            fantasy("Untagged", "9781617294556", &[]),
+        ];
+        let groups = browse(&books, Facet::Subject);
+        let titles = |key: &str| groups[key].iter().map(|b| b.title.as_str()).collect::<Vec<_>>();
+        assert_eq!(groups.keys().collect::<Vec<_>>(), vec!["Dragons", "Fantasy", UNCLASSIFIED]);
+        assert_eq!(titles("Fantasy"), vec!["Earthsea", "The Hobbit"]);
+        assert_eq!(titles(UNCLASSIFIED), vec!["Untagged"]);
+        assert_eq!("class".parse::<Facet>().unwrap(), Facet::Class);
+        assert!("colour".parse::<Facet>().is_err());
+    }
+}
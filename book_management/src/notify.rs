@@ -0,0 +1,169 @@
+// 领域事件的订阅：图书馆本身不再打印任何内容，需要提示的调用方注册订阅者
+use std::fmt;
+use std::sync::Arc;
+use crate::{format_money, Event, Library};
+
+// 订阅者在每次修改完成后收到事件，library 是修改后的状态，可用于查询书名等信息
+pub trait Subscriber: Send + Sync {
+    fn notify(&self, library: &Library, event: &Event);
+}
+
+// 任意闭包都可以作为订阅者
+impl<F> Subscriber for F
+where
+    F: Fn(&Library, &Event) + Send + Sync,
+{
+    fn notify(&self, library: &Library, event: &Event) {
+        self(library, event)
+    }
+}
+
+// 把事件打印到标准输出，提示语与以前图书馆直接打印的一致
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StdoutSubscriber;
+
+impl Subscriber for StdoutSubscriber {
+    fn notify(&self, library: &Library, event: &Event) {
+        if let Some(message) = message(library, event) {
+            println!("{}", message);
+        }
+    }
+}
+
+// 事件对应的提示语，没有提示的事件返回 None
+pub fn message(library: &Library, event: &Event) -> Option<String> {
+    let title = |isbn: &str| library.find_book_by_isbn(isbn).map(|book| book.title.clone()).unwrap_or_default();
+    let message = match event {
+        Event::BookAdded { book } => format!("书籍 '{}' 已添加到图书馆。", book.title),
+        Event::CopyAdded { isbn, copy_id, .. } => format!("书籍 '{}' 新增副本 #{}。", title(isbn), copy_id),
+        Event::PatronRegistered { patron } => format!("读者 {} 已登记。", patron),
+        Event::Borrowed { isbn, patron, copy_id, due, .. } => {
+            format!("书籍 '{}'（副本 #{}）已被 {} 借阅，应还日期 {}。", title(isbn), copy_id, patron, due)
+        }
+        Event::Returned { isbn, patron, copy_id, fine } => {
+            let mut message = format!("书籍 '{}'（副本 #{}）已归还。", title(isbn), copy_id);
+            if *fine > 0 {
+                message.push_str(&format!("\n{} 逾期归还，罚款 {}。", patron, format_money(*fine)));
+            }
+            message
+        }
+        Event::Renewed { isbn, patron, due } => {
+            format!("书籍 '{}' 已为 {} 续借，新的应还日期 {}。", title(isbn), patron, due)
+        }
+        Event::HoldPlaced { isbn, patron } => format!(
+            "{} 已预约书籍 '{}'，排在第 {} 位。",
+            patron,
+            title(isbn),
+            library.hold_position(isbn, patron).unwrap_or_default()
+        ),
+        Event::HoldShelved { isbn, copy_id, patron, expires } => format!(
+            "书籍 '{}'（副本 #{}）已放上预约书架，为 {} 保留至 {}。",
+            title(isbn),
+            copy_id,
+            patron,
+            expires
+        ),
+        Event::HoldExpired { isbn, patron } => format!("{} 预约的书籍 '{}' 超过取书期限，已取消。", patron, title(isbn)),
+        _ => return None,
+    };
+    Some(message)
+}
+
+// 图书馆持有的订阅者列表。订阅者属于运行时配置，不参与序列化和比较；
+// 克隆出的图书馆共用同样的订阅者
+#[derive(Clone, Default)]
+pub(crate) struct Subscribers(Vec<Arc<dyn Subscriber>>);
+
+impl Subscribers {
+    pub(crate) fn push(&mut self, subscriber: Arc<dyn Subscriber>) {
+        self.0.push(subscriber);
+    }
+
+    pub(crate) fn notify(&self, library: &Library, event: &Event) {
+        for subscriber in &self.0 {
+            subscriber.notify(library, event);
+        }
+    }
+}
+
+impl fmt::Debug for Subscribers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Subscribers({})", self.0.len())
+    }
+}
+
+impl PartialEq for Subscribers {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use chrono::{Duration, NaiveDate};
+    use crate::{store_contract, Book, ManualClock, MembershipTier};
+
+    // 收集提示语的订阅者
+    fn collecting(library: &mut Library) -> Arc<Mutex<Vec<String>>> {
+        let messages = Arc::new(Mutex::new(Vec::new()));
+        let sink = messages.clone();
+        library.subscribe(Arc::new(move |library: &Library, event: &Event| {
+            if let Some(message) = message(library, event) {
+                sink.lock().unwrap().push(message);
+            }
+        }));
+        messages
+    }
+
+    #[test]
+    fn test_subscriber_receives_messages() {
+        let now = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(10, 0, 0).unwrap();
+        let clock = Arc::new(ManualClock::new(now));
+        let mut library = Library::with_clock(clock.clone());
+        store_contract::register(&mut library, "Alice", MembershipTier::Standard);
+        store_contract::register(&mut library, "Bob", MembershipTier::Standard);
+        let messages = collecting(&mut library);
+
+        let hobbit = Book::new("The Hobbit".to_string(), "J.R.R. Tolkien".to_string(), "9780618640157".to_string());
+        library.add_book(hobbit).unwrap();
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        library.place_hold("9780618640157", "Bob").unwrap();
+        clock.advance(Duration::days(31));
+        library.return_book("9780618640157", "Alice").unwrap();
+        clock.advance(Duration::days(30));
+        library.expire_holds().unwrap();
+        // 失败的操作没有事件
+        assert!(library.borrow_book("9780618640157", "Carol").is_err());
+
+        assert_eq!(
+            *messages.lock().unwrap(),
+            vec![
+                "书籍 'The Hobbit' 已添加到图书馆。",
+                "书籍 'The Hobbit'（副本 #1）已被 Alice 借阅，应还日期 2024-01-31。",
+                "Bob 已预约书籍 'The Hobbit'，排在第 1 位。",
+                "书籍 'The Hobbit'（副本 #1）已归还。\nAlice 逾期归还，罚款 ¥0.10。",
+                "书籍 'The Hobbit'（副本 #1）已放上预约书架，为 Bob 保留至 2024-02-08。",
+                "Bob 预约的书籍 'The Hobbit' 超过取书期限，已取消。",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_format_books() {
+        let mut library = Library::new();
+        assert_eq!(library.format_books(), "图书馆目前没有书籍。");
+        library
+            .add_book(Book::new("指环王".to_string(), "托尔金".to_string(), "9787111213826".to_string()))
+            .unwrap();
+        library
+            .add_book(Book::new("The Hobbit".to_string(), "J.R.R. Tolkien".to_string(), "9780618640157".to_string()))
+            .unwrap();
+        assert_eq!(
+            library.format_books(),
+            "图书馆现有书籍: \n- The Hobbit，作者：J.R.R. Tolkien，ISBN：978-0-618-64015-7（可借 1/1）\n\
+             - 指环王，作者：托尔金，ISBN：978-7-111-21382-6（可借 1/1）"
+        );
+    }
+}
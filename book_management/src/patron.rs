@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+
+// 会员等级
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum MembershipTier {
+    Basic,
+    Standard,
+    Premium,
+}
+
+impl fmt::Display for MembershipTier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MembershipTier::Basic => write!(f, "普通"),
+            MembershipTier::Standard => write!(f, "标准"),
+            MembershipTier::Premium => write!(f, "高级"),
+        }
+    }
+}
+
+impl FromStr for MembershipTier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "basic" => Ok(MembershipTier::Basic),
+            "standard" => Ok(MembershipTier::Standard),
+            "premium" => Ok(MembershipTier::Premium),
+            _ => Err(format!("未知的会员等级: {}", s)),
+        }
+    }
+}
+
+// 读者状态
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PatronStatus {
+    #[default]
+    Active,
+    Suspended, // 被暂停借阅资格
+}
+
+// 每个会员等级的借阅限制
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TierLimits {
+    pub max_loans: usize, // 同时在借的最大册数
+    pub loan_period_days: Option<i64>, // 借期天数，未设置时使用借阅规则中的默认借期
+}
+
+// 默认的各等级借阅限制
+pub fn default_tier_limits() -> BTreeMap<MembershipTier, TierLimits> {
+    BTreeMap::from([
+        (MembershipTier::Basic, TierLimits { max_loans: 3, loan_period_days: Some(14) }),
+        (MembershipTier::Standard, TierLimits { max_loans: 5, loan_period_days: None }),
+        (MembershipTier::Premium, TierLimits { max_loans: 10, loan_period_days: Some(60) }),
+    ])
+}
+
+// 读者信息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Patron {
+    pub id: String, // 读者证号
+    pub name: String, // 姓名
+    pub contact: String, // 联系方式
+    pub tier: MembershipTier, // 会员等级
+    pub expires: NaiveDate, // 会员有效期，当天仍有效
+    pub status: PatronStatus, // 状态
+}
+
+impl Patron {
+    pub fn new(id: String, name: String, contact: String, tier: MembershipTier, expires: NaiveDate) -> Self {
+        Patron { id, name, contact, tier, expires, status: PatronStatus::Active }
+    }
+
+    pub fn is_expired(&self, today: NaiveDate) -> bool {
+        today > self.expires
+    }
+}
+
+impl fmt::Display for Patron {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}（{}），{}会员，有效期至 {}",
+            self.name, self.id, self.tier, self.expires
+        )
+    }
+}
@@ -0,0 +1,771 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use serde_json::{Map, Value};
+use super::*;
+
+// 默认的数据文件，位于当前目录
+pub const LIBRARY_DATA_FILE: &str = "library_data.json";
+
+// 定义一个 Trait 用于抽象图书馆数据的存储后端
+pub trait Storage: Send {
+    // 从存储中加载图书馆，数据不存在时返回空图书馆
+    fn load(&mut self) -> LibraryResult<Library>;
+    // 将图书馆的当前状态写入存储
+    fn save(&mut self, library: &Library) -> LibraryResult<()>;
+    // 存储所在的路径
+    fn path(&self) -> &Path;
+}
+
+// 可在运行时选择的存储后端
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageKind {
+    Json,    // 整个图书馆保存为一个 JSON 文件
+    Journal, // 只追加的变更日志，每行一条 JSON 记录
+}
+
+impl StorageKind {
+    // 根据文件扩展名推断存储后端，.jsonl 和 .journal 使用日志后端
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("jsonl") | Some("journal") => StorageKind::Journal,
+            _ => StorageKind::Json,
+        }
+    }
+
+    // 在给定路径上打开对应的存储后端
+    pub fn open(self, path: impl Into<PathBuf>) -> Box<dyn Storage> {
+        match self {
+            StorageKind::Json => Box::new(JsonFileStorage::new(path)),
+            StorageKind::Journal => Box::new(JournalStorage::new(path)),
+        }
+    }
+}
+
+impl FromStr for StorageKind {
+    type Err = LibraryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "json" => Ok(StorageKind::Json),
+            "journal" => Ok(StorageKind::Journal),
+            _ => Err(LibraryError::UnknownStorageKind(s.to_string())),
+        }
+    }
+}
+
+// 数据文件对应的审计日志，例如 library_data.json -> library_data.json.audit
+pub fn audit_log_path(path: &Path) -> PathBuf {
+    sibling_path(path, "audit")
+}
+
+// 默认保留的备份数量
+pub const DEFAULT_BACKUP_COUNT: usize = 3;
+
+// 先写入同目录下的临时文件并同步到磁盘，再原子地重命名为目标文件，
+// 这样崩溃时目标文件要么是旧内容，要么是完整的新内容
+fn write_atomically<F>(path: &Path, write: F) -> LibraryResult<()>
+where
+    F: FnOnce(&mut BufWriter<File>) -> LibraryResult<()>,
+{
+    let tmp_path = sibling_path(path, "tmp");
+    let result = (|| {
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        write(&mut writer)?;
+        writer.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+        fs::rename(&tmp_path, path)?;
+        sync_parent_dir(path);
+        Ok(())
+    })();
+    if result.is_err() {
+        let _ = fs::remove_file(&tmp_path);
+    }
+    result
+}
+
+// 在文件名后追加后缀，例如 library_data.json -> library_data.json.1
+fn sibling_path(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.file_name().map(|name| name.to_os_string()).unwrap_or_default();
+    name.push(".");
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+// 同步父目录以确保重命名本身落盘；部分平台不支持打开目录，失败时忽略
+fn sync_parent_dir(path: &Path) {
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    if let Ok(dir) = File::open(parent) {
+        let _ = dir.sync_all();
+    }
+}
+
+// JSON 文件后端：每次保存都原子地重写整个文件，并轮换保留最近的备份
+#[derive(Debug, Clone)]
+pub struct JsonFileStorage {
+    path: PathBuf,
+    backups: usize,
+}
+
+impl JsonFileStorage {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        JsonFileStorage { path: path.into(), backups: DEFAULT_BACKUP_COUNT }
+    }
+
+    // 设置保留的备份数量，0 表示不保留备份
+    pub fn with_backups(mut self, backups: usize) -> Self {
+        self.backups = backups;
+        self
+    }
+
+    // 第 n 个备份的路径，1 为最新
+    pub fn backup_path(&self, n: usize) -> PathBuf {
+        sibling_path(&self.path, &n.to_string())
+    }
+
+    // 将现有数据文件复制为 .1，较旧的备份依次后移，超出数量的被删除
+    fn rotate_backups(&self) -> LibraryResult<()> {
+        if self.backups == 0 || !self.path.exists() {
+            return Ok(());
+        }
+        let _ = fs::remove_file(self.backup_path(self.backups));
+        for n in (1..self.backups).rev() {
+            let from = self.backup_path(n);
+            if from.exists() {
+                fs::rename(&from, self.backup_path(n + 1))?;
+            }
+        }
+        fs::copy(&self.path, self.backup_path(1))?;
+        Ok(())
+    }
+
+    fn read_library(path: &Path) -> LibraryResult<Library> {
+        let reader = BufReader::new(File::open(path)?);
+        serde_json::from_reader(reader)
+            .map_err(|e| LibraryError::SerdeError(e.to_string())) // 显式转换
+    }
+
+    // 数据文件损坏时，按从新到旧的顺序寻找可读的备份。找到后把损坏的文件
+    // 复制为 .corrupt，再用备份原子地替换数据文件，之后的加载将直接成功。
+    // 任何时刻崩溃，数据文件要么仍是损坏的内容（下次加载会再次恢复），要么已是备份的内容
+    fn recover(&self, cause: LibraryError) -> LibraryError {
+        for n in 1..=self.backups {
+            let backup = self.backup_path(n);
+            let library = match Self::read_library(&backup) {
+                Ok(library) => library,
+                Err(_) => continue,
+            };
+            let restored = fs::copy(&self.path, sibling_path(&self.path, "corrupt"))
+                .map_err(LibraryError::from)
+                .and_then(|_| {
+                    write_atomically(&self.path, |writer| {
+                        io::copy(&mut File::open(&backup)?, writer)?;
+                        Ok(())
+                    })
+                });
+            if let Err(e) = restored {
+                return e;
+            }
+            return LibraryError::RecoveredFromBackup {
+                library: Box::new(library),
+                backup,
+                cause: cause.to_string(),
+            };
+        }
+        cause
+    }
+}
+
+impl Storage for JsonFileStorage {
+    fn load(&mut self) -> LibraryResult<Library> {
+        if !self.path.exists() {
+            return Ok(Library::new());
+        }
+        Self::read_library(&self.path).map_err(|e| self.recover(e))
+    }
+
+    fn save(&mut self, library: &Library) -> LibraryResult<()> {
+        self.rotate_backups()?;
+        write_atomically(&self.path, |writer| {
+            serde_json::to_writer_pretty(writer, library)
+                .map_err(|e| LibraryError::SerdeError(e.to_string())) // 显式转换
+        })
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+// 日志中的一条记录。图书馆序列化后的每个顶层字段，
+// 如果是对象（例如 books）就按键记录增删，否则整体替换
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum JournalEntry {
+    Put { table: String, key: String, value: Value },
+    Remove { table: String, key: String },
+    Set { field: String, value: Value },
+}
+
+impl JournalEntry {
+    fn apply(self, state: &mut Map<String, Value>) {
+        match self {
+            JournalEntry::Put { table, key, value } => {
+                let table = state
+                    .entry(table)
+                    .or_insert_with(|| Value::Object(Map::new()));
+                if !table.is_object() {
+                    *table = Value::Object(Map::new());
+                }
+                if let Value::Object(rows) = table {
+                    rows.insert(key, value);
+                }
+            }
+            JournalEntry::Remove { table, key } => {
+                if let Some(Value::Object(rows)) = state.get_mut(&table) {
+                    rows.remove(&key);
+                }
+            }
+            JournalEntry::Set { field, value } => {
+                state.insert(field, value);
+            }
+        }
+    }
+}
+
+// 计算从 old 到 new 所需的日志记录
+fn diff_states(old: &Map<String, Value>, new: &Map<String, Value>) -> Vec<JournalEntry> {
+    let mut entries = Vec::new();
+    for (field, new_value) in new {
+        match (old.get(field), new_value) {
+            (Some(Value::Object(old_rows)), Value::Object(new_rows)) => {
+                for (key, value) in new_rows {
+                    if old_rows.get(key) != Some(value) {
+                        entries.push(JournalEntry::Put {
+                            table: field.clone(),
+                            key: key.clone(),
+                            value: value.clone(),
+                        });
+                    }
+                }
+                for key in old_rows.keys().filter(|key| !new_rows.contains_key(*key)) {
+                    entries.push(JournalEntry::Remove { table: field.clone(), key: key.clone() });
+                }
+            }
+            (old_value, value) if old_value != Some(value) => {
+                entries.push(JournalEntry::Set { field: field.clone(), value: value.clone() });
+            }
+            _ => {}
+        }
+    }
+    entries
+}
+
+fn library_to_state(library: &Library) -> LibraryResult<Map<String, Value>> {
+    match serde_json::to_value(library)? {
+        Value::Object(state) => Ok(state),
+        _ => Err(LibraryError::SerdeError("图书馆数据必须序列化为对象".to_string())),
+    }
+}
+
+// 日志后端：每次保存只追加与上次保存相比发生变化的记录，
+// 加载时从头重放整个日志
+#[derive(Debug, Clone)]
+pub struct JournalStorage {
+    path: PathBuf,
+    state: Option<Map<String, Value>>, // 日志重放后的状态，用于计算下一次的差异
+}
+
+impl JournalStorage {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        JournalStorage { path: path.into(), state: None }
+    }
+
+    // 重放日志文件，返回序列化形式的图书馆状态
+    fn replay(&self) -> LibraryResult<Map<String, Value>> {
+        let mut state = library_to_state(&Library::new())?;
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(_) => return Ok(state),
+        };
+        let lines = BufReader::new(file).lines().collect::<Result<Vec<_>, _>>()?;
+        let last = lines.len().saturating_sub(1);
+        let mut valid_len = 0;
+        for (index, line) in lines.iter().enumerate() {
+            if !line.trim().is_empty() {
+                match serde_json::from_str::<JournalEntry>(line) {
+                    Ok(entry) => entry.apply(&mut state),
+                    // 最后一行不完整说明上次追加时被中断，截掉它，以免之后的追加接在残行后面
+                    Err(_) if index == last => {
+                        OpenOptions::new().write(true).open(&self.path)?.set_len(valid_len)?;
+                        break;
+                    }
+                    Err(e) => {
+                        return Err(LibraryError::SerdeError(format!("日志第 {} 行: {}", index + 1, e)))
+                    }
+                }
+            }
+            valid_len += line.len() as u64 + 1;
+        }
+        Ok(state)
+    }
+
+    // 将日志压缩为只包含当前状态的最少记录
+    pub fn compact(&mut self) -> LibraryResult<()> {
+        let state = self.replay()?;
+        let entries = diff_states(&Map::new(), &state);
+        write_atomically(&self.path, |writer| {
+            for entry in &entries {
+                serde_json::to_writer(&mut *writer, entry)?;
+                writer.write_all(b"\n")?;
+            }
+            Ok(())
+        })?;
+        self.state = Some(state);
+        Ok(())
+    }
+}
+
+impl Storage for JournalStorage {
+    fn load(&mut self) -> LibraryResult<Library> {
+        let state = self.replay()?;
+        let library = serde_json::from_value(Value::Object(state.clone()))?;
+        self.state = Some(state);
+        Ok(library)
+    }
+
+    fn save(&mut self, library: &Library) -> LibraryResult<()> {
+        let old = match self.state.take() {
+            Some(state) => state,
+            None => self.replay()?,
+        };
+        let new = library_to_state(library)?;
+        let entries = diff_states(&old, &new);
+        if !entries.is_empty() {
+            let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+            let mut writer = BufWriter::new(file);
+            for entry in &entries {
+                serde_json::to_writer(&mut writer, entry)?;
+                writer.write_all(b"\n")?;
+            }
+            writer.into_inner().map_err(|e| e.into_error())?.sync_data()?;
+        }
+        self.state = Some(new);
+        Ok(())
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+// 绑定了存储后端的图书馆：每次修改后都会立即写回存储
+pub struct PersistentLibrary {
+    library: Library,
+    storage: Box<dyn Storage>,
+}
+
+impl PersistentLibrary {
+    // 在给定路径上用指定后端打开图书馆
+    pub fn open(kind: StorageKind, path: impl Into<PathBuf>) -> LibraryResult<Self> {
+        Self::with_storage(kind.open(path))
+    }
+
+    // 使用任意存储后端打开图书馆
+    pub fn with_storage(mut storage: Box<dyn Storage>) -> LibraryResult<Self> {
+        let library = storage.load()?;
+        Ok(PersistentLibrary { library, storage })
+    }
+
+    pub fn library(&self) -> &Library {
+        &self.library
+    }
+
+    pub fn storage(&self) -> &dyn Storage {
+        self.storage.as_ref()
+    }
+
+    // 之后的每次修改都会写入该审计日志，日志不影响保存的数据
+    pub fn set_audit_log(&mut self, log: Arc<Mutex<AuditLog>>) {
+        self.library.set_audit_log(log);
+    }
+
+    // 显式保存当前状态
+    pub fn save(&mut self) -> LibraryResult<()> {
+        self.storage.save(&self.library)
+    }
+
+    // 对图书馆执行任意修改，成功后写回存储，例如续借：
+    // store.update(|library| library.renew_book(isbn, borrower))
+    // 修改产生的事件在写回成功后才写入审计日志、通知订阅者。任何一步失败都恢复到修改前的状态，
+    // 内存中的图书馆不会领先于存储，审计日志也不会记录被撤销的修改
+    pub fn update<T, F>(&mut self, f: F) -> LibraryResult<T>
+    where
+        F: FnOnce(&mut Library) -> LibraryResult<T>,
+    {
+        let snapshot = self.library.clone();
+        self.library.defer_events();
+        let result = f(&mut self.library).and_then(|value| {
+            self.storage.save(&self.library)?;
+            if let Err(e) = self.library.publish_deferred() {
+                // 审计日志写入失败，存储也改回修改前的状态
+                let _ = self.storage.save(&snapshot);
+                return Err(e);
+            }
+            Ok(value)
+        });
+        if result.is_err() {
+            self.library = snapshot;
+        }
+        result
+    }
+}
+
+impl BookStore for PersistentLibrary {
+    fn add_book(&mut self, book: Book) -> LibraryResult<()> {
+        self.update(|library| library.add_book(book))
+    }
+
+    fn remove_book(&mut self, isbn: &str) -> LibraryResult<Book> {
+        self.update(|library| library.remove_book(isbn))
+    }
+
+    fn update_book(&mut self, isbn: &str, update: BookUpdate) -> LibraryResult<()> {
+        self.update(|library| library.update_book(isbn, update))
+    }
+
+    fn get_book(&self, isbn: &str) -> LibraryResult<Book> {
+        BookStore::get_book(&self.library, isbn)
+    }
+
+    fn get_books_by_author(&self, author: &str) -> LibraryResult<Vec<Book>> {
+        self.library.get_books_by_author(author)
+    }
+
+    fn all_books(&self) -> LibraryResult<Vec<Book>> {
+        BookStore::all_books(&self.library)
+    }
+
+    fn register_patron(&mut self, patron: Patron) -> LibraryResult<()> {
+        self.update(|library| library.register_patron(patron))
+    }
+
+    fn borrow_book(&mut self, isbn: &str, borrower: &str) -> LibraryResult<Loan> {
+        self.update(|library| library.borrow_book(isbn, borrower))
+    }
+
+    fn return_book(&mut self, isbn: &str, borrower: &str) -> LibraryResult<ReturnReceipt> {
+        self.update(|library| library.return_book(isbn, borrower))
+    }
+}
+
+// 保存到指定路径的 JSON 文件
+pub fn save_library_to_path(library: &Library, path: impl AsRef<Path>) -> LibraryResult<()> {
+    JsonFileStorage::new(path.as_ref()).save(library)
+}
+
+// 从指定路径的 JSON 文件加载
+pub fn load_library_from_path(path: impl AsRef<Path>) -> LibraryResult<Library> {
+    JsonFileStorage::new(path.as_ref()).load()
+}
+
+pub fn save_library_to_file(library: &Library) -> LibraryResult<()> {
+    save_library_to_path(library, LIBRARY_DATA_FILE)
+}
+
+pub fn load_library_from_file() -> LibraryResult<Library> {
+    load_library_from_path(LIBRARY_DATA_FILE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_book(isbn: &str) -> Book {
+        Book::new("Rust Programming".to_string(), "John Doe".to_string(), isbn.to_string())
+    }
+
+    #[test]
+    fn test_json_storage_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catalog.json");
+
+        let mut library = Library::new();
+        store_contract::register(&mut library, "Alice", MembershipTier::Standard);
+        library.add_book(sample_book("9780618640157")).unwrap();
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        save_library_to_path(&library, &path).unwrap();
+
+        let loaded = load_library_from_path(&path).unwrap();
+        assert_eq!(loaded.find_book_by_isbn("9780618640157").unwrap().borrowers(), vec!["Alice"]);
+    }
+
+    #[test]
+    fn test_journal_storage_replays_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catalog.jsonl");
+
+        let mut store = PersistentLibrary::open(StorageKind::Journal, &path).unwrap();
+        store_contract::register_all(&mut store, &["Alice", "Bob"]);
+        store.add_book(sample_book("9780618640157")).unwrap();
+        store.add_book(sample_book("9780141439518")).unwrap();
+        store.borrow_book("9780618640157", "Alice").unwrap();
+        store.return_book("9780618640157", "Alice").unwrap();
+        store.borrow_book("9780141439518", "Bob").unwrap();
+        store.update(|library| library.renew_book("9780141439518", "Bob")).unwrap();
+
+        // 每次修改只追加变化的那本书，从不整体重写书目表
+        let journal = std::fs::read_to_string(&path).unwrap();
+        let book_puts = journal.lines().filter(|line| line.contains(r#""table":"books""#)).count();
+        assert_eq!(book_puts, 5);
+        assert!(!journal.contains(r#""field":"books""#));
+
+        let reopened = PersistentLibrary::open(StorageKind::Journal, &path).unwrap();
+        assert_eq!(reopened.all_books().unwrap().len(), 2);
+        assert!(reopened.get_book("9780618640157").unwrap().borrowers().is_empty());
+        assert_eq!(reopened.get_book("9780141439518").unwrap().borrowers(), vec!["Bob"]);
+        assert_eq!(reopened.library().active_loans()[0].renewals, 1);
+    }
+
+    #[test]
+    fn test_journal_ignores_torn_last_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catalog.jsonl");
+
+        let mut store = PersistentLibrary::open(StorageKind::Journal, &path).unwrap();
+        store.add_book(sample_book("9780618640157")).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"op\":\"put\",\"table\":\"bo").unwrap();
+
+        let reopened = PersistentLibrary::open(StorageKind::Journal, &path).unwrap();
+        assert_eq!(reopened.all_books().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_journal_save_after_torn_last_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catalog.jsonl");
+
+        let mut store = PersistentLibrary::open(StorageKind::Journal, &path).unwrap();
+        store.add_book(sample_book("9780618640157")).unwrap();
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(b"{\"op\":\"put\",\"table\":\"bo").unwrap();
+
+        // 打开时截掉残行，之后追加的记录不会接在残行后面
+        let mut store = PersistentLibrary::open(StorageKind::Journal, &path).unwrap();
+        store.add_book(sample_book("9780261103573")).unwrap();
+        assert_eq!(store.all_books().unwrap().len(), 2);
+
+        let reopened = PersistentLibrary::open(StorageKind::Journal, &path).unwrap();
+        assert_eq!(reopened.all_books().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_journal_compact() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catalog.jsonl");
+
+        let mut storage = JournalStorage::new(&path);
+        let mut library = Library::new();
+        store_contract::register(&mut library, "Alice", MembershipTier::Standard);
+        library.add_book(sample_book("9780618640157")).unwrap();
+        storage.save(&library).unwrap();
+        for _ in 0..5 {
+            library.borrow_book("9780618640157", "Alice").unwrap();
+            storage.save(&library).unwrap();
+            library.return_book("9780618640157", "Alice").unwrap();
+            storage.save(&library).unwrap();
+        }
+
+        let before = std::fs::read_to_string(&path).unwrap().lines().count();
+        storage.compact().unwrap();
+        let after = std::fs::read_to_string(&path).unwrap().lines().count();
+        assert!(after < before);
+        let loaded = JournalStorage::new(&path).load().unwrap();
+        assert!(loaded.find_book_by_isbn("9780618640157").unwrap().borrowers().is_empty());
+    }
+
+    #[test]
+    fn test_catalogs_at_different_paths_are_independent() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut first = PersistentLibrary::open(StorageKind::Json, dir.path().join("a.json")).unwrap();
+        let mut second = PersistentLibrary::open(StorageKind::Journal, dir.path().join("b.jsonl")).unwrap();
+        first.add_book(sample_book("9780618640157")).unwrap();
+        second.add_book(sample_book("9780141439518")).unwrap();
+
+        let first = PersistentLibrary::open(StorageKind::Json, dir.path().join("a.json")).unwrap();
+        assert!(first.get_book("9780618640157").is_ok());
+        assert_eq!(first.get_book("9780141439518").err(), Some(LibraryError::BookNotFound));
+    }
+
+    #[test]
+    fn test_json_save_keeps_rotating_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut storage = JsonFileStorage::new(dir.path().join("catalog.json")).with_backups(2);
+        let mut library = Library::new();
+        for isbn in ["9780618640157", "9780141439518", "9781617294556", "9787111213826"] {
+            library.add_book(sample_book(isbn)).unwrap();
+            storage.save(&library).unwrap();
+        }
+
+        assert_eq!(load_library_from_path(storage.backup_path(1)).unwrap().all_books().unwrap().len(), 3);
+        assert_eq!(load_library_from_path(storage.backup_path(2)).unwrap().all_books().unwrap().len(), 2);
+        assert!(!storage.backup_path(3).exists());
+        assert!(!dir.path().join("catalog.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_json_load_recovers_from_newest_readable_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catalog.json");
+        let mut storage = JsonFileStorage::new(&path);
+        let mut library = Library::new();
+        library.add_book(sample_book("9780618640157")).unwrap();
+        storage.save(&library).unwrap();
+        library.add_book(sample_book("9780141439518")).unwrap();
+        storage.save(&library).unwrap();
+        library.add_book(sample_book("9781617294556")).unwrap();
+        storage.save(&library).unwrap();
+
+        // 最新的备份也损坏了，应当退回到第二个备份
+        std::fs::write(&path, "{\"books\": {").unwrap();
+        std::fs::write(storage.backup_path(1), "garbage").unwrap();
+
+        match storage.load() {
+            Err(LibraryError::RecoveredFromBackup { library, backup, .. }) => {
+                assert_eq!(library.all_books().unwrap().len(), 1);
+                assert_eq!(backup, storage.backup_path(2));
+            }
+            other => panic!("expected recovery, got {:?}", other),
+        }
+        // 数据文件已被备份替换，损坏的内容另存以便排查
+        assert_eq!(storage.load().unwrap().all_books().unwrap().len(), 1);
+        let corrupt = std::fs::read_to_string(dir.path().join("catalog.json.corrupt")).unwrap();
+        assert_eq!(corrupt, "{\"books\": {");
+        assert!(!dir.path().join("catalog.json.tmp").exists());
+    }
+
+    #[test]
+    fn test_json_load_corrupt_without_backup_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catalog.json");
+        std::fs::write(&path, "not json").unwrap();
+
+        let result = JsonFileStorage::new(&path).load();
+        assert!(matches!(result, Err(LibraryError::SerdeError(_))));
+    }
+
+    #[test]
+    fn test_storage_kind_selection() {
+        assert_eq!("journal".parse::<StorageKind>(), Ok(StorageKind::Journal));
+        assert_eq!("JSON".parse::<StorageKind>(), Ok(StorageKind::Json));
+        assert_eq!(
+            "xml".parse::<StorageKind>(),
+            Err(LibraryError::UnknownStorageKind("xml".to_string()))
+        );
+        assert_eq!(StorageKind::from_path(Path::new("a/catalog.jsonl")), StorageKind::Journal);
+        assert_eq!(StorageKind::from_path(Path::new("library_data.json")), StorageKind::Json);
+    }
+
+    #[test]
+    fn test_persistent_library_passes_store_contract() {
+        let dir = tempfile::tempdir().unwrap();
+        let count = std::cell::Cell::new(0);
+        crate::store_contract::run(|clock| {
+            count.set(count.get() + 1);
+            let path = dir.path().join(format!("library{}.json", count.get()));
+            let mut store = PersistentLibrary::open(StorageKind::Json, path).unwrap();
+            store
+                .update(|library| {
+                    library.set_clock(clock);
+                    Ok(())
+                })
+                .unwrap();
+            store
+        });
+    }
+
+    // 统计收到的事件数的订阅者
+    fn count_events(store: &mut PersistentLibrary) -> Arc<Mutex<usize>> {
+        let notified = Arc::new(Mutex::new(0));
+        let counter = notified.clone();
+        store.library.subscribe(Arc::new(move |_: &Library, _: &Event| *counter.lock().unwrap() += 1));
+        notified
+    }
+
+    #[test]
+    fn test_failed_save_rolls_back_mutation() {
+        let storage = store_contract::FailingStorage("library.json".into());
+        let mut store = PersistentLibrary::with_storage(Box::new(storage)).unwrap();
+        let log = Arc::new(Mutex::new(AuditLog::in_memory()));
+        store.set_audit_log(log.clone());
+        let notified = count_events(&mut store);
+
+        assert!(matches!(store.add_book(sample_book("9780618640157")), Err(LibraryError::IoError(_))));
+        let result = store.update(|library| {
+            store_contract::register(library, "Alice", MembershipTier::Standard);
+            Ok(())
+        });
+        assert!(matches!(result, Err(LibraryError::IoError(_))));
+
+        // 没有写入存储的修改不会留在内存中，也不会写入审计日志或通知订阅者
+        assert!(store.all_books().unwrap().is_empty());
+        assert!(store.library().get_patron("Alice").is_err());
+        assert!(log.lock().unwrap().records().is_empty());
+        assert_eq!(*notified.lock().unwrap(), 0);
+        // 之后成功的保存也不会带上被撤销的修改
+        assert_eq!(store.library(), &Library::new());
+    }
+
+    #[test]
+    fn test_failed_audit_write_rolls_back_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("library.json");
+        let audit_path = audit_log_path(&path);
+        let mut store = PersistentLibrary::open(StorageKind::Json, &path).unwrap();
+        store.set_audit_log(Arc::new(Mutex::new(AuditLog::open(&audit_path).unwrap())));
+        store.add_book(sample_book("9780618640157")).unwrap();
+        let notified = count_events(&mut store);
+
+        // 日志文件换成目录后，之后的追加都会失败
+        fs::remove_file(&audit_path).unwrap();
+        fs::create_dir(&audit_path).unwrap();
+        assert!(matches!(store.add_book(sample_book("9780261103573")), Err(LibraryError::IoError(_))));
+
+        assert_eq!(store.all_books().unwrap().len(), 1);
+        assert_eq!(*notified.lock().unwrap(), 0);
+        let reopened = PersistentLibrary::open(StorageKind::Json, &path).unwrap();
+        assert_eq!(reopened.all_books().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_events_published_after_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("library.json");
+        let mut store = PersistentLibrary::open(StorageKind::Json, &path).unwrap();
+        let log = Arc::new(Mutex::new(AuditLog::in_memory()));
+        store.set_audit_log(log.clone());
+        let saved = path.clone();
+        store.library.subscribe(Arc::new(move |_: &Library, event: &Event| {
+            // 收到通知时修改已经写入存储
+            if let Event::BookAdded { book } = event {
+                if book.isbn != "9780618640157" {
+                    return;
+                }
+                let stored = load_library_from_path(&saved).unwrap();
+                assert!(stored.find_book_by_isbn(&book.isbn).is_ok());
+            }
+        }));
+
+        store.add_book(sample_book("9780618640157")).unwrap();
+        assert_eq!(log.lock().unwrap().records().len(), 1);
+        // 失败的修改恢复为立即发布，之后直接修改图书馆时事件不会一直推迟
+        assert!(store.add_book(sample_book("9780618640157")).is_err());
+        store.library.add_book(sample_book("9780261103573")).unwrap();
+        assert_eq!(log.lock().unwrap().records().len(), 2);
+    }
+}
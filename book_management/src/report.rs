@@ -0,0 +1,518 @@
+// 流通报表：借阅排行、分类利用率、读者排行、逾期汇总和月度流通量。
+// 报表统一表示为表格，可以输出为对齐的文本、CSV 或 JSON
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use crate::metadata::UNCLASSIFIED;
+use crate::{format_money, BookFilter, Library, LibraryError, LibraryResult, Loan, Money};
+
+// 报表种类
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReportKind {
+    MostBorrowed, // 借阅次数最多的书籍
+    Utilization, // 各分类的馆藏利用率
+    TopPatrons, // 借阅最多的读者
+    Overdue, // 逾期未还的借阅
+    Monthly, // 每月借出和归还的数量
+}
+
+impl FromStr for ReportKind {
+    type Err = LibraryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "most-borrowed" | "popular" => Ok(ReportKind::MostBorrowed),
+            "utilization" => Ok(ReportKind::Utilization),
+            "top-patrons" | "patrons" => Ok(ReportKind::TopPatrons),
+            "overdue" => Ok(ReportKind::Overdue),
+            "monthly" | "circulation" => Ok(ReportKind::Monthly),
+            _ => Err(LibraryError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+// 报表的输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Text, // 列对齐的文本表格
+    Csv,
+    Json, // 对象数组，键为列名
+}
+
+impl FromStr for ReportFormat {
+    type Err = LibraryError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" | "txt" => Ok(ReportFormat::Text),
+            "csv" => Ok(ReportFormat::Csv),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(LibraryError::UnknownFormat(s.to_string())),
+        }
+    }
+}
+
+// 统计范围。借阅按借出日期、归还按归还日期落入 [from, to]，两端为 None 时不限制；
+// 逾期汇总和利用率反映当前状态，不受日期范围影响
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReportOptions {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    pub limit: Option<usize>, // 排行类报表最多列出的行数
+}
+
+impl ReportOptions {
+    fn contains(&self, date: NaiveDate) -> bool {
+        self.from.is_none_or(|from| date >= from) && self.to.is_none_or(|to| date <= to)
+    }
+
+    fn truncate<T>(&self, rows: &mut Vec<T>) {
+        if let Some(limit) = self.limit {
+            rows.truncate(limit);
+        }
+    }
+}
+
+// 表格中的一格。JSON 和 CSV 中保留原始数值，文本中按含义格式化
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Cell {
+    Text(String),
+    Count(u64),
+    Percent(f64), // 百分比，例如 37.5
+    Money(Money), // 以分为单位
+    Date(NaiveDate),
+}
+
+impl Cell {
+    fn display(&self) -> String {
+        match self {
+            Cell::Text(text) => text.clone(),
+            Cell::Count(count) => count.to_string(),
+            Cell::Percent(percent) => format!("{:.1}%", percent),
+            Cell::Money(amount) => format_money(*amount),
+            Cell::Date(date) => date.to_string(),
+        }
+    }
+
+    fn raw(&self) -> String {
+        match self {
+            Cell::Percent(percent) => format!("{:.1}", percent),
+            Cell::Money(amount) => amount.to_string(),
+            other => other.display(),
+        }
+    }
+
+    fn is_numeric(&self) -> bool {
+        !matches!(self, Cell::Text(_) | Cell::Date(_))
+    }
+}
+
+// 表格的一列：给程序用的键和给人看的表头
+#[derive(Debug, Clone, PartialEq)]
+pub struct Column {
+    pub key: &'static str,
+    pub header: &'static str,
+}
+
+const fn column(key: &'static str, header: &'static str) -> Column {
+    Column { key, header }
+}
+
+// 一份报表
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub title: String,
+    pub columns: Vec<Column>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Report {
+    pub fn render(&self, format: ReportFormat) -> LibraryResult<String> {
+        match format {
+            ReportFormat::Text => Ok(self.to_text()),
+            ReportFormat::Csv => self.to_csv(),
+            ReportFormat::Json => Ok(self.to_json().to_string()),
+        }
+    }
+
+    // 对象数组，键为列名
+    pub fn to_json(&self) -> Value {
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                let object: Map<String, Value> = self
+                    .columns
+                    .iter()
+                    .zip(row)
+                    .map(|(column, cell)| (column.key.to_string(), serde_json::to_value(cell).unwrap_or(Value::Null)))
+                    .collect();
+                Value::Object(object)
+            })
+            .collect();
+        Value::Array(rows)
+    }
+
+    fn to_csv(&self) -> LibraryResult<String> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(self.columns.iter().map(|column| column.key))?;
+        for row in &self.rows {
+            writer.write_record(row.iter().map(Cell::raw))?;
+        }
+        let bytes = writer.into_inner().map_err(|e| LibraryError::IoError(e.to_string()))?;
+        Ok(String::from_utf8_lossy(&bytes).into_owned())
+    }
+
+    // 标题、表头和数据行，数值列右对齐。中文按两个字符宽度计算
+    fn to_text(&self) -> String {
+        if self.rows.is_empty() {
+            return format!("{}\n（无数据）", self.title);
+        }
+        let mut widths: Vec<usize> = self.columns.iter().map(|column| display_width(column.header)).collect();
+        let cells: Vec<Vec<String>> = self.rows.iter().map(|row| row.iter().map(Cell::display).collect()).collect();
+        for row in &cells {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(display_width(cell));
+            }
+        }
+        let numeric: Vec<bool> = (0..self.columns.len())
+            .map(|i| self.rows.iter().all(|row| row.get(i).is_some_and(Cell::is_numeric)))
+            .collect();
+        let line = |values: Vec<&str>| {
+            values
+                .iter()
+                .zip(&widths)
+                .zip(&numeric)
+                .map(|((value, width), numeric)| {
+                    let padding = " ".repeat(width - display_width(value));
+                    if *numeric { format!("{}{}", padding, value) } else { format!("{}{}", value, padding) }
+                })
+                .collect::<Vec<_>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        };
+        let mut lines = vec![self.title.clone(), line(self.columns.iter().map(|column| column.header).collect())];
+        lines.push(widths.iter().map(|width| "-".repeat(*width)).collect::<Vec<_>>().join("  "));
+        lines.extend(cells.iter().map(|row| line(row.iter().map(String::as_str).collect())));
+        lines.join("\n")
+    }
+}
+
+// 终端中的显示宽度，中日韩字符和全角符号占两列
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x1100..=0x115F | 0x2E80..=0xA4CF | 0xAC00..=0xD7A3 | 0xF900..=0xFAFF | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60 | 0xFFE0..=0xFFE6 => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+fn percent(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        (part as f64 * 1000.0 / total as f64).round() / 10.0
+    }
+}
+
+// 生成指定种类的报表
+pub fn generate(library: &Library, kind: ReportKind, options: &ReportOptions) -> Report {
+    match kind {
+        ReportKind::MostBorrowed => most_borrowed(library, options),
+        ReportKind::Utilization => utilization(library, options),
+        ReportKind::TopPatrons => top_patrons(library, options),
+        ReportKind::Overdue => overdue(library),
+        ReportKind::Monthly => monthly_circulation(library, options),
+    }
+}
+
+fn loans_in_range<'a>(library: &'a Library, options: &ReportOptions) -> Vec<&'a Loan> {
+    library.loans().into_iter().filter(|loan| options.contains(loan.checked_out.date())).collect()
+}
+
+// 借阅次数最多的书籍，次数相同时按标题排序。已移除的书籍仍按 ISBN 计入
+pub fn most_borrowed(library: &Library, options: &ReportOptions) -> Report {
+    let mut counts: BTreeMap<&str, u64> = BTreeMap::new();
+    for loan in loans_in_range(library, options) {
+        *counts.entry(&loan.isbn).or_default() += 1;
+    }
+    let mut rows: Vec<(u64, String, String, &str)> = counts
+        .into_iter()
+        .map(|(isbn, count)| {
+            let (title, author) = library
+                .find_book_by_isbn(isbn)
+                .map(|book| (book.title.clone(), book.author.clone()))
+                .unwrap_or_default();
+            (count, title, author, isbn)
+        })
+        .collect();
+    rows.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)).then_with(|| a.3.cmp(b.3)));
+    options.truncate(&mut rows);
+    Report {
+        title: "借阅排行".to_string(),
+        columns: vec![column("isbn", "ISBN"), column("title", "标题"), column("author", "作者"), column("loans", "借阅次数")],
+        rows: rows
+            .into_iter()
+            .map(|(count, title, author, isbn)| {
+                vec![Cell::Text(isbn.to_string()), Cell::Text(title), Cell::Text(author), Cell::Count(count)]
+            })
+            .collect(),
+    }
+}
+
+// 各分类的副本数、在借副本数和利用率（在借副本占比），以及统计范围内的借阅次数
+pub fn utilization(library: &Library, options: &ReportOptions) -> Report {
+    #[derive(Default)]
+    struct Usage {
+        titles: usize,
+        copies: usize,
+        on_loan: usize,
+        loans: u64,
+    }
+    let category_of = |isbn: &str| {
+        library
+            .find_book_by_isbn(isbn)
+            .ok()
+            .and_then(|book| book.category.clone())
+            .unwrap_or_else(|| UNCLASSIFIED.to_string())
+    };
+    let mut usage: BTreeMap<String, Usage> = BTreeMap::new();
+    for book in library.filter_books(&BookFilter::default()) {
+        let entry = usage.entry(book.category.clone().unwrap_or_else(|| UNCLASSIFIED.to_string())).or_default();
+        entry.titles += 1;
+        entry.copies += book.total_copies();
+        entry.on_loan += book.total_copies() - book.available_copies();
+    }
+    for loan in loans_in_range(library, options) {
+        usage.entry(category_of(&loan.isbn)).or_default().loans += 1;
+    }
+    Report {
+        title: "分类利用率".to_string(),
+        columns: vec![
+            column("category", "分类"),
+            column("titles", "品种"),
+            column("copies", "副本"),
+            column("on_loan", "在借"),
+            column("utilization", "利用率"),
+            column("loans", "借阅次数"),
+        ],
+        rows: usage
+            .into_iter()
+            .map(|(category, usage)| {
+                vec![
+                    Cell::Text(category),
+                    Cell::Count(usage.titles as u64),
+                    Cell::Count(usage.copies as u64),
+                    Cell::Count(usage.on_loan as u64),
+                    Cell::Percent(percent(usage.on_loan, usage.copies)),
+                    Cell::Count(usage.loans),
+                ]
+            })
+            .collect(),
+    }
+}
+
+// 借阅次数最多的读者，同时列出当前在借和逾期的数量
+pub fn top_patrons(library: &Library, options: &ReportOptions) -> Report {
+    let today = library.today();
+    let mut counts: BTreeMap<&str, (u64, u64, u64)> = BTreeMap::new();
+    for loan in loans_in_range(library, options) {
+        let entry = counts.entry(&loan.borrower).or_default();
+        entry.0 += 1;
+        entry.1 += loan.is_active() as u64;
+        entry.2 += loan.is_overdue(today) as u64;
+    }
+    let mut rows: Vec<(&str, (u64, u64, u64))> = counts.into_iter().collect();
+    rows.sort_by(|a, b| b.1 .0.cmp(&a.1 .0).then_with(|| a.0.cmp(b.0)));
+    options.truncate(&mut rows);
+    Report {
+        title: "读者借阅排行".to_string(),
+        columns: vec![
+            column("patron", "读者证号"),
+            column("name", "姓名"),
+            column("loans", "借阅次数"),
+            column("active", "在借"),
+            column("overdue", "逾期"),
+        ],
+        rows: rows
+            .into_iter()
+            .map(|(id, (loans, active, overdue))| {
+                let name = library.get_patron(id).map(|patron| patron.name.clone()).unwrap_or_default();
+                vec![
+                    Cell::Text(id.to_string()),
+                    Cell::Text(name),
+                    Cell::Count(loans),
+                    Cell::Count(active),
+                    Cell::Count(overdue),
+                ]
+            })
+            .collect(),
+    }
+}
+
+// 截至今天逾期未还的借阅，按逾期天数从多到少排列，罚款按现在归还估算
+pub fn overdue(library: &Library) -> Report {
+    let today = library.today();
+    let mut loans = library.overdue_loans(today);
+    loans.sort_by(|a, b| b.days_overdue(today).cmp(&a.days_overdue(today)).then_with(|| a.id.cmp(&b.id)));
+    let mut total: Money = 0;
+    let rows = loans
+        .into_iter()
+        .map(|loan| {
+            let book = library.find_book_by_isbn(&loan.isbn).ok();
+            let days = loan.days_overdue(today);
+            let fine = library.fine_policy().calculate(days, book.and_then(|book| book.category.as_deref()));
+            total += fine;
+            vec![
+                Cell::Count(loan.id),
+                Cell::Text(loan.isbn.clone()),
+                Cell::Text(book.map(|book| book.title.clone()).unwrap_or_default()),
+                Cell::Text(loan.borrower.clone()),
+                Cell::Date(loan.due),
+                Cell::Count(days as u64),
+                Cell::Money(fine),
+            ]
+        })
+        .collect::<Vec<_>>();
+    Report {
+        title: format!("逾期未还（截至 {}，共 {} 笔，预计罚款 {}）", today, rows.len(), format_money(total)),
+        columns: vec![
+            column("loan_id", "借阅编号"),
+            column("isbn", "ISBN"),
+            column("title", "标题"),
+            column("patron", "借阅者"),
+            column("due", "应还日期"),
+            column("days_overdue", "逾期天数"),
+            column("fine", "预计罚款"),
+        ],
+        rows,
+    }
+}
+
+// 每月的借出和归还数量，月份形如 2024-01
+pub fn monthly_circulation(library: &Library, options: &ReportOptions) -> Report {
+    let mut months: BTreeMap<String, (u64, u64)> = BTreeMap::new();
+    for loan in library.loans() {
+        let checked_out = loan.checked_out.date();
+        if options.contains(checked_out) {
+            months.entry(checked_out.format("%Y-%m").to_string()).or_default().0 += 1;
+        }
+        if let Some(returned) = loan.returned.map(|returned| returned.date()).filter(|date| options.contains(*date)) {
+            months.entry(returned.format("%Y-%m").to_string()).or_default().1 += 1;
+        }
+    }
+    Report {
+        title: "月度流通量".to_string(),
+        columns: vec![column("month", "月份"), column("checkouts", "借出"), column("returns", "归还")],
+        rows: months
+            .into_iter()
+            .map(|(month, (checkouts, returns))| vec![Cell::Text(month), Cell::Count(checkouts), Cell::Count(returns)])
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use chrono::Duration;
+    use crate::{store_contract, Book, ManualClock, MembershipTier};
+
+    // 两本书三位读者，跨两个月的借阅历史
+    fn library() -> (Library, Arc<ManualClock>) {
+        let now = NaiveDate::from_ymd_opt(2024, 1, 10).unwrap().and_hms_opt(10, 0, 0).unwrap();
+        let clock = Arc::new(ManualClock::new(now));
+        let mut library = Library::with_clock(clock.clone());
+        for patron in ["Alice", "Bob", "Carol"] {
+            store_contract::register(&mut library, patron, MembershipTier::Standard);
+        }
+        let mut hobbit = Book::new("The Hobbit".to_string(), "J.R.R. Tolkien".to_string(), "9780618640157".to_string());
+        hobbit.category = Some("小说".to_string());
+        library.add_book(hobbit).unwrap();
+        library.add_copy("9780618640157", String::new(), Default::default()).unwrap();
+        let sicp = Book::new("SICP".to_string(), "Abelson".to_string(), "9780262510875".to_string());
+        library.add_book(sicp).unwrap();
+
+        library.borrow_book("9780618640157", "Alice").unwrap();
+        library.borrow_book("9780262510875", "Alice").unwrap();
+        clock.advance(Duration::days(5));
+        library.return_book("9780618640157", "Alice").unwrap();
+        library.borrow_book("9780618640157", "Bob").unwrap();
+        clock.advance(Duration::days(30));
+        library.return_book("9780618640157", "Bob").unwrap();
+        library.borrow_book("9780618640157", "Carol").unwrap();
+        (library, clock)
+    }
+
+    fn column_of(report: &Report, key: &str) -> Vec<String> {
+        let index = report.columns.iter().position(|column| column.key == key).unwrap();
+        report.rows.iter().map(|row| row[index].raw()).collect()
+    }
+
+    #[test]
+    fn test_rankings_and_utilization() {
+        let (library, _) = library();
+        let report = most_borrowed(&library, &ReportOptions { limit: Some(1), ..Default::default() });
+        assert_eq!(column_of(&report, "title"), vec!["The Hobbit"]);
+        assert_eq!(column_of(&report, "loans"), vec!["3"]);
+
+        let report = top_patrons(&library, &ReportOptions::default());
+        assert_eq!(column_of(&report, "patron"), vec!["Alice", "Bob", "Carol"]);
+        assert_eq!(column_of(&report, "loans"), vec!["2", "1", "1"]);
+        assert_eq!(column_of(&report, "overdue"), vec!["1", "0", "0"]);
+
+        // 小说 2 个副本借出 1 个，未分类的 SICP 仍被 Alice 借着
+        let report = utilization(&library, &ReportOptions::default());
+        assert_eq!(column_of(&report, "category"), vec!["小说", UNCLASSIFIED]);
+        assert_eq!(column_of(&report, "utilization"), vec!["50.0", "100.0"]);
+
+        let since_february = ReportOptions { from: NaiveDate::from_ymd_opt(2024, 2, 1), ..Default::default() };
+        assert_eq!(column_of(&most_borrowed(&library, &since_february), "loans"), vec!["1"]);
+    }
+
+    #[test]
+    fn test_overdue_and_monthly() {
+        let (library, _) = library();
+        let report = overdue(&library);
+        assert_eq!(column_of(&report, "patron"), vec!["Alice"]);
+        assert_eq!(column_of(&report, "days_overdue"), vec!["5"]);
+        assert_eq!(report.title, "逾期未还（截至 2024-02-14，共 1 笔，预计罚款 ¥0.50）");
+
+        let report = monthly_circulation(&library, &ReportOptions::default());
+        assert_eq!(column_of(&report, "month"), vec!["2024-01", "2024-02"]);
+        assert_eq!(column_of(&report, "checkouts"), vec!["3", "1"]);
+        assert_eq!(column_of(&report, "returns"), vec!["1", "1"]);
+    }
+
+    #[test]
+    fn test_render_formats() {
+        let (library, _) = library();
+        let report = overdue(&library);
+        assert_eq!(
+            report.render(ReportFormat::Csv).unwrap(),
+            "loan_id,isbn,title,patron,due,days_overdue,fine\n2,9780262510875,SICP,Alice,2024-02-09,5,50\n"
+        );
+        let json: Value = serde_json::from_str(&report.render(ReportFormat::Json).unwrap()).unwrap();
+        assert_eq!(json[0]["fine"], 50);
+        assert_eq!(json[0]["due"], "2024-02-09");
+
+        let text = utilization(&library, &ReportOptions::default()).render(ReportFormat::Text).unwrap();
+        assert_eq!(
+            text,
+            "分类利用率\n\
+             分类    品种  副本  在借  利用率  借阅次数\n\
+             ------  ----  ----  ----  ------  --------\n\
+             小说       1     2     1   50.0%         3\n\
+             未分类     1     1     1  100.0%         1"
+        );
+        assert_eq!("monthly".parse::<ReportKind>().unwrap(), ReportKind::Monthly);
+        assert!("weekly".parse::<ReportKind>().is_err());
+    }
+}
@@ -0,0 +1,286 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::sync::OnceLock;
+use unicode_normalization::UnicodeNormalization;
+use crate::Book;
+
+// 标题中的词比作者中的词权重更高
+const TITLE_WEIGHT: f64 = 2.0;
+const AUTHOR_WEIGHT: f64 = 1.0;
+
+// 将文本切分为检索用的词：转为小写并去掉重音符号，
+// 拉丁文字按非字母数字字符切分，中日韩文字每个字单独成词
+pub fn tokenize(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    for c in fold(text).chars() {
+        if is_cjk(c) {
+            if !current.is_empty() {
+                tokens.push(std::mem::take(&mut current));
+            }
+            tokens.push(c.to_string());
+        } else if c.is_alphanumeric() {
+            current.push(c);
+        } else if !current.is_empty() {
+            tokens.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+// 小写并去掉重音符号，例如 "Brontë" -> "bronte"
+pub fn fold(text: &str) -> String {
+    text.nfd()
+        .filter(|c| !('\u{0300}'..='\u{036f}').contains(c))
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32,
+        0x3040..=0x30FF // 平假名、片假名
+        | 0x3400..=0x4DBF // 扩展 A
+        | 0x4E00..=0x9FFF // 基本汉字
+        | 0xAC00..=0xD7AF // 韩文音节
+        | 0xF900..=0xFAFF // 兼容汉字
+        | 0x20000..=0x2A6DF) // 扩展 B
+}
+
+// 某个词在一本书中出现的次数
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Posting {
+    title: u32,
+    author: u32,
+}
+
+// 标题和作者的倒排索引：词 -> (ISBN -> 出现次数)
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchIndex {
+    terms: BTreeMap<String, HashMap<String, Posting>>,
+    documents: HashSet<String>,
+}
+
+impl SearchIndex {
+    pub fn build<'a>(books: impl IntoIterator<Item = &'a Book>) -> Self {
+        let mut index = SearchIndex::default();
+        for book in books {
+            index.insert(book);
+        }
+        index
+    }
+
+    // 加入或更新一本书
+    pub fn insert(&mut self, book: &Book) {
+        self.remove(&book.isbn);
+        for token in tokenize(&book.title) {
+            self.posting(token, &book.isbn).title += 1;
+        }
+        for token in book.authors().into_iter().flat_map(tokenize) {
+            self.posting(token, &book.isbn).author += 1;
+        }
+        self.documents.insert(book.isbn.clone());
+    }
+
+    // 从索引中移除一本书
+    pub fn remove(&mut self, isbn: &str) {
+        if !self.documents.remove(isbn) {
+            return;
+        }
+        self.terms.retain(|_, postings| {
+            postings.remove(isbn);
+            !postings.is_empty()
+        });
+    }
+
+    fn posting(&mut self, token: String, isbn: &str) -> &mut Posting {
+        self.terms.entry(token).or_default().entry(isbn.to_string()).or_default()
+    }
+
+    // 执行查询，返回按相关度从高到低排序的 (ISBN, 得分)
+    pub fn search(&self, query: &str) -> Vec<(String, f64)> {
+        let mut scores: HashMap<String, f64> = HashMap::new();
+        for clause in parse_query(query) {
+            let mut clause_scores: Option<HashMap<String, f64>> = None;
+            for term in &clause {
+                let term_scores = self.score_term(term);
+                clause_scores = Some(match clause_scores {
+                    None => term_scores,
+                    // AND：只保留每个词都命中的书，得分累加
+                    Some(previous) => previous
+                        .into_iter()
+                        .filter_map(|(isbn, score)| term_scores.get(&isbn).map(|s| (isbn, score + s)))
+                        .collect(),
+                });
+            }
+            // OR：任一子句命中即可，取最高得分
+            for (isbn, score) in clause_scores.unwrap_or_default() {
+                let entry = scores.entry(isbn).or_insert(0.0);
+                *entry = entry.max(score);
+            }
+        }
+        let mut results: Vec<(String, f64)> = scores.into_iter().collect();
+        results.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        results
+    }
+
+    // 单个词的得分：按标题/作者加权的词频乘以逆文档频率
+    fn score_term(&self, term: &QueryTerm) -> HashMap<String, f64> {
+        let matching: Vec<&HashMap<String, Posting>> = if term.prefix {
+            self.terms
+                .range(term.text.clone()..)
+                .take_while(|(token, _)| token.starts_with(&term.text))
+                .map(|(_, postings)| postings)
+                .collect()
+        } else {
+            self.terms.get(&term.text).into_iter().collect()
+        };
+        let total = self.documents.len().max(1) as f64;
+        let mut scores = HashMap::new();
+        for postings in matching {
+            let idf = (1.0 + total / postings.len() as f64).ln();
+            for (isbn, posting) in postings {
+                let tf = posting.title as f64 * TITLE_WEIGHT + posting.author as f64 * AUTHOR_WEIGHT;
+                *scores.entry(isbn.clone()).or_insert(0.0) += tf * idf;
+            }
+        }
+        scores
+    }
+}
+
+// 检索结果
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult<'a> {
+    pub book: &'a Book,
+    pub score: f64, // 相关度，越高越相关
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct QueryTerm {
+    text: String,
+    prefix: bool, // 以 * 结尾的词按前缀匹配
+}
+
+// 将查询解析为“OR 连接的若干 AND 子句”。词之间默认是 AND，
+// 用 OR（或 |）分隔不同的子句，AND 可以省略
+fn parse_query(query: &str) -> Vec<Vec<QueryTerm>> {
+    let mut clauses = vec![Vec::new()];
+    for word in query.split_whitespace() {
+        match word {
+            "OR" | "|" => clauses.push(Vec::new()),
+            "AND" | "&" => {}
+            _ => {
+                let prefix = word.ends_with('*');
+                let tokens = tokenize(word.trim_end_matches('*'));
+                let count = tokens.len();
+                let clause = clauses.last_mut().unwrap();
+                for (i, text) in tokens.into_iter().enumerate() {
+                    clause.push(QueryTerm { text, prefix: prefix && i + 1 == count });
+                }
+            }
+        }
+    }
+    clauses.retain(|clause| !clause.is_empty());
+    clauses
+}
+
+// 图书馆持有的索引，首次检索时才从书目构建，之后随书目增删同步更新。
+// 索引可以随时从书目重建，因此不参与序列化和比较
+#[derive(Clone, Default)]
+pub(crate) struct LazyIndex(OnceLock<SearchIndex>);
+
+impl LazyIndex {
+    pub(crate) fn get_or_build<'a>(&self, books: impl IntoIterator<Item = &'a Book>) -> &SearchIndex {
+        self.0.get_or_init(|| SearchIndex::build(books))
+    }
+
+    // 索引尚未构建时无需维护
+    pub(crate) fn get_mut(&mut self) -> Option<&mut SearchIndex> {
+        self.0.get_mut()
+    }
+}
+
+impl fmt::Debug for LazyIndex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let state = if self.0.get().is_some() { "built" } else { "empty" };
+        write!(f, "LazyIndex({})", state)
+    }
+}
+
+impl PartialEq for LazyIndex {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn book(title: &str, author: &str, isbn: &str) -> Book {
+        Book::new(title.to_string(), author.to_string(), isbn.to_string())
+    }
+
+    fn sample_index() -> SearchIndex {
+        SearchIndex::build(&[
+            book("The Lord of the Rings", "J.R.R. Tolkien", "1"),
+            book("The Hobbit", "J.R.R. Tolkien", "2"),
+            book("Jane Eyre", "Charlotte Brontë", "3"),
+            book("指环王", "托尔金", "4"),
+            book("Rust in Action", "Tim McNamara", "5"),
+        ])
+    }
+
+    fn isbns(results: Vec<(String, f64)>) -> Vec<String> {
+        results.into_iter().map(|(isbn, _)| isbn).collect()
+    }
+
+    #[test]
+    fn test_tokenize_folds_case_accents_and_cjk() {
+        assert_eq!(tokenize("Charlotte BRONTË"), vec!["charlotte", "bronte"]);
+        assert_eq!(tokenize("J.R.R. Tolkien"), vec!["j", "r", "r", "tolkien"]);
+        assert_eq!(tokenize("Rust编程之道"), vec!["rust", "编", "程", "之", "道"]);
+    }
+
+    #[test]
+    fn test_search_is_case_and_accent_insensitive() {
+        let index = sample_index();
+        assert_eq!(isbns(index.search("bronte")), vec!["3"]);
+        assert_eq!(isbns(index.search("JANE")), vec!["3"]);
+    }
+
+    #[test]
+    fn test_search_and_or_prefix() {
+        let index = sample_index();
+        assert_eq!(isbns(index.search("tolkien hobbit")), vec!["2"]);
+        assert_eq!(isbns(index.search("hobbit OR rust")).len(), 2);
+        assert_eq!(isbns(index.search("tolk*")).len(), 2);
+        assert!(index.search("tolk").is_empty());
+    }
+
+    #[test]
+    fn test_search_ranks_title_matches_higher() {
+        let index = SearchIndex::build(&[
+            book("Notes on Rust", "Jane Doe", "1"),
+            book("Cooking", "Rust Cohle", "2"),
+        ]);
+        assert_eq!(isbns(index.search("rust")), vec!["1", "2"]);
+    }
+
+    #[test]
+    fn test_search_cjk_per_character() {
+        let index = sample_index();
+        assert_eq!(isbns(index.search("指环")), vec!["4"]);
+        assert_eq!(isbns(index.search("托尔金")), vec!["4"]);
+    }
+
+    #[test]
+    fn test_remove_from_index() {
+        let mut index = sample_index();
+        index.remove("2");
+        assert!(index.search("hobbit").is_empty());
+        assert_eq!(isbns(index.search("tolkien")), vec!["1"]);
+    }
+}
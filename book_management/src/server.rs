@@ -0,0 +1,509 @@
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Mutex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tiny_http::{Header, Request, Response};
+use crate::persistence::Storage;
+use crate::{Book, BookFilter, BookStore, Library, LibraryError, LibraryResult, Metadata, SharedLibrary};
+
+// 路由处理的结果：状态码和 JSON 响应体
+#[derive(Debug, Clone, PartialEq)]
+pub struct Reply {
+    pub status: u16,
+    pub body: Value,
+}
+
+impl Reply {
+    fn ok(body: Value) -> Self {
+        Reply { status: 200, body }
+    }
+
+    fn created(body: Value) -> Self {
+        Reply { status: 201, body }
+    }
+
+    fn error(status: u16, code: &str, message: &str) -> Self {
+        Reply { status, body: json!({ "error": code, "message": message }) }
+    }
+}
+
+impl From<LibraryError> for Reply {
+    fn from(error: LibraryError) -> Self {
+        Reply::error(status_for(&error), error_code(&error), &error.to_string())
+    }
+}
+
+// 每种错误对应的 HTTP 状态码
+pub fn status_for(error: &LibraryError) -> u16 {
+    use LibraryError::*;
+    match error {
+        BookNotFound | PatronNotFound | HoldNotFound => 404,
+        BookAlreadyExists | PatronAlreadyExists | HoldAlreadyPlaced | BookAlreadyBorrowed | BookNotBorrowed
+        | BookOnHold | BookAvailable | BookHasHolds { .. } => 409,
+        PatronSuspended | MembershipExpired | UnpaidFines { .. } | LoanLimitReached { .. } | RenewalLimitReached => 403,
+        InvalidIsbn(_) | InvalidClassification(_) | InvalidAmount | MissingField(_) | SerdeError(_) | UnknownFormat(_) | UnknownStorageKind(_) => 400,
+        IoError(_) | DatabaseError(_) | RecoveredFromBackup { .. } => 500,
+    }
+}
+
+// 错误响应中给程序判断用的错误代码
+pub fn error_code(error: &LibraryError) -> &'static str {
+    use LibraryError::*;
+    match error {
+        BookAlreadyExists => "book_already_exists",
+        BookNotFound => "book_not_found",
+        BookAlreadyBorrowed => "book_already_borrowed",
+        BookNotBorrowed => "book_not_borrowed",
+        RenewalLimitReached => "renewal_limit_reached",
+        UnpaidFines { .. } => "unpaid_fines",
+        InvalidAmount => "invalid_amount",
+        BookOnHold => "book_on_hold",
+        BookHasHolds { .. } => "book_has_holds",
+        BookAvailable => "book_available",
+        HoldAlreadyPlaced => "hold_already_placed",
+        HoldNotFound => "hold_not_found",
+        PatronAlreadyExists => "patron_already_exists",
+        PatronNotFound => "patron_not_found",
+        PatronSuspended => "patron_suspended",
+        MembershipExpired => "membership_expired",
+        LoanLimitReached { .. } => "loan_limit_reached",
+        InvalidIsbn(_) => "invalid_isbn",
+        InvalidClassification(_) => "invalid_classification",
+        IoError(_) => "io_error",
+        SerdeError(_) => "invalid_json",
+        UnknownStorageKind(_) => "unknown_storage_kind",
+        UnknownFormat(_) => "unknown_format",
+        MissingField(_) => "missing_field",
+        DatabaseError(_) => "database_error",
+        RecoveredFromBackup { .. } => "recovered_from_backup",
+    }
+}
+
+// POST /books 的请求体
+#[derive(Deserialize)]
+struct NewBook {
+    title: String,
+    author: String,
+    isbn: String,
+    #[serde(default)]
+    category: Option<String>,
+    #[serde(flatten)]
+    metadata: Metadata, // publisher、year、subjects 等扩展信息，都可以省略
+}
+
+// 借阅和归还的请求体
+#[derive(Deserialize)]
+struct PatronBody {
+    patron: String,
+}
+
+fn parse_body<'a, T: Deserialize<'a>>(body: &'a str) -> LibraryResult<T> {
+    Ok(serde_json::from_str(body)?)
+}
+
+// 处理一个请求。path 可以带查询参数，GET /books?author=... 按作者过滤，
+// 也可以按 publisher、year_from、year_to、edition、language、subject、category、class 筛选。
+// 返回的布尔值表示图书馆是否被修改，需要写回存储
+pub fn route(store: &SharedLibrary, method: &str, path: &str, body: &str) -> (Reply, bool) {
+    let (path, query) = path.split_once('?').unwrap_or((path, ""));
+    let segments: Vec<String> = path.split('/').filter(|s| !s.is_empty()).map(percent_decode).collect();
+    let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+    let mut store = store.clone();
+    let result = match (method, segments.as_slice()) {
+        ("GET", ["books"]) => match books_filter(query) {
+            Ok(filter) if filter.is_empty() => store.all_books().map(|books| Reply::ok(json!(books))),
+            Ok(BookFilter { author: Some(author), .. }) if query.split('&').count() == 1 => {
+                store.get_books_by_author(&author).map(|books| Reply::ok(json!(books)))
+            }
+            Ok(filter) => Ok(Reply::ok(store.read(|library| json!(library.filter_books(&filter))))),
+            Err(reply) => return (reply, false),
+        },
+        ("POST", ["books"]) => parse_body::<NewBook>(body).and_then(|new| {
+            let mut book = Book::new(new.title, new.author, new.isbn);
+            book.category = new.category;
+            book.metadata = new.metadata;
+            let isbn = book.isbn.clone();
+            store.add_book(book)?;
+            Ok(Reply::created(json!(store.get_book(&isbn)?)))
+        }),
+        ("GET", ["books", isbn]) => store.get_book(isbn).map(|book| Reply::ok(json!(book))),
+        ("POST", ["books", isbn, "borrow"]) => parse_body::<PatronBody>(body)
+            .and_then(|body| store.borrow_book(isbn, &body.patron))
+            .map(|loan| Reply::created(json!(loan))),
+        ("POST", ["books", isbn, "return"]) => parse_body::<PatronBody>(body)
+            .and_then(|body| store.return_book(isbn, &body.patron))
+            .map(|receipt| {
+                Reply::ok(json!({
+                    "copy_id": receipt.copy_id,
+                    "loan_id": receipt.loan_id,
+                    "fine": receipt.fine,
+                    "hold_for": receipt.hold_for,
+                }))
+            }),
+        ("GET", ["patrons", id, "loans"]) => store.read(|library| {
+            library.get_patron(id)?;
+            Ok(Reply::ok(json!(library.loans_for(id))))
+        }),
+        (_, ["books"]) | (_, ["books", _]) | (_, ["books", _, "borrow" | "return"]) | (_, ["patrons", _, "loans"]) => {
+            return (Reply::error(405, "method_not_allowed", "不支持的请求方法"), false)
+        }
+        _ => return (Reply::error(404, "not_found", "未知的路径"), false),
+    };
+    match result {
+        Ok(reply) => {
+            let modified = method == "POST";
+            (reply, modified)
+        }
+        Err(error) => (error.into(), false),
+    }
+}
+
+// GET /books 的筛选条件，年份不是数字时返回 400
+fn books_filter(query: &str) -> Result<BookFilter, Reply> {
+    let year = |key: &str| {
+        query_param(query, key)
+            .map(|year| year.trim().parse().map_err(|_| Reply::error(400, "invalid_query", &format!("无效的年份: {}", year))))
+            .transpose()
+    };
+    Ok(BookFilter {
+        author: query_param(query, "author"),
+        publisher: query_param(query, "publisher"),
+        year_from: year("year_from")?,
+        year_to: year("year_to")?,
+        edition: query_param(query, "edition"),
+        language: query_param(query, "language"),
+        subject: query_param(query, "subject"),
+        category: query_param(query, "category"),
+        classification: query_param(query, "class"),
+    })
+}
+
+// 查询参数中某个键的值
+fn query_param(query: &str, key: &str) -> Option<String> {
+    query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, value)| percent_decode(&value.replace('+', " ")))
+}
+
+// 解码 URL 中的 %XX 转义，非法的转义原样保留
+fn percent_decode(input: &str) -> String {
+    let bytes = input.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| input.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+// 内嵌的 HTTP 服务器。所有请求共享同一个图书馆，修改成功后写回存储
+pub struct Server {
+    http: tiny_http::Server,
+    store: SharedLibrary,
+    storage: Option<Mutex<Box<dyn Storage>>>,
+}
+
+impl Server {
+    // 在给定地址上监听，端口为 0 时由系统分配
+    pub fn bind(addr: impl ToSocketAddrs, store: SharedLibrary) -> LibraryResult<Self> {
+        let http = tiny_http::Server::http(addr).map_err(|e| LibraryError::IoError(e.to_string()))?;
+        Ok(Server { http, store, storage: None })
+    }
+
+    // 修改成功后写回的存储
+    pub fn with_storage(mut self, storage: Box<dyn Storage>) -> Self {
+        self.storage = Some(Mutex::new(storage));
+        self
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.http.server_addr().to_ip()
+    }
+
+    pub fn store(&self) -> &SharedLibrary {
+        &self.store
+    }
+
+    // 逐个处理请求，直到调用 shutdown
+    pub fn run(&self) {
+        for request in self.http.incoming_requests() {
+            self.handle(request);
+        }
+    }
+
+    // 让 run 返回
+    pub fn shutdown(&self) {
+        self.http.unblock();
+    }
+
+    fn handle(&self, mut request: Request) {
+        let mut body = String::new();
+        let reply = match request.as_reader().read_to_string(&mut body) {
+            Ok(_) => self.dispatch(request.method().as_str(), request.url(), &body),
+            Err(e) => Reply::error(400, "invalid_body", &e.to_string()),
+        };
+        let header = Header::from_bytes("Content-Type", "application/json; charset=utf-8").unwrap();
+        let response = Response::from_string(reply.body.to_string())
+            .with_status_code(reply.status)
+            .with_header(header);
+        if let Err(e) = request.respond(response) {
+            eprintln!("发送响应失败: {}", e);
+        }
+    }
+
+    // 处理一个请求，修改成功后写回存储，写回成功后才写入审计日志、通知订阅者。
+    // 任何一步失败都恢复到请求之前的状态，返回 500 的请求不会让内存中的图书馆领先于存储，
+    // 审计日志也不会记录被撤销的修改
+    fn dispatch(&self, method: &str, path: &str, body: &str) -> Reply {
+        let storage = match &self.storage {
+            Some(storage) if method != "GET" => storage,
+            _ => return route(&self.store, method, path, body).0,
+        };
+        // 修改请求在存储锁内依次处理，恢复快照时不会覆盖其他请求的修改
+        let mut storage = storage.lock().unwrap_or_else(|e| e.into_inner());
+        let snapshot = self.store.snapshot();
+        self.store.write(Library::defer_events);
+        let (reply, modified) = route(&self.store, method, path, body);
+        if !modified {
+            // 失败的请求可能已经让过期的预约出队，与存储保持一致，一并撤销
+            self.store.write(|library| *library = snapshot);
+            return reply;
+        }
+        let result = self.store.read(|library| storage.save(library)).and_then(|()| {
+            self.store.write(Library::publish_deferred).inspect_err(|_| {
+                // 审计日志写入失败，存储也改回请求之前的状态
+                let _ = storage.save(&snapshot);
+            })
+        });
+        match result {
+            Ok(()) => reply,
+            Err(error) => {
+                self.store.write(|library| *library = snapshot);
+                error.into()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+    use std::sync::Arc;
+    use std::thread::{self, JoinHandle};
+    use crate::persistence::{JsonFileStorage, StorageKind};
+    use crate::{store_contract, AuditLog, Event, MembershipTier};
+
+    // 在 127.0.0.1 的随机端口上启动服务器
+    fn start(library: Library) -> (Arc<Server>, JoinHandle<()>) {
+        let server = Arc::new(Server::bind("127.0.0.1:0", SharedLibrary::new(library)).unwrap());
+        let runner = server.clone();
+        (server, thread::spawn(move || runner.run()))
+    }
+
+    fn stop((server, handle): (Arc<Server>, JoinHandle<()>)) {
+        server.shutdown();
+        handle.join().unwrap();
+    }
+
+    // 发送一个请求，返回状态码和解析后的响应体
+    fn request(server: &Server, method: &str, path: &str, body: &str) -> (u16, Value) {
+        let mut stream = TcpStream::connect(server.local_addr().unwrap()).unwrap();
+        write!(
+            stream,
+            "{} {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+            method,
+            path,
+            body.len(),
+            body
+        )
+        .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+        (status, serde_json::from_str(body).unwrap())
+    }
+
+    fn library() -> Library {
+        let mut library = Library::new();
+        store_contract::register(&mut library, "Alice", MembershipTier::Standard);
+        store_contract::register(&mut library, "Bob", MembershipTier::Standard);
+        library
+    }
+
+    const HOBBIT: &str = r#"{"title": "The Hobbit", "author": "J.R.R. Tolkien", "isbn": "978-0-618-64015-7"}"#;
+
+    #[test]
+    fn test_books_routes() {
+        let running = start(library());
+        let server = &running.0;
+
+        let (status, book) = request(server, "POST", "/books", HOBBIT);
+        assert_eq!(status, 201);
+        assert_eq!(book["isbn"], "9780618640157");
+        let (status, error) = request(server, "POST", "/books", HOBBIT);
+        assert_eq!(status, 409);
+        assert_eq!(error["error"], "book_already_exists");
+        assert_eq!(error["message"], LibraryError::BookAlreadyExists.to_string());
+
+        let pride = r#"{"title": "Pride and Prejudice", "author": "Jane Austen", "isbn": "9780141439518", "category": "小说"}"#;
+        assert_eq!(request(server, "POST", "/books", pride).0, 201);
+        let (status, books) = request(server, "GET", "/books", "");
+        assert_eq!(status, 200);
+        assert_eq!(books.as_array().unwrap().len(), 2);
+        let (_, books) = request(server, "GET", "/books?author=Jane%20Austen", "");
+        assert_eq!(books[0]["category"], "小说");
+        assert_eq!(books.as_array().unwrap().len(), 1);
+
+        let lotr = r#"{"title": "指环王", "author": "托尔金", "isbn": "9787111213826",
+            "co_authors": ["丁棣"], "year": 2001, "language": "zh", "subjects": ["奇幻"], "classification": {"scheme": "Clc", "number": "I561.45"}}"#;
+        let (status, book) = request(server, "POST", "/books", lotr);
+        assert_eq!(status, 201);
+        assert_eq!(book["classification"]["number"], "I561.45");
+        let (_, books) = request(server, "GET", "/books?subject=%E5%A5%87%E5%B9%BB&year_from=2000", "");
+        assert_eq!(books.as_array().unwrap().len(), 1);
+        assert_eq!(books[0]["co_authors"][0], "丁棣");
+        assert_eq!(request(server, "GET", "/books?author=%E4%B8%81%E6%A3%A3", "").1.as_array().unwrap().len(), 1);
+        let (status, error) = request(server, "GET", "/books?year_to=soon", "");
+        assert_eq!((status, &error["error"]), (400, &Value::from("invalid_query")));
+        assert_eq!(request(server, "DELETE", "/books/9787111213826", "").0, 405);
+
+        // ISBN-10 也能找到同一本书
+        let (status, book) = request(server, "GET", "/books/0-618-64015-0", "");
+        assert_eq!(status, 200);
+        assert_eq!(book["title"], "The Hobbit");
+        assert_eq!(request(server, "GET", "/books/9787020002207", "").0, 404);
+
+        let invalid = r#"{"title": "Bad", "author": "Nobody", "isbn": "123"}"#;
+        let (status, error) = request(server, "POST", "/books", invalid);
+        assert_eq!((status, &error["error"]), (400, &Value::from("invalid_isbn")));
+        let (status, error) = request(server, "POST", "/books", r#"{"title": "Missing"}"#);
+        assert_eq!((status, &error["error"]), (400, &Value::from("invalid_json")));
+
+        assert_eq!(request(server, "DELETE", "/books", "").0, 405);
+        assert_eq!(request(server, "GET", "/nowhere", "").0, 404);
+        stop(running);
+    }
+
+    #[test]
+    fn test_borrow_return_and_loans() {
+        let running = start(library());
+        let server = &running.0;
+        request(server, "POST", "/books", HOBBIT);
+
+        let (status, loan) = request(server, "POST", "/books/9780618640157/borrow", r#"{"patron": "Alice"}"#);
+        assert_eq!(status, 201);
+        assert_eq!(loan["borrower"], "Alice");
+        let (status, error) = request(server, "POST", "/books/9780618640157/borrow", r#"{"patron": "Bob"}"#);
+        assert_eq!((status, &error["error"]), (409, &Value::from("book_already_borrowed")));
+        let (status, error) = request(server, "POST", "/books/9780618640157/borrow", r#"{"patron": "Nobody"}"#);
+        assert_eq!((status, &error["error"]), (404, &Value::from("patron_not_found")));
+
+        let (status, loans) = request(server, "GET", "/patrons/Alice/loans", "");
+        assert_eq!(status, 200);
+        assert_eq!(loans.as_array().unwrap().len(), 1);
+        assert_eq!(loans[0]["isbn"], "9780618640157");
+        assert_eq!(request(server, "GET", "/patrons/Nobody/loans", "").0, 404);
+
+        let (status, receipt) = request(server, "POST", "/books/9780618640157/return", r#"{"patron": "Alice"}"#);
+        assert_eq!(status, 200);
+        assert_eq!(receipt["fine"], 0);
+        let (status, error) = request(server, "POST", "/books/9780618640157/return", r#"{"patron": "Alice"}"#);
+        assert_eq!((status, &error["error"]), (409, &Value::from("book_not_borrowed")));
+        stop(running);
+    }
+
+    #[test]
+    fn test_mutations_are_saved_to_storage() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("library.json");
+        let server = Server::bind("127.0.0.1:0", SharedLibrary::new(library())).unwrap();
+        let server = Arc::new(server.with_storage(StorageKind::Json.open(&path)));
+        let runner = server.clone();
+        let running = (server, thread::spawn(move || runner.run()));
+
+        request(&running.0, "POST", "/books", HOBBIT);
+        request(&running.0, "POST", "/books/9780618640157/borrow", r#"{"patron": "Bob"}"#);
+        let saved = JsonFileStorage::new(&path).load().unwrap();
+        assert!(saved.find_book_by_isbn("9780618640157").unwrap().is_borrowed_by("Bob"));
+        // 失败的请求不会写入
+        let before = std::fs::read(&path).unwrap();
+        assert_eq!(request(&running.0, "POST", "/books", HOBBIT).0, 409);
+        assert_eq!(std::fs::read(&path).unwrap(), before);
+        stop(running);
+    }
+
+    #[test]
+    fn test_failed_save_rolls_back_mutation() {
+        let mut audited = library();
+        let log = Arc::new(Mutex::new(AuditLog::in_memory()));
+        audited.set_audit_log(log.clone());
+        let notified = Arc::new(Mutex::new(0));
+        let counter = notified.clone();
+        audited.subscribe(Arc::new(move |_: &Library, _: &Event| *counter.lock().unwrap() += 1));
+        let server = Server::bind("127.0.0.1:0", SharedLibrary::new(audited)).unwrap();
+        let server = Arc::new(server.with_storage(Box::new(store_contract::FailingStorage("library.json".into()))));
+        let runner = server.clone();
+        let running = (server, thread::spawn(move || runner.run()));
+
+        let (status, error) = request(&running.0, "POST", "/books", HOBBIT);
+        assert_eq!((status, &error["error"]), (500, &Value::from("io_error")));
+        // 没有写入存储的修改也不会留在内存中，审计日志和订阅者都没有收到它
+        assert_eq!(request(&running.0, "GET", "/books", "").1, json!([]));
+        assert_eq!(running.0.store().snapshot(), library());
+        assert!(log.lock().unwrap().records().is_empty());
+        assert_eq!(*notified.lock().unwrap(), 0);
+        stop(running);
+    }
+
+    #[test]
+    fn test_events_published_after_save() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("library.json");
+        let mut audited = library();
+        let log = Arc::new(Mutex::new(AuditLog::in_memory()));
+        audited.set_audit_log(log.clone());
+        let server = Server::bind("127.0.0.1:0", SharedLibrary::new(audited)).unwrap();
+        let server = Arc::new(server.with_storage(StorageKind::Json.open(&path)));
+        let runner = server.clone();
+        let running = (server, thread::spawn(move || runner.run()));
+
+        assert_eq!(request(&running.0, "POST", "/books", HOBBIT).0, 201);
+        assert_eq!(request(&running.0, "POST", "/books", HOBBIT).0, 409);
+        assert_eq!(log.lock().unwrap().records().len(), 1);
+        assert_eq!(JsonFileStorage::new(&path).load().unwrap().all_books().unwrap().len(), 1);
+        // 请求之外直接修改图书馆时仍然立即写入审计日志
+        running.0.store().write(|library| library.remove_book("9780618640157")).unwrap();
+        assert_eq!(log.lock().unwrap().records().len(), 2);
+        stop(running);
+    }
+
+    #[test]
+    fn test_error_status_mapping() {
+        assert_eq!(status_for(&LibraryError::UnpaidFines { balance: 2100, threshold: 1000 }), 403);
+        assert_eq!(status_for(&LibraryError::LoanLimitReached { limit: 3 }), 403);
+        assert_eq!(status_for(&LibraryError::HoldNotFound), 404);
+        assert_eq!(status_for(&LibraryError::DatabaseError("locked".to_string())), 500);
+        let reply = Reply::from(LibraryError::BookHasHolds { count: 2 });
+        assert_eq!(reply.status, 409);
+        assert_eq!(reply.body["error"], "book_has_holds");
+        assert_eq!(percent_decode("%E5%B0%8F%E8%AF%B4%2"), "小说%2");
+    }
+}
@@ -0,0 +1,214 @@
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use crate::{Book, BookStore, BookUpdate, Library, LibraryResult, Loan, Patron, ReturnReceipt};
+
+// 可以在多个线程之间共享的图书馆句柄，克隆得到的句柄指向同一个图书馆。
+// 所有修改都在写锁内完成，因此对同一 ISBN 的并发借阅不会同时成功；
+// 读操作在读锁内完成，看到的总是某次修改之前或之后的完整状态
+#[derive(Debug, Clone, Default)]
+pub struct SharedLibrary {
+    inner: Arc<RwLock<Library>>,
+}
+
+impl SharedLibrary {
+    pub fn new(library: Library) -> Self {
+        SharedLibrary { inner: Arc::new(RwLock::new(library)) }
+    }
+
+    // 在读锁内访问图书馆，适合需要同时读取多项数据的场景，例如列出书籍和借阅记录
+    pub fn read<T>(&self, f: impl FnOnce(&Library) -> T) -> T {
+        f(&self.read_lock())
+    }
+
+    // 在写锁内修改图书馆，闭包中的多步操作对其他线程来说是原子的，例如：
+    // shared.write(|library| library.renew_book(isbn, borrower))
+    pub fn write<T>(&self, f: impl FnOnce(&mut Library) -> T) -> T {
+        f(&mut self.write_lock())
+    }
+
+    // 当前状态的完整副本，之后的修改不会影响它，适合耗时较长的列表和报表
+    pub fn snapshot(&self) -> Library {
+        self.read_lock().clone()
+    }
+
+    // 某个线程在持有锁时 panic 会使锁中毒。图书馆的操作先完成所有检查再修改，
+    // 审计日志写入失败时会撤销修改，出错时不会留下修改了一半的状态，
+    // 所以这里继续使用锁中的数据，而不是让所有终端都停止服务
+    fn read_lock(&self) -> RwLockReadGuard<'_, Library> {
+        self.inner.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write_lock(&self) -> RwLockWriteGuard<'_, Library> {
+        self.inner.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl BookStore for SharedLibrary {
+    fn add_book(&mut self, book: Book) -> LibraryResult<()> {
+        self.write(|library| library.add_book(book))
+    }
+
+    fn remove_book(&mut self, isbn: &str) -> LibraryResult<Book> {
+        self.write(|library| library.remove_book(isbn))
+    }
+
+    fn update_book(&mut self, isbn: &str, update: BookUpdate) -> LibraryResult<()> {
+        self.write(|library| library.update_book(isbn, update))
+    }
+
+    fn get_book(&self, isbn: &str) -> LibraryResult<Book> {
+        self.read(|library| BookStore::get_book(library, isbn))
+    }
+
+    fn get_books_by_author(&self, author: &str) -> LibraryResult<Vec<Book>> {
+        self.read(|library| library.get_books_by_author(author))
+    }
+
+    fn all_books(&self) -> LibraryResult<Vec<Book>> {
+        self.read(<Library as BookStore>::all_books)
+    }
+
+    fn register_patron(&mut self, patron: Patron) -> LibraryResult<()> {
+        self.write(|library| library.register_patron(patron))
+    }
+
+    fn borrow_book(&mut self, isbn: &str, borrower: &str) -> LibraryResult<Loan> {
+        self.write(|library| library.borrow_book(isbn, borrower))
+    }
+
+    fn return_book(&mut self, isbn: &str, borrower: &str) -> LibraryResult<ReturnReceipt> {
+        self.write(|library| library.return_book(isbn, borrower))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Barrier;
+    use std::thread;
+    use crate::{store_contract, LibraryError, MembershipTier, PatronStatus, TierLimits};
+
+    const ISBNS: [&str; 4] = ["9780618640157", "9780141439518", "9781617294556", "9787111213826"];
+
+    // 每本书一个副本，读者 P0..P{patrons} 不限借阅册数
+    fn shared_library(patrons: usize) -> SharedLibrary {
+        let mut library = Library::new();
+        library.set_tier_limits(MembershipTier::Standard, TierLimits { max_loans: usize::MAX, loan_period_days: None });
+        for isbn in ISBNS {
+            library.add_book(Book::new(isbn.to_string(), "Author".to_string(), isbn.to_string())).unwrap();
+        }
+        for i in 0..patrons {
+            store_contract::register(&mut library, &format!("P{}", i), MembershipTier::Standard);
+        }
+        SharedLibrary::new(library)
+    }
+
+    // 借出的副本与未归还的借阅记录一一对应
+    fn assert_consistent(library: &Library) {
+        let borrowed: usize = BookStore::all_books(library).unwrap().iter().map(|book| book.borrowers().len()).sum();
+        assert_eq!(borrowed, library.active_loans().len());
+        for loan in library.active_loans() {
+            assert!(library.find_book_by_isbn(&loan.isbn).unwrap().is_borrowed_by(&loan.borrower));
+        }
+    }
+
+    #[test]
+    fn test_shared_library_passes_store_contract() {
+        store_contract::run(|clock| SharedLibrary::new(Library::with_clock(clock)));
+    }
+
+    #[test]
+    fn test_concurrent_borrows_of_one_copy_only_one_succeeds() {
+        const THREADS: usize = 32;
+        for _ in 0..20 {
+            let shared = shared_library(THREADS);
+            let barrier = Arc::new(Barrier::new(THREADS));
+            let handles: Vec<_> = (0..THREADS)
+                .map(|i| {
+                    let mut shared = shared.clone();
+                    let barrier = barrier.clone();
+                    thread::spawn(move || {
+                        barrier.wait();
+                        shared.borrow_book(ISBNS[0], &format!("P{}", i))
+                    })
+                })
+                .collect();
+            let results: Vec<_> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+            assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+            assert!(results
+                .iter()
+                .filter_map(|result| result.as_ref().err())
+                .all(|error| *error == LibraryError::BookAlreadyBorrowed));
+            assert_eq!(shared.read(|library| library.active_loans().len()), 1);
+        }
+    }
+
+    #[test]
+    fn test_stress_borrow_return_with_snapshot_readers() {
+        const WRITERS: usize = 8;
+        const READERS: usize = 4;
+        const ROUNDS: usize = 200;
+        let shared = shared_library(WRITERS);
+        let mut handles = Vec::new();
+        for i in 0..WRITERS {
+            let mut shared = shared.clone();
+            handles.push(thread::spawn(move || {
+                let patron = format!("P{}", i);
+                let mut borrowed = 0;
+                for round in 0..ROUNDS {
+                    let isbn = ISBNS[(i + round) % ISBNS.len()];
+                    if shared.borrow_book(isbn, &patron).is_ok() {
+                        borrowed += 1;
+                        thread::yield_now();
+                        shared.return_book(isbn, &patron).unwrap();
+                    }
+                }
+                borrowed
+            }));
+        }
+        let readers: Vec<_> = (0..READERS)
+            .map(|_| {
+                let shared = shared.clone();
+                thread::spawn(move || {
+                    for _ in 0..ROUNDS {
+                        assert_consistent(&shared.snapshot());
+                        shared.read(assert_consistent);
+                    }
+                })
+            })
+            .collect();
+
+        let borrowed: usize = handles.into_iter().map(|handle| handle.join().unwrap()).sum();
+        for reader in readers {
+            reader.join().unwrap();
+        }
+        let library = shared.snapshot();
+        assert_consistent(&library);
+        assert!(library.active_loans().is_empty());
+        assert_eq!(library.loans().len(), borrowed);
+    }
+
+    #[test]
+    fn test_snapshot_is_isolated_from_later_changes() {
+        let mut shared = shared_library(1);
+        let before = shared.snapshot();
+        shared.borrow_book(ISBNS[0], "P0").unwrap();
+        assert!(before.active_loans().is_empty());
+        assert_eq!(shared.snapshot().active_loans().len(), 1);
+    }
+
+    #[test]
+    fn test_panicking_writer_does_not_block_others() {
+        let mut shared = shared_library(1);
+        let other = shared.clone();
+        let result = thread::spawn(move || {
+            other.write(|library| {
+                library.set_patron_status("P0", PatronStatus::Suspended).unwrap();
+                panic!("终端崩溃");
+            })
+        })
+        .join();
+        assert!(result.is_err());
+        assert_eq!(shared.borrow_book(ISBNS[0], "P0"), Err(LibraryError::PatronSuspended));
+        assert_eq!(shared.get_book(ISBNS[0]).unwrap().isbn, ISBNS[0]);
+    }
+}
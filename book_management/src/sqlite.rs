@@ -0,0 +1,631 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+use std::sync::Arc;
+use chrono::{Duration, NaiveDate, NaiveDateTime};
+use rusqlite::{params, Connection, OptionalExtension, Transaction, TransactionBehavior};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use crate::loan::SharedClock;
+use crate::{
+    isbn, patron, Book, BookCopy, BookStore, BookUpdate, Clock, FinePolicy, Isbn, LibraryError, LibraryResult,
+    Loan, LoanPolicy, MembershipTier, Money, Patron, PatronStatus, ReturnReceipt, TierLimits,
+};
+
+// 数据库结构的迁移脚本，第 n 个脚本把版本从 n 升到 n + 1。
+// 当前版本记录在 PRAGMA user_version 中，已有的脚本不能再修改，只能追加
+const MIGRATIONS: &[&str] = &[
+    // 1：书目、副本、读者和借阅记录
+    "CREATE TABLE books (
+        isbn TEXT PRIMARY KEY,
+        title TEXT NOT NULL,
+        author TEXT NOT NULL,
+        category TEXT
+    );
+    CREATE TABLE copies (
+        isbn TEXT NOT NULL REFERENCES books(isbn) ON UPDATE CASCADE,
+        copy_id INTEGER NOT NULL,
+        shelf_location TEXT NOT NULL DEFAULT '',
+        condition TEXT NOT NULL,
+        borrowed_by TEXT,
+        PRIMARY KEY (isbn, copy_id)
+    );
+    CREATE TABLE patrons (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        contact TEXT NOT NULL,
+        tier TEXT NOT NULL,
+        expires TEXT NOT NULL,
+        status TEXT NOT NULL
+    );
+    CREATE TABLE loans (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        isbn TEXT NOT NULL,
+        copy_id INTEGER NOT NULL,
+        borrower TEXT NOT NULL,
+        checked_out TEXT NOT NULL,
+        due TEXT NOT NULL,
+        renewals INTEGER NOT NULL DEFAULT 0,
+        returned TEXT
+    );",
+    // 2：按作者、标题和借阅者查询的索引
+    "CREATE INDEX books_author ON books(author);
+    CREATE INDEX books_title ON books(title COLLATE NOCASE);
+    CREATE INDEX loans_borrower ON loans(borrower, returned);
+    CREATE INDEX loans_isbn ON loans(isbn, copy_id);",
+    // 3：罚款账目，amount 为正表示欠款增加
+    "CREATE TABLE ledger (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        borrower TEXT NOT NULL,
+        amount INTEGER NOT NULL,
+        loan_id INTEGER,
+        at TEXT NOT NULL
+    );
+    CREATE INDEX ledger_borrower ON ledger(borrower);",
+    // 4：书目的扩展信息，以 JSON 保存
+    "ALTER TABLE books ADD COLUMN metadata TEXT NOT NULL DEFAULT '{}';",
+    // 5：合著者单独成表，按作者查询时可以走索引
+    "CREATE TABLE co_authors (
+        isbn TEXT NOT NULL REFERENCES books(isbn) ON UPDATE CASCADE,
+        name TEXT NOT NULL,
+        PRIMARY KEY (isbn, name)
+    );
+    CREATE INDEX co_authors_name ON co_authors(name);
+    INSERT OR IGNORE INTO co_authors (isbn, name)
+        SELECT books.isbn, value FROM books, json_each(books.metadata, '$.co_authors');",
+];
+
+// 数据库结构的当前版本
+pub const SCHEMA_VERSION: usize = MIGRATIONS.len();
+
+// 基于 SQLite 的图书存储，书目不需要整体加载到内存。
+// 借阅和归还在事务中完成，中途失败不会留下借出一半的状态。
+// 借阅规则与 Library 相同，但不支持预约
+pub struct SqliteStore {
+    conn: Connection,
+    clock: SharedClock,
+    loan_policy: LoanPolicy,
+    fine_policy: FinePolicy,
+    tier_limits: BTreeMap<MembershipTier, TierLimits>,
+}
+
+impl SqliteStore {
+    // 打开（或创建）数据库文件，并升级到最新的结构
+    pub fn open(path: impl AsRef<Path>) -> LibraryResult<Self> {
+        Self::with_connection(Connection::open(path)?)
+    }
+
+    // 内存数据库，主要用于测试
+    pub fn open_in_memory() -> LibraryResult<Self> {
+        Self::with_connection(Connection::open_in_memory()?)
+    }
+
+    fn with_connection(mut conn: Connection) -> LibraryResult<Self> {
+        conn.pragma_update(None, "foreign_keys", true)?;
+        migrate(&mut conn)?;
+        Ok(SqliteStore {
+            conn,
+            clock: SharedClock::default(),
+            loan_policy: LoanPolicy::default(),
+            fine_policy: FinePolicy::default(),
+            tier_limits: patron::default_tier_limits(),
+        })
+    }
+
+    // 使用指定时钟，便于测试逾期等与时间有关的逻辑
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = SharedClock::new(clock);
+        self
+    }
+
+    pub fn set_loan_policy(&mut self, policy: LoanPolicy) {
+        self.loan_policy = policy;
+    }
+
+    pub fn set_fine_policy(&mut self, policy: FinePolicy) {
+        self.fine_policy = policy;
+    }
+
+    pub fn set_tier_limits(&mut self, tier: MembershipTier, limits: TierLimits) {
+        self.tier_limits.insert(tier, limits);
+    }
+
+    // 数据库结构的版本
+    pub fn schema_version(&self) -> LibraryResult<usize> {
+        schema_version(&self.conn)
+    }
+
+    // 按标题查找书籍，忽略大小写
+    pub fn find_books_by_title(&self, title: &str) -> LibraryResult<Vec<Book>> {
+        load_books(&self.conn, BOOKS_BY_TITLE, params![title])
+    }
+
+    pub fn get_patron(&self, id: &str) -> LibraryResult<Patron> {
+        get_patron(&self.conn, id)
+    }
+
+    // 某位借阅者的全部借阅记录
+    pub fn loans_for(&self, borrower: &str) -> LibraryResult<Vec<Loan>> {
+        let mut statement = self.conn.prepare(
+            "SELECT id, isbn, copy_id, borrower, checked_out, due, renewals, returned
+             FROM loans WHERE borrower = ?1 ORDER BY id",
+        )?;
+        let loans = statement.query_map(params![borrower], loan_from_row)?.collect::<Result<_, _>>()?;
+        Ok(loans)
+    }
+
+    // 借阅者当前的欠款
+    pub fn balance(&self, borrower: &str) -> LibraryResult<Money> {
+        balance(&self.conn, borrower)
+    }
+
+    // 写事务：立即获取写锁，避免两个进程同时借出同一副本
+    fn write_transaction(&mut self) -> LibraryResult<Transaction<'_>> {
+        Ok(self.conn.transaction_with_behavior(TransactionBehavior::Immediate)?)
+    }
+}
+
+fn schema_version(conn: &Connection) -> LibraryResult<usize> {
+    let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    Ok(version as usize)
+}
+
+// 依次执行尚未执行的迁移脚本，每个脚本在单独的事务中执行
+fn migrate(conn: &mut Connection) -> LibraryResult<()> {
+    let current = schema_version(conn)?;
+    if current > SCHEMA_VERSION {
+        return Err(LibraryError::DatabaseError(format!(
+            "数据库版本 {} 高于程序支持的版本 {}",
+            current, SCHEMA_VERSION
+        )));
+    }
+    for (version, script) in MIGRATIONS.iter().enumerate().skip(current) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(script)?;
+        tx.pragma_update(None, "user_version", (version + 1) as i64)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+// 枚举按 serde 的名称保存为文本，例如 Condition::Good 保存为 "Good"
+fn enum_to_text<T: Serialize>(value: &T) -> LibraryResult<String> {
+    match serde_json::to_value(value)? {
+        Value::String(text) => Ok(text),
+        other => Ok(other.to_string()),
+    }
+}
+
+fn enum_from_text<T: DeserializeOwned>(text: String) -> LibraryResult<T> {
+    Ok(serde_json::from_value(Value::String(text))?)
+}
+
+// load_books 的查询条件，测试中检查由它们生成的语句都走索引
+const BOOK_BY_ISBN: &str = "WHERE isbn = ?1";
+const BOOKS_BY_TITLE: &str = "WHERE title = ?1 COLLATE NOCASE";
+// 主要作者和合著者分别走各自的索引，再合并结果
+const BOOKS_BY_AUTHOR: &str =
+    "WHERE isbn IN (SELECT isbn FROM books WHERE author = ?1 UNION SELECT isbn FROM co_authors WHERE name = ?1)";
+
+fn books_sql(filter: &str) -> String {
+    format!("SELECT isbn, title, author, category, metadata FROM books {} ORDER BY isbn", filter)
+}
+
+fn copies_sql(filter: &str) -> String {
+    format!(
+        "SELECT isbn, copy_id, shelf_location, condition, borrowed_by FROM copies
+         WHERE isbn IN (SELECT isbn FROM books {}) ORDER BY isbn, copy_id",
+        filter
+    )
+}
+
+// 按条件加载书籍及其副本，结果按 ISBN 排序
+fn load_books(conn: &Connection, filter: &str, params: impl rusqlite::Params + Copy) -> LibraryResult<Vec<Book>> {
+    let mut statement = conn.prepare(&books_sql(filter))?;
+    let rows: Vec<(Book, String)> = statement
+        .query_map(params, |row| {
+            let book = Book {
+                isbn: row.get(0)?,
+                title: row.get(1)?,
+                author: row.get(2)?,
+                category: row.get(3)?,
+                metadata: Default::default(),
+                copies: Vec::new(),
+            };
+            Ok((book, row.get(4)?))
+        })?
+        .collect::<Result<_, _>>()?;
+    let mut books = Vec::with_capacity(rows.len());
+    for (mut book, metadata) in rows {
+        book.metadata = serde_json::from_str(&metadata)?;
+        books.push(book);
+    }
+    if books.is_empty() {
+        return Ok(books);
+    }
+
+    let mut statement = conn.prepare(&copies_sql(filter))?;
+    let rows: Vec<(String, u32, String, String, Option<String>)> = statement
+        .query_map(params, |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?)))?
+        .collect::<Result<_, _>>()?;
+    let positions: HashMap<String, usize> =
+        books.iter().enumerate().map(|(i, book)| (book.isbn.clone(), i)).collect();
+    for (isbn, copy_id, shelf_location, condition, borrowed_by) in rows {
+        let mut copy = BookCopy::new(copy_id, shelf_location, enum_from_text(condition)?);
+        copy.borrowed_by = borrowed_by;
+        if let Some(&i) = positions.get(&isbn) {
+            books[i].copies.push(copy);
+        }
+    }
+    Ok(books)
+}
+
+// 把书目的合著者写入 co_authors 表，先清除原有的记录
+fn save_co_authors(conn: &Connection, isbn: &str, co_authors: &[String]) -> LibraryResult<()> {
+    conn.execute("DELETE FROM co_authors WHERE isbn = ?1", params![isbn])?;
+    for name in co_authors {
+        conn.execute("INSERT OR IGNORE INTO co_authors (isbn, name) VALUES (?1, ?2)", params![isbn, name])?;
+    }
+    Ok(())
+}
+
+fn get_book(conn: &Connection, isbn: &str) -> LibraryResult<Book> {
+    load_books(conn, BOOK_BY_ISBN, params![isbn])?.pop().ok_or(LibraryError::BookNotFound)
+}
+
+fn get_patron(conn: &Connection, id: &str) -> LibraryResult<Patron> {
+    let row: Option<(String, String, String, String, NaiveDate, String)> = conn
+        .query_row(
+            "SELECT id, name, contact, tier, expires, status FROM patrons WHERE id = ?1",
+            params![id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?)),
+        )
+        .optional()?;
+    let (id, name, contact, tier, expires, status) = row.ok_or(LibraryError::PatronNotFound)?;
+    let mut patron = Patron::new(id, name, contact, enum_from_text(tier)?, expires);
+    patron.status = enum_from_text(status)?;
+    Ok(patron)
+}
+
+fn balance(conn: &Connection, borrower: &str) -> LibraryResult<Money> {
+    let balance: i64 = conn.query_row(
+        "SELECT COALESCE(SUM(amount), 0) FROM ledger WHERE borrower = ?1",
+        params![borrower],
+        |row| row.get(0),
+    )?;
+    Ok(balance.max(0) as Money)
+}
+
+fn loan_from_row(row: &rusqlite::Row) -> rusqlite::Result<Loan> {
+    Ok(Loan {
+        id: row.get(0)?,
+        isbn: row.get(1)?,
+        copy_id: row.get(2)?,
+        borrower: row.get(3)?,
+        checked_out: row.get(4)?,
+        due: row.get(5)?,
+        renewals: row.get(6)?,
+        returned: row.get(7)?,
+    })
+}
+
+impl BookStore for SqliteStore {
+    fn add_book(&mut self, mut book: Book) -> LibraryResult<()> {
+        book.isbn = Isbn::parse(&book.isbn)?.as_str().to_string();
+        let tx = self.write_transaction()?;
+        let exists: bool =
+            tx.query_row("SELECT EXISTS(SELECT 1 FROM books WHERE isbn = ?1)", params![book.isbn], |row| row.get(0))?;
+        if exists {
+            return Err(LibraryError::BookAlreadyExists);
+        }
+        tx.execute(
+            "INSERT INTO books (isbn, title, author, category, metadata) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![book.isbn, book.title, book.author, book.category, serde_json::to_string(&book.metadata)?],
+        )?;
+        save_co_authors(&tx, &book.isbn, &book.metadata.co_authors)?;
+        for copy in &book.copies {
+            tx.execute(
+                "INSERT INTO copies (isbn, copy_id, shelf_location, condition, borrowed_by)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![book.isbn, copy.copy_id, copy.shelf_location, enum_to_text(&copy.condition)?, copy.borrowed_by],
+            )?;
+        }
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn remove_book(&mut self, isbn: &str) -> LibraryResult<Book> {
+        let isbn = &isbn::normalize(isbn);
+        let tx = self.write_transaction()?;
+        let book = get_book(&tx, isbn)?;
+        if !book.borrowers().is_empty() {
+            return Err(LibraryError::BookAlreadyBorrowed);
+        }
+        tx.execute("DELETE FROM copies WHERE isbn = ?1", params![isbn])?;
+        tx.execute("DELETE FROM co_authors WHERE isbn = ?1", params![isbn])?;
+        tx.execute("DELETE FROM books WHERE isbn = ?1", params![isbn])?;
+        tx.commit()?;
+        Ok(book)
+    }
+
+    fn update_book(&mut self, isbn: &str, update: BookUpdate) -> LibraryResult<()> {
+        let isbn = &isbn::normalize(isbn);
+        let tx = self.write_transaction()?;
+        let book = get_book(&tx, isbn)?;
+        let new_isbn = match &update.isbn {
+            Some(new_isbn) => Isbn::parse(new_isbn)?.as_str().to_string(),
+            None => isbn.clone(),
+        };
+        if new_isbn != *isbn && get_book(&tx, &new_isbn).is_ok() {
+            return Err(LibraryError::BookAlreadyExists);
+        }
+        let metadata = update.metadata.unwrap_or(book.metadata);
+        tx.execute(
+            "UPDATE books SET isbn = ?1, title = ?2, author = ?3, category = ?4, metadata = ?5 WHERE isbn = ?6",
+            params![
+                new_isbn,
+                update.title.unwrap_or(book.title),
+                update.author.unwrap_or(book.author),
+                update.category.unwrap_or(book.category),
+                serde_json::to_string(&metadata)?,
+                isbn
+            ],
+        )?;
+        // 副本和合著者通过外键级联更新，借阅记录需要单独迁移
+        tx.execute("UPDATE loans SET isbn = ?1 WHERE isbn = ?2", params![new_isbn, isbn])?;
+        save_co_authors(&tx, &new_isbn, &metadata.co_authors)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn get_book(&self, isbn: &str) -> LibraryResult<Book> {
+        get_book(&self.conn, &isbn::normalize(isbn))
+    }
+
+    fn get_books_by_author(&self, author: &str) -> LibraryResult<Vec<Book>> {
+        load_books(&self.conn, BOOKS_BY_AUTHOR, params![author])
+    }
+
+    fn all_books(&self) -> LibraryResult<Vec<Book>> {
+        load_books(&self.conn, "", [])
+    }
+
+    fn register_patron(&mut self, patron: Patron) -> LibraryResult<()> {
+        let inserted = self.conn.execute(
+            "INSERT OR IGNORE INTO patrons (id, name, contact, tier, expires, status) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            params![
+                patron.id,
+                patron.name,
+                patron.contact,
+                enum_to_text(&patron.tier)?,
+                patron.expires,
+                enum_to_text(&patron.status)?
+            ],
+        )?;
+        if inserted == 0 {
+            return Err(LibraryError::PatronAlreadyExists);
+        }
+        Ok(())
+    }
+
+    fn borrow_book(&mut self, isbn: &str, borrower: &str) -> LibraryResult<Loan> {
+        let isbn = &isbn::normalize(isbn);
+        let now = self.clock.now();
+        let threshold = self.fine_policy.block_threshold;
+        let default_period = self.loan_policy.loan_period_days;
+        // 事务借用了整个存储，先取出需要的规则
+        let tier_limits = self.tier_limits.clone();
+        let tx = self.write_transaction()?;
+        let patron = get_patron(&tx, borrower)?;
+        let limits = tier_limits.get(&patron.tier);
+        let period = limits.and_then(|limits| limits.loan_period_days).unwrap_or(default_period);
+        let due = now.date() + Duration::days(period);
+        let max_loans = limits.map(|limits| limits.max_loans);
+        if patron.status == PatronStatus::Suspended {
+            return Err(LibraryError::PatronSuspended);
+        }
+        if patron.is_expired(now.date()) {
+            return Err(LibraryError::MembershipExpired);
+        }
+        if let Some(limit) = max_loans {
+            let on_loan: usize = tx.query_row(
+                "SELECT COUNT(*) FROM loans WHERE borrower = ?1 AND returned IS NULL",
+                params![borrower],
+                |row| row.get(0),
+            )?;
+            if on_loan >= limit {
+                return Err(LibraryError::LoanLimitReached { limit });
+            }
+        }
+        // 欠款超过上限的借阅者不能继续借阅
+        let balance = balance(&tx, borrower)?;
+        if balance > threshold {
+            return Err(LibraryError::UnpaidFines { balance, threshold });
+        }
+        get_book(&tx, isbn)?;
+        let copy_id: u32 = tx
+            .query_row(
+                "SELECT copy_id FROM copies WHERE isbn = ?1 AND borrowed_by IS NULL ORDER BY copy_id LIMIT 1",
+                params![isbn],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or(LibraryError::BookAlreadyBorrowed)?;
+        tx.execute(
+            "UPDATE copies SET borrowed_by = ?1 WHERE isbn = ?2 AND copy_id = ?3",
+            params![borrower, isbn, copy_id],
+        )?;
+        tx.execute(
+            "INSERT INTO loans (isbn, copy_id, borrower, checked_out, due) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![isbn, copy_id, borrower, now, due],
+        )?;
+        let loan = Loan {
+            id: tx.last_insert_rowid() as u64,
+            isbn: isbn.to_string(),
+            copy_id,
+            borrower: borrower.to_string(),
+            checked_out: now,
+            due,
+            renewals: 0,
+            returned: None,
+        };
+        tx.commit()?;
+        Ok(loan)
+    }
+
+    fn return_book(&mut self, isbn: &str, borrower: &str) -> LibraryResult<ReturnReceipt> {
+        let isbn = &isbn::normalize(isbn);
+        let now: NaiveDateTime = self.clock.now();
+        let fine_policy = self.fine_policy.clone();
+
+        let tx = self.write_transaction()?;
+        let book = get_book(&tx, isbn)?;
+        let copy = book
+            .copies
+            .iter()
+            .find(|copy| copy.borrowed_by.as_deref() == Some(borrower))
+            .ok_or(LibraryError::BookNotBorrowed)?;
+        tx.execute(
+            "UPDATE copies SET borrowed_by = NULL WHERE isbn = ?1 AND copy_id = ?2",
+            params![isbn, copy.copy_id],
+        )?;
+        let mut receipt = ReturnReceipt { copy_id: copy.copy_id, loan_id: None, fine: 0, hold_for: None };
+        let loan = tx
+            .query_row(
+                "SELECT id, isbn, copy_id, borrower, checked_out, due, renewals, returned
+                 FROM loans WHERE isbn = ?1 AND copy_id = ?2 AND returned IS NULL",
+                params![isbn, copy.copy_id],
+                loan_from_row,
+            )
+            .optional()?;
+        if let Some(loan) = loan {
+            tx.execute("UPDATE loans SET returned = ?1 WHERE id = ?2", params![now, loan.id])?;
+            receipt.loan_id = Some(loan.id);
+            receipt.fine = fine_policy.calculate(loan.days_overdue(now.date()), book.category.as_deref());
+            if receipt.fine > 0 {
+                tx.execute(
+                    "INSERT INTO ledger (borrower, amount, loan_id, at) VALUES (?1, ?2, ?3, ?4)",
+                    params![borrower, receipt.fine as i64, loan.id, now],
+                )?;
+            }
+        }
+        tx.commit()?;
+        Ok(receipt)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::store_contract;
+
+    #[test]
+    fn test_sqlite_store_passes_contract() {
+        store_contract::run(|clock| SqliteStore::open_in_memory().unwrap().with_clock(clock));
+    }
+
+    #[test]
+    fn test_migrations_upgrade_existing_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("library.db");
+        // 模拟只执行过第一个迁移脚本的旧数据库
+        {
+            let conn = Connection::open(&path).unwrap();
+            conn.execute_batch(MIGRATIONS[0]).unwrap();
+            conn.pragma_update(None, "user_version", 1).unwrap();
+            conn.execute(
+                "INSERT INTO books (isbn, title, author) VALUES ('9780618640157', 'The Hobbit', 'J.R.R. Tolkien')",
+                [],
+            )
+            .unwrap();
+        }
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.schema_version().unwrap(), SCHEMA_VERSION);
+        assert_eq!(store.get_book("9780618640157").unwrap().title, "The Hobbit");
+        drop(store);
+
+        // 再次打开时不会重复执行迁移
+        let store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.schema_version().unwrap(), SCHEMA_VERSION);
+        assert_eq!(store.find_books_by_title("the hobbit").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_co_author_lookup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("library.db");
+        // 合著者单独成表之前的数据库，合著者只在扩展信息中
+        {
+            let conn = Connection::open(&path).unwrap();
+            for script in &MIGRATIONS[..4] {
+                conn.execute_batch(script).unwrap();
+            }
+            conn.pragma_update(None, "user_version", 4).unwrap();
+            conn.execute(
+                "INSERT INTO books (isbn, title, author, metadata)
+                 VALUES ('9780618640157', 'The Hobbit', 'J.R.R. Tolkien', '{\"co_authors\": [\"Alan Lee\"]}')",
+                [],
+            )
+            .unwrap();
+        }
+        let mut store = SqliteStore::open(&path).unwrap();
+        assert_eq!(store.get_books_by_author("Alan Lee").unwrap().len(), 1);
+
+        // 修改合著者和 ISBN 后按新的合著者查到
+        let mut metadata = store.get_book("9780618640157").unwrap().metadata;
+        metadata.co_authors = vec!["Christopher Tolkien".to_string()];
+        let update = BookUpdate { isbn: Some("9780261103573".to_string()), metadata: Some(metadata), ..Default::default() };
+        store.update_book("9780618640157", update).unwrap();
+        assert!(store.get_books_by_author("Alan Lee").unwrap().is_empty());
+        let books = store.get_books_by_author("Christopher Tolkien").unwrap();
+        assert_eq!(books.iter().map(|book| book.isbn.as_str()).collect::<Vec<_>>(), ["9780261103573"]);
+        assert_eq!(store.get_books_by_author("J.R.R. Tolkien").unwrap().len(), 1);
+
+        store.remove_book("9780261103573").unwrap();
+        assert!(store.get_books_by_author("Christopher Tolkien").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_newer_schema_is_rejected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("library.db");
+        Connection::open(&path).unwrap().pragma_update(None, "user_version", 99).unwrap();
+        assert!(matches!(SqliteStore::open(&path), Err(LibraryError::DatabaseError(_))));
+    }
+
+    // 检查 load_books 实际执行的两条语句，任何一步退化为全表扫描都会失败
+    #[test]
+    fn test_lookups_use_indexes() {
+        let store = SqliteStore::open_in_memory().unwrap();
+        let plan = |sql: &str| -> String {
+            let mut statement = store.conn.prepare(&format!("EXPLAIN QUERY PLAN {}", sql)).unwrap();
+            let rows = statement.query_map(params!["x"], |row| row.get::<_, String>(3)).unwrap();
+            rows.map(Result::unwrap).collect::<Vec<_>>().join("; ")
+        };
+        let lookups = [
+            (BOOK_BY_ISBN, &["sqlite_autoindex_books_1"][..]),
+            (BOOKS_BY_TITLE, &["books_title"][..]),
+            (BOOKS_BY_AUTHOR, &["books_author", "co_authors_name"][..]),
+        ];
+        for (filter, indexes) in lookups {
+            for sql in [books_sql(filter), copies_sql(filter)] {
+                let plan = plan(&sql);
+                assert!(!plan.contains("SCAN"), "{}: {}", filter, plan);
+                for index in indexes {
+                    assert!(plan.contains(index), "{}: {}", filter, plan);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_failed_borrow_leaves_no_partial_state() {
+        let mut store = SqliteStore::open_in_memory().unwrap();
+        store_contract::register(&mut store, "Alice", MembershipTier::Standard);
+        store.add_book(Book::new("The Hobbit".to_string(), "J.R.R. Tolkien".to_string(), "9780618640157".to_string())).unwrap();
+        // 让插入借阅记录失败，副本的借出状态应随事务回滚
+        store.conn.execute_batch("DROP TABLE loans").unwrap();
+        assert!(matches!(store.borrow_book("9780618640157", "Alice"), Err(LibraryError::DatabaseError(_))));
+        assert!(store.get_book("9780618640157").unwrap().borrowers().is_empty());
+    }
+}
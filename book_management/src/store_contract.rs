@@ -0,0 +1,217 @@
+// 所有 BookStore 实现都要通过的测试，各实现在自己的测试中调用 run
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use chrono::{Duration, NaiveDate};
+use crate::persistence::Storage;
+use crate::*;
+
+fn hobbit() -> Book {
+    Book::new("The Hobbit".to_string(), "J.R.R. Tolkien".to_string(), "978-0-618-64015-7".to_string())
+}
+
+fn pride() -> Book {
+    Book::new("Pride and Prejudice".to_string(), "Jane Austen".to_string(), "9780141439518".to_string())
+}
+
+// 测试用的读者，读者证号即姓名，会员到 2099 年底才过期
+pub fn patron(id: &str, tier: MembershipTier) -> Patron {
+    let expires = NaiveDate::from_ymd_opt(2099, 12, 31).unwrap();
+    Patron::new(id.to_string(), id.to_string(), String::new(), tier, expires)
+}
+
+pub fn register<S: BookStore>(store: &mut S, id: &str, tier: MembershipTier) {
+    store.register_patron(patron(id, tier)).unwrap();
+}
+
+// 登记若干标准会员
+pub fn register_all<S: BookStore>(store: &mut S, ids: &[&str]) {
+    for id in ids {
+        register(store, id, MembershipTier::Standard);
+    }
+}
+
+// 每次保存都失败的存储，例如磁盘已满
+pub struct FailingStorage(pub PathBuf);
+
+impl Storage for FailingStorage {
+    fn load(&mut self) -> LibraryResult<Library> {
+        Ok(Library::new())
+    }
+
+    fn save(&mut self, _library: &Library) -> LibraryResult<()> {
+        Err(LibraryError::IoError("No space left on device".to_string()))
+    }
+
+    fn path(&self) -> &Path {
+        &self.0
+    }
+}
+
+// 依次运行所有检查，new_store 每次创建一个使用给定时钟的空存储
+pub fn run<S: BookStore>(new_store: impl Fn(Arc<dyn Clock>) -> S) {
+    let fresh = || {
+        let now = NaiveDate::from_ymd_opt(2024, 1, 1).unwrap().and_hms_opt(10, 0, 0).unwrap();
+        let clock = Arc::new(ManualClock::new(now));
+        (new_store(clock.clone()), clock)
+    };
+    add_and_get(fresh().0);
+    lookups(fresh().0);
+    borrow_and_return(fresh().0);
+    overdue_fines(fresh());
+    loan_limits(fresh().0);
+    remove_and_update(fresh().0);
+}
+
+fn add_and_get<S: BookStore>(mut store: S) {
+    store.add_book(hobbit()).unwrap();
+    assert_eq!(store.add_book(hobbit()), Err(LibraryError::BookAlreadyExists));
+    // ISBN-10 与对应的 ISBN-13 是同一本书
+    let same = Book::new("Another".to_string(), "Someone".to_string(), "0-618-64015-0".to_string());
+    assert_eq!(store.add_book(same), Err(LibraryError::BookAlreadyExists));
+    let invalid = Book::new("Bad".to_string(), "Nobody".to_string(), "978-0618640158".to_string());
+    assert_eq!(store.add_book(invalid), Err(LibraryError::InvalidIsbn("978-0618640158".to_string())));
+
+    let book = store.get_book("0618640150").unwrap();
+    assert_eq!(book.isbn, "9780618640157");
+    assert_eq!(book.title, "The Hobbit");
+    assert_eq!(book.total_copies(), 1);
+    assert_eq!(store.get_book("9780141439518"), Err(LibraryError::BookNotFound));
+}
+
+fn lookups<S: BookStore>(mut store: S) {
+    let mut book = pride();
+    book.category = Some("小说".to_string());
+    book.metadata = Metadata {
+        co_authors: vec!["Vivien Jones".to_string()],
+        publisher: Some("Penguin Classics".to_string()),
+        year: Some(2003),
+        language: Some("en".to_string()),
+        subjects: vec!["Courtship".to_string()],
+        classification: Some("823.7".parse().unwrap()),
+        ..Metadata::default()
+    };
+    book.add_copy("A-2".to_string(), Condition::Fair);
+    store.add_book(book.clone()).unwrap();
+    store.add_book(hobbit()).unwrap();
+    let mut silmarillion = hobbit();
+    silmarillion.title = "The Silmarillion".to_string();
+    silmarillion.isbn = "9787111213826".to_string();
+    store.add_book(silmarillion).unwrap();
+
+    let isbns = |books: Vec<Book>| books.into_iter().map(|book| book.isbn).collect::<Vec<_>>();
+    assert_eq!(isbns(store.all_books().unwrap()), vec!["9780141439518", "9780618640157", "9787111213826"]);
+    assert_eq!(isbns(store.get_books_by_author("J.R.R. Tolkien").unwrap()), vec!["9780618640157", "9787111213826"]);
+    assert!(store.get_books_by_author("Nobody").unwrap().is_empty());
+    // 合著者也能查到
+    assert_eq!(isbns(store.get_books_by_author("Vivien Jones").unwrap()), vec!["9780141439518"]);
+    // 副本、分类和扩展信息完整保存
+    assert_eq!(store.get_book("9780141439518").unwrap(), book);
+}
+
+fn borrow_and_return<S: BookStore>(mut store: S) {
+    let mut book = hobbit();
+    book.add_copy(String::new(), Condition::Good);
+    store.add_book(book).unwrap();
+    assert_eq!(store.borrow_book("9780618640157", "Alice"), Err(LibraryError::PatronNotFound));
+    register(&mut store, "Alice", MembershipTier::Standard);
+    register(&mut store, "Bob", MembershipTier::Standard);
+    register(&mut store, "Carol", MembershipTier::Standard);
+    assert_eq!(
+        store.register_patron(patron("Alice", MembershipTier::Basic)),
+        Err(LibraryError::PatronAlreadyExists)
+    );
+
+    let first = store.borrow_book("978-0-618-64015-7", "Alice").unwrap();
+    assert_eq!(first.copy_id, 1);
+    assert_eq!(first.due, NaiveDate::from_ymd_opt(2024, 1, 31).unwrap());
+    let second = store.borrow_book("9780618640157", "Bob").unwrap();
+    assert_eq!(second.copy_id, 2);
+    assert_ne!(first.id, second.id);
+    assert_eq!(store.borrow_book("9780618640157", "Carol"), Err(LibraryError::BookAlreadyBorrowed));
+    assert_eq!(store.get_book("9780618640157").unwrap().borrowers(), vec!["Alice", "Bob"]);
+    assert_eq!(store.borrow_book("9780141439518", "Carol"), Err(LibraryError::BookNotFound));
+
+    let receipt = store.return_book("9780618640157", "Alice").unwrap();
+    assert_eq!(receipt.copy_id, 1);
+    assert_eq!(receipt.loan_id, Some(first.id));
+    assert_eq!(receipt.fine, 0);
+    assert_eq!(store.return_book("9780618640157", "Alice"), Err(LibraryError::BookNotBorrowed));
+    assert_eq!(store.return_book("9780141439518", "Alice"), Err(LibraryError::BookNotFound));
+    assert_eq!(store.borrow_book("9780618640157", "Carol").unwrap().copy_id, 1);
+}
+
+fn overdue_fines<S: BookStore>((mut store, clock): (S, Arc<ManualClock>)) {
+    store.add_book(hobbit()).unwrap();
+    store.add_book(pride()).unwrap();
+    register(&mut store, "Alice", MembershipTier::Standard);
+
+    store.borrow_book("9780618640157", "Alice").unwrap();
+    clock.advance(Duration::days(40));
+    assert_eq!(store.return_book("9780618640157", "Alice").unwrap().fine, 100);
+
+    // 罚款达到上限后不能继续借阅
+    store.borrow_book("9780618640157", "Alice").unwrap();
+    clock.advance(Duration::days(300));
+    assert_eq!(store.return_book("9780618640157", "Alice").unwrap().fine, 2000);
+    assert_eq!(
+        store.borrow_book("9780141439518", "Alice"),
+        Err(LibraryError::UnpaidFines { balance: 2100, threshold: 1000 })
+    );
+}
+
+fn loan_limits<S: BookStore>(mut store: S) {
+    register(&mut store, "Basic", MembershipTier::Basic);
+    for isbn in ["9780618640157", "9780141439518", "9781617294556", "9787111213826"] {
+        store.add_book(Book::new(isbn.to_string(), "Author".to_string(), isbn.to_string())).unwrap();
+    }
+    let loan = store.borrow_book("9780618640157", "Basic").unwrap();
+    // 普通会员借期 14 天
+    assert_eq!(loan.due, NaiveDate::from_ymd_opt(2024, 1, 15).unwrap());
+    store.borrow_book("9780141439518", "Basic").unwrap();
+    store.borrow_book("9781617294556", "Basic").unwrap();
+    assert_eq!(store.borrow_book("9787111213826", "Basic"), Err(LibraryError::LoanLimitReached { limit: 3 }));
+    store.return_book("9780618640157", "Basic").unwrap();
+    store.borrow_book("9787111213826", "Basic").unwrap();
+}
+
+fn remove_and_update<S: BookStore>(mut store: S) {
+    store.add_book(hobbit()).unwrap();
+    store.add_book(pride()).unwrap();
+    register(&mut store, "Alice", MembershipTier::Standard);
+
+    store.borrow_book("9780618640157", "Alice").unwrap();
+    assert_eq!(store.remove_book("9780618640157"), Err(LibraryError::BookAlreadyBorrowed));
+
+    let update = BookUpdate {
+        title: Some("The Hobbit, or There and Back Again".to_string()),
+        category: Some(Some("小说".to_string())),
+        ..BookUpdate::default()
+    };
+    store.update_book("0618640150", update).unwrap();
+    let metadata = Metadata { edition: Some("第 2 版".to_string()), ..Metadata::default() };
+    store.update_book("9780618640157", BookUpdate { metadata: Some(metadata.clone()), ..BookUpdate::default() }).unwrap();
+    let book = store.get_book("9780618640157").unwrap();
+    assert_eq!(book.metadata, metadata);
+    assert_eq!(book.title, "The Hobbit, or There and Back Again");
+    assert_eq!(book.author, "J.R.R. Tolkien");
+    assert_eq!(book.category.as_deref(), Some("小说"));
+
+    let to = |isbn: &str| BookUpdate { isbn: Some(isbn.to_string()), ..BookUpdate::default() };
+    assert_eq!(store.update_book("9780618640157", to("9780141439518")), Err(LibraryError::BookAlreadyExists));
+    assert_eq!(
+        store.update_book("9780618640157", to("123")),
+        Err(LibraryError::InvalidIsbn("123".to_string()))
+    );
+    assert_eq!(store.update_book("9781617294556", to("9787111213826")), Err(LibraryError::BookNotFound));
+
+    // 更正 ISBN 后借出的副本和借阅记录跟随迁移
+    store.update_book("9780618640157", to("9787111213826")).unwrap();
+    assert_eq!(store.get_book("9780618640157"), Err(LibraryError::BookNotFound));
+    assert_eq!(store.get_book("9787111213826").unwrap().borrowers(), vec!["Alice"]);
+    assert!(store.return_book("9787111213826", "Alice").unwrap().loan_id.is_some());
+
+    assert_eq!(store.remove_book("9787111213826").unwrap().title, "The Hobbit, or There and Back Again");
+    assert_eq!(store.get_book("9787111213826"), Err(LibraryError::BookNotFound));
+    assert_eq!(store.remove_book("9787111213826"), Err(LibraryError::BookNotFound));
+    assert_eq!(store.all_books().unwrap().len(), 1);
+}
@@ -0,0 +1,565 @@
+use crate::error::{GradeError, GradeResult};
+use crate::gpa::GpaScale;
+use crate::grading::{GradeLevel, GradingScale};
+use crate::grade::{parse_score, Grade};
+use crate::io::{FileIO, DEFAULT_DATA_FILE};
+use crate::student::Student;
+use crate::system::GradeManagementSystem;
+
+pub const USAGE: &str = "\
+用法: grade_management_system [--data <数据文件>] <命令> [参数...]
+不带任何参数时启动图形界面，数据文件默认为 grades.json。
+修改数据的命令执行成功后会立即保存到数据文件。
+
+命令:
+  student add <学号> <姓名> <班级> <专业>   添加学生
+  student remove <学号>                     删除学生及其所有成绩
+  student list                              列出所有学生
+  grade add <学号> <科目> <成绩> <学期>     添加成绩
+  grade update <学号> <科目> <学期> <成绩>  修改成绩
+  course set <课程> <学分>                  添加课程或修改学分
+  course remove <课程>                      删除课程定义
+  course list                               列出所有课程
+  gpa-scale [4.0|5.0|pku]                   查看或设置绩点算法
+  scale show [<课程>]                       查看全局或某门课程的等级制
+  scale set <letter|letter-pm|five-level|pass-fail> [--course <课程>]   使用内置等级制
+  scale define <名称> <等级=最低分>... [--pass <及格线>] [--course <课程>]   自定义等级制
+  scale reset <课程>                        课程改用全局等级制
+  transcript <学号> [--semester <学期>]     显示成绩单，附学分加权平均成绩和绩点
+  average <学号> [<学期>]                   计算学期或累计的加权平均成绩和绩点
+  stats <科目> <学期>                       显示某门课程的成绩分布
+  import <CSV 文件>                         从 CSV 文件导入成绩
+  export <学号> <CSV 文件> [--semester <学期>]   导出成绩单到 CSV 文件
+  help                                      显示本帮助
+
+每个命令只接受上面列出的选项。名称本身以 -- 开头时，先写一个单独的 -- ，之后的参数都不再当作选项。";
+
+// 一条命令
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    AddStudent { id: String, name: String, class: String, major: String },
+    RemoveStudent { id: String },
+    ListStudents,
+    AddGrade { student_id: String, subject: String, score: f32, semester: String },
+    UpdateGrade { student_id: String, subject: String, semester: String, score: f32 },
+    SetCourse { name: String, credits: f32 },
+    RemoveCourse { name: String },
+    ListCourses,
+    GpaScale { scale: Option<GpaScale> },
+    ShowGradingScale { course: Option<String> },
+    SetGradingScale { scale: GradingScale, course: Option<String> },
+    ResetGradingScale { course: String },
+    Transcript { student_id: String, semester: Option<String> },
+    Average { student_id: String, semester: Option<String> },
+    Statistics { subject: String, semester: String },
+    Import { path: String },
+    Export { student_id: String, path: String, semester: Option<String> },
+    Help,
+}
+
+impl Command {
+    // 是否会修改数据，修改后需要保存
+    fn modifies(&self) -> bool {
+        matches!(
+            self,
+            Command::AddStudent { .. }
+                | Command::RemoveStudent { .. }
+                | Command::AddGrade { .. }
+                | Command::UpdateGrade { .. }
+                | Command::Import { .. }
+                | Command::SetCourse { .. }
+                | Command::RemoveCourse { .. }
+                | Command::GpaScale { scale: Some(_) }
+                | Command::SetGradingScale { .. }
+                | Command::ResetGradingScale { .. }
+        )
+    }
+}
+
+// 解析一条命令，例如 ["grade", "add", "2024001", "数学", "95", "2024春"]
+pub fn parse_command(args: &[String]) -> GradeResult<Command> {
+    let (words, options) = split_options(args)?;
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    // 每个命令只接受自己的选项，其余选项直接报错而不是悄悄忽略
+    let accepted: &[&str] = match words.as_slice() {
+        ["scale", "set", ..] => &["--course"],
+        ["scale", "define", ..] => &["--course", "--pass"],
+        ["transcript", ..] | ["export", ..] => &["--semester"],
+        _ => &[],
+    };
+    if let Some((name, _)) = options.iter().find(|(name, _)| !accepted.contains(&name.as_str())) {
+        return Err(GradeError::UnsupportedOption(name.clone()));
+    }
+    let option = |name: &str| options.iter().find(|(n, _)| n == name).map(|(_, value)| value.clone());
+    let semester = option("--semester");
+    let course = option("--course");
+    let pass_score = option("--pass");
+    let command = match words.as_slice() {
+        ["student", "add", id, name, class, major] => Command::AddStudent {
+            id: id.to_string(),
+            name: name.to_string(),
+            class: class.to_string(),
+            major: major.to_string(),
+        },
+        ["student", "remove", id] => Command::RemoveStudent { id: id.to_string() },
+        ["student", "list"] => Command::ListStudents,
+        ["grade", "add", student_id, subject, score, semester] => Command::AddGrade {
+            student_id: student_id.to_string(),
+            subject: subject.to_string(),
+            score: parse_score(score)?,
+            semester: semester.to_string(),
+        },
+        ["grade", "update", student_id, subject, semester, score] => Command::UpdateGrade {
+            student_id: student_id.to_string(),
+            subject: subject.to_string(),
+            semester: semester.to_string(),
+            score: parse_score(score)?,
+        },
+        ["course", "set", name, credits] => Command::SetCourse {
+            name: name.to_string(),
+            credits: credits.parse::<f32>().map_err(|_| GradeError::InvalidCredits(credits.to_string()))?,
+        },
+        ["course", "remove", name] => Command::RemoveCourse { name: name.to_string() },
+        ["course", "list"] => Command::ListCourses,
+        ["gpa-scale"] => Command::GpaScale { scale: None },
+        ["gpa-scale", scale] => Command::GpaScale { scale: Some(scale.parse()?) },
+        ["scale", "show"] => Command::ShowGradingScale { course: None },
+        ["scale", "show", name] => Command::ShowGradingScale { course: Some(name.to_string()) },
+        ["scale", "set", preset] => Command::SetGradingScale {
+            scale: GradingScale::from_preset(preset)?,
+            course,
+        },
+        ["scale", "define", name, levels @ ..] if !levels.is_empty() => {
+            let levels = levels.iter().map(|level| parse_level(level)).collect::<GradeResult<Vec<_>>>()?;
+            let pass_score = match pass_score {
+                Some(score) => parse_score(&score)?,
+                None => 60.0,
+            };
+            let scale = GradingScale::new(name.to_string(), levels, pass_score)?;
+            Command::SetGradingScale { scale, course }
+        }
+        ["scale", "reset", name] => Command::ResetGradingScale { course: name.to_string() },
+        ["transcript", student_id] => Command::Transcript { student_id: student_id.to_string(), semester },
+        ["average", student_id] => Command::Average { student_id: student_id.to_string(), semester: None },
+        ["average", student_id, semester] => {
+            Command::Average { student_id: student_id.to_string(), semester: Some(semester.to_string()) }
+        }
+        ["stats", subject, semester] => Command::Statistics { subject: subject.to_string(), semester: semester.to_string() },
+        ["import", path] => Command::Import { path: path.to_string() },
+        ["export", student_id, path] => {
+            Command::Export { student_id: student_id.to_string(), path: path.to_string(), semester }
+        }
+        ["help"] => Command::Help,
+        [] => return Err(GradeError::MissingCommand),
+        _ => return Err(GradeError::UnknownCommand(args.join(" "))),
+    };
+    Ok(command)
+}
+
+// 解析形如 优=90 的等级定义
+fn parse_level(level: &str) -> GradeResult<GradeLevel> {
+    let (name, min_score) = level.split_once('=').ok_or_else(|| GradeError::InvalidLevel(level.to_string()))?;
+    Ok(GradeLevel::new(name, parse_score(min_score)?))
+}
+
+// 命令行选项，按出现顺序保存 (选项名, 参数)
+type Options = Vec<(String, String)>;
+
+// 把参数分成普通参数和 --name value 形式的选项。单独的 -- 之后都是普通参数，
+// 用来输入本身以 -- 开头的名称
+fn split_options(args: &[String]) -> GradeResult<(Vec<String>, Options)> {
+    let mut words = Vec::new();
+    let mut options = Options::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            words.extend(args.cloned());
+            break;
+        }
+        if !arg.starts_with("--") {
+            words.push(arg.clone());
+            continue;
+        }
+        let value = args.next().ok_or_else(|| GradeError::MissingOption(arg.clone()))?;
+        if options.iter().any(|(name, _)| name == arg) {
+            return Err(GradeError::DuplicateOption(arg.clone()));
+        }
+        options.push((arg.clone(), value.clone()));
+    }
+    Ok((words, options))
+}
+
+// 执行一条命令，返回要输出的文本
+pub fn execute(system: &mut GradeManagementSystem, file_io: &FileIO, command: Command) -> GradeResult<String> {
+    let output = match command {
+        Command::AddStudent { id, name, class, major } => {
+            let student = Student::new(id, name, class, major);
+            let text = format!("学生添加成功: {}", student);
+            system.add_student(student)?;
+            text
+        }
+        Command::RemoveStudent { id } => {
+            system.remove_student(&id)?;
+            format!("已删除学号 {} 及其所有成绩", id)
+        }
+        Command::ListStudents => {
+            let mut students = system.get_all_students();
+            students.sort_by(|a, b| a.id.cmp(&b.id));
+            if students.is_empty() {
+                "没有学生".to_string()
+            } else {
+                students.iter().map(|student| student.to_string()).collect::<Vec<_>>().join("\n")
+            }
+        }
+        Command::AddGrade { student_id, subject, score, semester } => {
+            let grade = Grade::new(student_id, subject, score, semester);
+            let text = format!("成绩添加成功: {}, 等级: {}", grade, system.grade_level(&grade));
+            system.add_grade(grade)?;
+            text
+        }
+        Command::UpdateGrade { student_id, subject, semester, score } => {
+            system.update_grade(&student_id, &subject, &semester, score)?;
+            format!("已将学号 {} 的 {} 学期 {} 科目成绩修改为 {:.1}", student_id, semester, subject, score)
+        }
+        Command::SetCourse { name, credits } => {
+            system.set_course_credits(&name, credits)?;
+            match system.get_course(&name) {
+                Some(course) => format!("课程设置成功: {}", course),
+                None => format!("课程设置成功: {}", name),
+            }
+        }
+        Command::RemoveCourse { name } => {
+            system.remove_course(&name)?;
+            format!("已删除课程 {}，其成绩之后按默认学分计算", name)
+        }
+        Command::ListCourses => {
+            let courses = system.get_all_courses();
+            if courses.is_empty() {
+                "没有定义课程，所有科目按 1 学分计算".to_string()
+            } else {
+                courses.iter().map(|course| course.to_string()).collect::<Vec<_>>().join("\n")
+            }
+        }
+        Command::GpaScale { scale: Some(scale) } => {
+            system.set_gpa_scale(scale);
+            format!("绩点算法已设置为 {}", scale)
+        }
+        Command::GpaScale { scale: None } => format!("当前绩点算法: {}", system.gpa_scale()),
+        Command::ShowGradingScale { course: None } => format!("全局等级制: {}", system.grading_scale()),
+        Command::ShowGradingScale { course: Some(course) } => {
+            format!("{} 使用的等级制: {}", course, system.grading_scale_for(&course))
+        }
+        Command::SetGradingScale { scale, course: None } => {
+            let text = format!("全局等级制已设置为 {}", scale);
+            system.set_grading_scale(scale);
+            text
+        }
+        Command::SetGradingScale { scale, course: Some(course) } => {
+            let text = format!("{} 的等级制已设置为 {}", course, scale);
+            system.set_course_grading_scale(&course, Some(scale));
+            text
+        }
+        Command::ResetGradingScale { course } => {
+            system.get_course(&course).ok_or_else(|| GradeError::CourseNotFound(course.clone()))?;
+            system.set_course_grading_scale(&course, None);
+            format!("{} 改用全局等级制 {}", course, system.grading_scale().name)
+        }
+        Command::Transcript { student_id, semester } => transcript(system, &student_id, semester.as_deref())?,
+        Command::Average { student_id, semester } => {
+            let student = system
+                .get_student(&student_id)
+                .ok_or_else(|| GradeError::StudentNotFound(student_id.clone()))?;
+            let (label, average, gpa) = match &semester {
+                Some(semester) => (
+                    format!("{} 学期", semester),
+                    system.calculate_semester_average(&student_id, semester),
+                    system.calculate_semester_gpa(&student_id, semester),
+                ),
+                None => (
+                    "所有学期".to_string(),
+                    system.calculate_cumulative_average(&student_id),
+                    system.calculate_cumulative_gpa(&student_id),
+                ),
+            };
+            match (average, gpa) {
+                (Some(average), Some(gpa)) => format!(
+                    "{} 在 {}的加权平均成绩: {:.1}，绩点: {:.2}（{}）",
+                    student.name, label, average, gpa, system.gpa_scale()
+                ),
+                _ => format!("{} 在 {}没有成绩", student.name, label),
+            }
+        }
+        Command::Statistics { subject, semester } => {
+            let statistics = system.get_subject_statistics(&subject, &semester);
+            let mut lines = vec![format!(
+                "{} 学期 {} 科目成绩分布（{}）",
+                semester,
+                subject,
+                system.grading_scale_for(&subject).name
+            )];
+            // 按等级从高到低显示
+            for (level, count) in statistics {
+                lines.push(format!("{} 等级: {} 人", level, count));
+            }
+            let grades = system.get_subject_grades(&subject, &semester);
+            let passing = grades.iter().filter(|(_, grade)| system.is_passing(grade)).count();
+            lines.push(format!("及格: {} / {} 人", passing, grades.len()));
+            lines.join("\n")
+        }
+        Command::Import { path } => {
+            let before = system.get_all_grades().len();
+            file_io.import_grades_from_csv(system, &path)?;
+            format!("已导入 {} 条成绩", system.get_all_grades().len() - before)
+        }
+        Command::Export { student_id, path, semester } => {
+            file_io.export_transcript_to_csv(system, &student_id, semester.as_deref(), &path)?;
+            format!("成绩单已导出到 {}", path)
+        }
+        Command::Help => USAGE.to_string(),
+    };
+    Ok(output)
+}
+
+// 成绩单：学生信息和按学期、科目排列的成绩，附上各学期和累计的加权平均成绩与绩点
+fn transcript(system: &GradeManagementSystem, student_id: &str, semester: Option<&str>) -> GradeResult<String> {
+    let student = system
+        .get_student(student_id)
+        .ok_or_else(|| GradeError::StudentNotFound(student_id.to_string()))?;
+    let mut grades = match semester {
+        Some(semester) => system.get_student_semester_grades(student_id, semester),
+        None => system.get_student_grades(student_id),
+    };
+    grades.sort_by(|a, b| a.semester.cmp(&b.semester).then_with(|| a.subject.cmp(&b.subject)));
+    let mut lines = vec![student.to_string()];
+    if grades.is_empty() {
+        lines.push("没有成绩记录".to_string());
+    }
+    lines.extend(
+        grades
+            .iter()
+            .map(|grade| {
+                format!(
+                    "{}, 等级: {}, 学分: {:.1}",
+                    grade,
+                    system.grade_level(grade),
+                    system.credits_for(&grade.subject)
+                )
+            }),
+    );
+    let scale = system.gpa_scale();
+    let semesters = match semester {
+        Some(semester) => vec![semester.to_string()],
+        None => system.get_student_semesters(student_id),
+    };
+    for semester in &semesters {
+        if let (Some(average), Some(gpa)) = (
+            system.calculate_semester_average(student_id, semester),
+            system.calculate_semester_gpa(student_id, semester),
+        ) {
+            lines.push(format!("{} 学期加权平均成绩: {:.1}，绩点: {:.2}", semester, average, gpa));
+        }
+    }
+    if semester.is_none() {
+        if let (Some(average), Some(gpa)) = (
+            system.calculate_cumulative_average(student_id),
+            system.calculate_cumulative_gpa(student_id),
+        ) {
+            lines.push(format!(
+                "累计加权平均成绩: {:.1}，绩点: {:.2}，总学分: {:.1}",
+                average,
+                gpa,
+                system.total_credits(&grades)
+            ));
+        }
+    }
+    if !grades.is_empty() {
+        lines.push(format!("绩点算法: {}（满分 {:.1}）", scale, scale.max_points()));
+    }
+    Ok(lines.join("\n"))
+}
+
+// 命令行模式的入口：加载数据文件，执行命令，有修改时保存
+pub fn run(args: &[String]) -> GradeResult<String> {
+    // --data 是全局选项，只能写在命令之前
+    let (data_file, args) = match args {
+        [flag, path, rest @ ..] if flag == "--data" => (path.clone(), rest),
+        [flag] if flag == "--data" => return Err(GradeError::MissingOption(flag.clone())),
+        _ => (DEFAULT_DATA_FILE.to_string(), args),
+    };
+    let command = parse_command(args)?;
+    let file_io = FileIO::new(data_file);
+    let mut system = file_io.load_from_file()?;
+    let modifies = command.modifies();
+    let output = execute(&mut system, &file_io, command)?;
+    if modifies {
+        file_io.save_to_file(&system)?;
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::tests::TempFile;
+
+    fn args(line: &str) -> Vec<String> {
+        line.split_whitespace().map(str::to_string).collect()
+    }
+
+    fn parse(line: &str) -> GradeResult<Command> {
+        parse_command(&args(line))
+    }
+
+    #[test]
+    fn test_parse_commands() {
+        assert_eq!(
+            parse("grade add 1 数学 95.5 2024春").unwrap(),
+            Command::AddGrade {
+                student_id: "1".to_string(),
+                subject: "数学".to_string(),
+                score: 95.5,
+                semester: "2024春".to_string(),
+            }
+        );
+        assert_eq!(parse("course set 数学 4").unwrap(), Command::SetCourse { name: "数学".to_string(), credits: 4.0 });
+        assert_eq!(parse("gpa-scale pku").unwrap(), Command::GpaScale { scale: Some(GpaScale::Pku) });
+        assert_eq!(
+            parse("average 1 2024春").unwrap(),
+            Command::Average { student_id: "1".to_string(), semester: Some("2024春".to_string()) }
+        );
+        assert_eq!(
+            parse("transcript 1 --semester 2024春").unwrap(),
+            Command::Transcript { student_id: "1".to_string(), semester: Some("2024春".to_string()) }
+        );
+        assert_eq!(
+            parse("export 1 out.csv").unwrap(),
+            Command::Export { student_id: "1".to_string(), path: "out.csv".to_string(), semester: None }
+        );
+        assert_eq!(parse("help").unwrap(), Command::Help);
+    }
+
+    #[test]
+    fn test_parse_grading_scale_commands() {
+        assert_eq!(
+            parse("scale set pass-fail --course 体育").unwrap(),
+            Command::SetGradingScale { scale: GradingScale::pass_fail(), course: Some("体育".to_string()) }
+        );
+        let Command::SetGradingScale { scale, course: None } = parse("scale define 两级 好=80 差=0 --pass 80").unwrap() else {
+            panic!("应解析为全局等级制");
+        };
+        assert_eq!(scale.level_names(), ["好", "差"]);
+        assert_eq!(scale.pass_score, 80.0);
+        assert!(matches!(parse("scale define 两级 好=80 差=0 --pass 101"), Err(GradeError::InvalidScore(_))));
+        assert!(matches!(parse("scale define 两级 好80"), Err(GradeError::InvalidLevel(level)) if level == "好80"));
+        assert!(matches!(parse("scale define 两级 好=80 好=0"), Err(GradeError::InvalidGradingScale(_))));
+        assert!(matches!(parse("scale set gpa"), Err(GradeError::UnknownGradingScale(_))));
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_scores() {
+        for score in ["abc", "NaN", "inf", "-1", "100.1"] {
+            let line = format!("grade add 1 数学 {} 2024春", score);
+            assert!(matches!(parse(&line), Err(GradeError::InvalidScore(s)) if s == score), "{}", score);
+        }
+        assert!(parse("grade update 1 数学 2024春 0").is_ok());
+        assert!(parse("grade update 1 数学 2024春 100").is_ok());
+        assert!(matches!(parse("course set 数学 四"), Err(GradeError::InvalidCredits(c)) if c == "四"));
+    }
+
+    #[test]
+    fn test_parse_rejects_unsupported_options() {
+        assert!(matches!(parse("student list --semester 2024春"), Err(GradeError::UnsupportedOption(o)) if o == "--semester"));
+        assert!(matches!(parse("transcript 1 --course 数学"), Err(GradeError::UnsupportedOption(o)) if o == "--course"));
+        assert!(matches!(parse("scale set letter --pass 50"), Err(GradeError::UnsupportedOption(o)) if o == "--pass"));
+        assert!(matches!(parse("average 1 --semester 2024春"), Err(GradeError::UnsupportedOption(_))));
+        assert!(matches!(parse("transcript 1 --verbose x"), Err(GradeError::UnsupportedOption(o)) if o == "--verbose"));
+        assert!(matches!(parse("transcript 1 --semester"), Err(GradeError::MissingOption(o)) if o == "--semester"));
+        assert!(matches!(
+            parse("transcript 1 --semester 2024春 --semester 2024秋"),
+            Err(GradeError::DuplicateOption(o)) if o == "--semester"
+        ));
+    }
+
+    #[test]
+    fn test_parse_names_after_separator() {
+        assert_eq!(
+            parse("student add 1 -- --course 1班 计算机").unwrap(),
+            Command::AddStudent {
+                id: "1".to_string(),
+                name: "--course".to_string(),
+                class: "1班".to_string(),
+                major: "计算机".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_unknown_commands() {
+        assert!(matches!(parse(""), Err(GradeError::MissingCommand)));
+        assert!(matches!(parse("student"), Err(GradeError::UnknownCommand(c)) if c == "student"));
+        assert!(matches!(parse("grade add 1 数学 90"), Err(GradeError::UnknownCommand(_))));
+    }
+
+    fn run_line(data: &TempFile, line: &str) -> GradeResult<String> {
+        let mut all = vec!["--data".to_string(), data.path().to_string()];
+        all.extend(args(line));
+        run(&all)
+    }
+
+    #[test]
+    fn test_run_saves_modifications() {
+        let data = TempFile::new("json");
+        run_line(&data, "student add 1 张三 1班 计算机").unwrap();
+        run_line(&data, "course set 数学 4").unwrap();
+        run_line(&data, "grade add 1 数学 90 2024春").unwrap();
+        run_line(&data, "grade add 1 英语 60 2024春").unwrap();
+        run_line(&data, "gpa-scale 5.0").unwrap();
+
+        // 每条命令都重新读取数据文件
+        let system = FileIO::new(data.path().to_string()).load_from_file().unwrap();
+        assert_eq!(system.get_all_grades().len(), 2);
+        assert_eq!(system.gpa_scale(), GpaScale::FivePoint);
+        let output = run_line(&data, "average 1 2024春").unwrap();
+        assert!(output.contains("加权平均成绩: 84.0"), "{}", output);
+        assert!(output.contains("绩点: 3.40"), "{}", output);
+    }
+
+    #[test]
+    fn test_run_does_not_save_on_error_or_query() {
+        let data = TempFile::new("json");
+        assert!(matches!(run_line(&data, "grade add 1 数学 90 2024春"), Err(GradeError::StudentNotFound(_))));
+        assert!(!std::path::Path::new(data.path()).exists());
+        assert_eq!(run_line(&data, "student list").unwrap(), "没有学生");
+        assert!(!std::path::Path::new(data.path()).exists());
+
+        run_line(&data, "student add 1 张三 1班 计算机").unwrap();
+        let saved = std::fs::read_to_string(data.path()).unwrap();
+        assert!(matches!(run_line(&data, "student add 1 李四 2班 数学"), Err(GradeError::StudentExists(_))));
+        assert_eq!(std::fs::read_to_string(data.path()).unwrap(), saved);
+    }
+
+    #[test]
+    fn test_run_data_option() {
+        assert!(matches!(run(&args("--data")), Err(GradeError::MissingOption(o)) if o == "--data"));
+        let data = TempFile::new("json");
+        // --data 只能写在命令之前
+        let line = format!("student list --data {}", data.path());
+        assert!(matches!(run(&args(&line)), Err(GradeError::UnsupportedOption(o)) if o == "--data"));
+    }
+
+    #[test]
+    fn test_execute_uses_course_scale() {
+        let mut system = GradeManagementSystem::new();
+        let file_io = FileIO::new(String::new());
+        execute(&mut system, &file_io, parse("student add 1 张三 1班 计算机").unwrap()).unwrap();
+        execute(&mut system, &file_io, parse("scale set pass-fail --course 体育").unwrap()).unwrap();
+        let output = execute(&mut system, &file_io, parse("grade add 1 体育 65 2024春").unwrap()).unwrap();
+        assert!(output.ends_with("等级: 通过"), "{}", output);
+        execute(&mut system, &file_io, parse("scale reset 体育").unwrap()).unwrap();
+        assert_eq!(system.grading_scale_for("体育"), &GradingScale::letter());
+        assert!(matches!(
+            execute(&mut system, &file_io, parse("scale reset 数学").unwrap()),
+            Err(GradeError::CourseNotFound(_))
+        ));
+    }
+}
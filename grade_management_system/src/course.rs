@@ -0,0 +1,61 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use crate::error::{GradeError, GradeResult};
+use crate::grading::GradingScale;
+
+// 没有定义课程的科目按这个学分计算，此时加权平均等同于算术平均
+pub const DEFAULT_CREDITS: f32 = 1.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "CourseRecord")]
+pub struct Course {
+    pub name: String,    // 课程名称，与成绩中的科目对应
+    pub credits: f32,    // 学分
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub grading_scale: Option<GradingScale>,   // 课程单独使用的等级制，为空时使用全局等级制
+}
+
+// 数据文件中的课程，读入后检查学分，学分不正确时整个文件加载失败，而不是悄悄丢掉这门课程
+#[derive(Deserialize)]
+struct CourseRecord {
+    name: String,
+    credits: f32,
+    #[serde(default)]
+    grading_scale: Option<GradingScale>,
+}
+
+impl TryFrom<CourseRecord> for Course {
+    type Error = GradeError;
+
+    fn try_from(record: CourseRecord) -> GradeResult<Self> {
+        check_credits(record.credits)?;
+        Ok(Course { name: record.name, credits: record.credits, grading_scale: record.grading_scale })
+    }
+}
+
+impl Course {
+    // 创建新课程
+    pub fn new(name: String, credits: f32) -> Self {
+        Course { name, credits, grading_scale: None }
+    }
+}
+
+// 学分必须是大于 0 的有限数
+pub fn check_credits(credits: f32) -> GradeResult<()> {
+    if credits.is_finite() && credits > 0.0 {
+        Ok(())
+    } else {
+        Err(GradeError::InvalidCredits(credits.to_string()))
+    }
+}
+
+// 实现显示特征
+impl fmt::Display for Course {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "课程: {}, 学分: {:.1}", self.name, self.credits)?;
+        if let Some(scale) = &self.grading_scale {
+            write!(f, ", 等级制: {}", scale.name)?;
+        }
+        Ok(())
+    }
+}
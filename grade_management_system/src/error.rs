@@ -0,0 +1,66 @@
+use std::error::Error;
+use std::fmt;
+use std::io;
+use crate::messages;
+
+// 成绩管理系统的错误类型，显示给用户的文字在 messages 模块中
+#[derive(Debug)]
+pub enum GradeError {
+    StudentExists(String),   // 学号已存在
+    StudentNotFound(String), // 学号不存在
+    GradeExists { student_id: String, subject: String, semester: String },
+    GradeNotFound { student_id: String, subject: String, semester: String },
+    CourseNotFound(String),        // 课程不存在
+    InvalidCredits(String),        // 学分必须是大于 0 的数
+    UnknownGpaScale(String),       // 无法识别的绩点算法
+    UnknownGradingScale(String),   // 没有这个内置等级制
+    InvalidGradingScale(String),   // 等级制的定义不正确
+    InvalidScore(String),          // 成绩不是 0 到 100 之间的数
+    InvalidLevel(String),          // 等级定义不是 等级=最低分 的形式
+    InvalidRecord { line: u64 },   // CSV 记录的列数不足
+    MissingCommand,                // 命令行没有给出命令
+    UnknownCommand(String),        // 无法识别的命令
+    MissingOption(String),         // 选项缺少参数
+    UnsupportedOption(String),     // 命令不接受这个选项
+    DuplicateOption(String),       // 同一个选项出现了多次
+    Io(io::Error),                 // 读写文件失败
+    Parse(serde_json::Error),      // 数据文件的序列化或解析失败
+    Csv(csv::Error),               // 读写 CSV 失败
+}
+
+pub type GradeResult<T> = Result<T, GradeError>;
+
+impl fmt::Display for GradeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&messages::error(self))
+    }
+}
+
+impl Error for GradeError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            GradeError::Io(e) => Some(e),
+            GradeError::Parse(e) => Some(e),
+            GradeError::Csv(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for GradeError {
+    fn from(e: io::Error) -> Self {
+        GradeError::Io(e)
+    }
+}
+
+impl From<serde_json::Error> for GradeError {
+    fn from(e: serde_json::Error) -> Self {
+        GradeError::Parse(e)
+    }
+}
+
+impl From<csv::Error> for GradeError {
+    fn from(e: csv::Error) -> Self {
+        GradeError::Csv(e)
+    }
+}
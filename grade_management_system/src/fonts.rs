@@ -0,0 +1,126 @@
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use eframe::egui;
+
+// 用环境变量指定中文字体文件的路径，优先于自动查找
+pub const FONT_PATH_ENV: &str = "GRADE_MANAGEMENT_FONT";
+
+// 常见的中文字体文件，按优先顺序排列，比较时不区分大小写
+const CJK_FONT_FILES: &[&str] = &[
+    // macOS
+    "PingFang.ttc",
+    "Hiragino Sans GB.ttc",
+    "STHeiti Medium.ttc",
+    "STHeiti Light.ttc",
+    // Windows
+    "msyh.ttc",
+    "msyh.ttf",
+    "simhei.ttf",
+    "simsun.ttc",
+    // Linux
+    "NotoSansCJK-Regular.ttc",
+    "NotoSansCJKsc-Regular.otf",
+    "NotoSansSC-Regular.otf",
+    "SourceHanSansSC-Regular.otf",
+    "SourceHanSans-Regular.ttc",
+    "wqy-microhei.ttc",
+    "wqy-zenhei.ttc",
+    "DroidSansFallbackFull.ttf",
+];
+
+// Linux 的字体按厂商分在多层子目录中，查找时最多深入这么多层
+const MAX_SEARCH_DEPTH: usize = 4;
+
+// 注册到 egui 的字体名
+const CJK_FONT_NAME: &str = "cjk";
+
+// 各平台的字体目录，不存在的目录在查找时跳过
+fn font_dirs() -> Vec<PathBuf> {
+    let mut dirs = Vec::new();
+    let home = env::var_os("HOME").map(PathBuf::from);
+    if cfg!(target_os = "windows") {
+        let windir = env::var_os("WINDIR").map(PathBuf::from).unwrap_or_else(|| PathBuf::from(r"C:\Windows"));
+        dirs.push(windir.join("Fonts"));
+        if let Some(local) = env::var_os("LOCALAPPDATA") {
+            dirs.push(PathBuf::from(local).join(r"Microsoft\Windows\Fonts"));
+        }
+    } else if cfg!(target_os = "macos") {
+        dirs.push(PathBuf::from("/System/Library/Fonts"));
+        dirs.push(PathBuf::from("/System/Library/Fonts/Supplemental"));
+        dirs.push(PathBuf::from("/Library/Fonts"));
+        if let Some(home) = &home {
+            dirs.push(home.join("Library/Fonts"));
+        }
+    } else {
+        if let Some(data_home) = env::var_os("XDG_DATA_HOME") {
+            dirs.push(PathBuf::from(data_home).join("fonts"));
+        }
+        if let Some(home) = &home {
+            dirs.push(home.join(".local/share/fonts"));
+            dirs.push(home.join(".fonts"));
+        }
+        dirs.push(PathBuf::from("/usr/share/fonts"));
+        dirs.push(PathBuf::from("/usr/local/share/fonts"));
+    }
+    dirs
+}
+
+// 在目录（及其子目录）中收集所有已知的中文字体，返回 (优先级, 路径)
+fn collect_fonts(dir: &Path, depth: usize, found: &mut Vec<(usize, PathBuf)>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if depth < MAX_SEARCH_DEPTH {
+                collect_fonts(&path, depth + 1, found);
+            }
+            continue;
+        }
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if let Some(rank) = CJK_FONT_FILES.iter().position(|known| known.eq_ignore_ascii_case(name)) {
+            found.push((rank, path));
+        }
+    }
+}
+
+// 查找中文字体：先看环境变量指定的路径，再在系统字体目录中按优先顺序查找
+pub fn find_cjk_font() -> Result<PathBuf, String> {
+    if let Some(path) = env::var_os(FONT_PATH_ENV).map(PathBuf::from) {
+        if path.is_file() {
+            return Ok(path);
+        }
+        return Err(format!("{} 指定的字体文件 {} 不存在", FONT_PATH_ENV, path.display()));
+    }
+    let mut found = Vec::new();
+    for dir in font_dirs() {
+        collect_fonts(&dir, 0, &mut found);
+    }
+    found
+        .into_iter()
+        .min_by_key(|(rank, _)| *rank)
+        .map(|(_, path)| path)
+        .ok_or_else(|| format!("未找到中文字体，可以用环境变量 {} 指定字体文件", FONT_PATH_ENV))
+}
+
+// 加载中文字体并设为首选字体。找不到或读取失败时保留 egui 的默认字体，
+// 返回警告信息，中文可能无法正常显示
+pub fn install_cjk_font(ctx: &egui::Context) -> Result<PathBuf, String> {
+    let path = find_cjk_font()?;
+    let bytes = fs::read(&path).map_err(|e| format!("读取字体文件 {} 失败: {}", path.display(), e))?;
+
+    let mut fonts = egui::FontDefinitions::default();
+    fonts.font_data.insert(CJK_FONT_NAME.to_owned(), egui::FontData::from_owned(bytes));
+
+    // 将中文字体设置为比例字体和等宽字体的优先字体
+    for family in [egui::FontFamily::Proportional, egui::FontFamily::Monospace] {
+        fonts.families.entry(family).or_default().insert(0, CJK_FONT_NAME.to_owned());
+    }
+
+    ctx.set_fonts(fonts);
+    Ok(path)
+}
@@ -0,0 +1,111 @@
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use crate::error::GradeError;
+
+// 绩点的计算方法
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GpaScale {
+    #[default]
+    FourPoint,  // 4.0 分制：90 分以上 4.0，80-89 分 3.0，70-79 分 2.0，60-69 分 1.0
+    FivePoint,  // 5.0 分制：绩点 = (分数 - 50) / 10，100 分为 5.0，60 分为 1.0
+    Pku,        // 北大 4.0 公式：绩点 = 4 - 3 × (100 - 分数)² / 1600
+}
+
+impl GpaScale {
+    pub const ALL: [GpaScale; 3] = [GpaScale::FourPoint, GpaScale::FivePoint, GpaScale::Pku];
+
+    // 由百分制成绩换算绩点，不及格为 0
+    pub fn points(self, score: f32) -> f32 {
+        let score = score.clamp(0.0, 100.0);
+        if score < 60.0 {
+            return 0.0;
+        }
+        match self {
+            GpaScale::FourPoint => match score {
+                s if s >= 90.0 => 4.0,
+                s if s >= 80.0 => 3.0,
+                s if s >= 70.0 => 2.0,
+                _ => 1.0,
+            },
+            GpaScale::FivePoint => (score - 50.0) / 10.0,
+            GpaScale::Pku => 4.0 - 3.0 * (100.0 - score).powi(2) / 1600.0,
+        }
+    }
+
+    // 满绩点
+    pub fn max_points(self) -> f32 {
+        match self {
+            GpaScale::FivePoint => 5.0,
+            GpaScale::FourPoint | GpaScale::Pku => 4.0,
+        }
+    }
+}
+
+impl fmt::Display for GpaScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            GpaScale::FourPoint => "4.0 分制",
+            GpaScale::FivePoint => "5.0 分制",
+            GpaScale::Pku => "北大 4.0 公式",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for GpaScale {
+    type Err = GradeError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "4" | "4.0" | "four" => Ok(GpaScale::FourPoint),
+            "5" | "5.0" | "five" => Ok(GpaScale::FivePoint),
+            "pku" => Ok(GpaScale::Pku),
+            _ => Err(GradeError::UnknownGpaScale(s.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_four_point() {
+        let scale = GpaScale::FourPoint;
+        assert_eq!(scale.points(95.0), 4.0);
+        assert_eq!(scale.points(85.0), 3.0);
+        assert_eq!(scale.points(75.0), 2.0);
+        assert_eq!(scale.points(60.0), 1.0);
+        assert_eq!(scale.points(59.9), 0.0);
+        assert_eq!(scale.max_points(), 4.0);
+    }
+
+    #[test]
+    fn test_five_point() {
+        let scale = GpaScale::FivePoint;
+        assert_eq!(scale.points(100.0), 5.0);
+        assert_eq!(scale.points(85.0), 3.5);
+        assert_eq!(scale.points(60.0), 1.0);
+        assert_eq!(scale.points(59.0), 0.0);
+        assert_eq!(scale.max_points(), 5.0);
+    }
+
+    #[test]
+    fn test_pku() {
+        let scale = GpaScale::Pku;
+        assert_eq!(scale.points(100.0), 4.0);
+        assert_eq!(scale.points(90.0), 3.8125);
+        assert_eq!(scale.points(60.0), 1.0);
+        assert_eq!(scale.points(59.0), 0.0);
+        assert_eq!(scale.max_points(), 4.0);
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("4.0".parse::<GpaScale>().unwrap(), GpaScale::FourPoint);
+        assert_eq!("5".parse::<GpaScale>().unwrap(), GpaScale::FivePoint);
+        assert_eq!("PKU".parse::<GpaScale>().unwrap(), GpaScale::Pku);
+        assert!(matches!("3.0".parse::<GpaScale>(), Err(GradeError::UnknownGpaScale(s)) if s == "3.0"));
+    }
+}
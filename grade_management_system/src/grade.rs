@@ -1,5 +1,7 @@
 use std::fmt;
 use serde::{Serialize, Deserialize};
+use crate::error::{GradeError, GradeResult};
+use crate::grading::GradingScale;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Grade {
@@ -25,20 +27,22 @@ impl Grade {
         self.score = new_score;
     }
 
-    // 获取成绩等级
-    pub fn get_grade_level(&self) -> &str {
-        match self.score {
-            score if score >= 90.0 => "A",
-            score if score >= 80.0 => "B",
-            score if score >= 70.0 => "C",
-            score if score >= 60.0 => "D",
-            _ => "F",
-        }
+    // 按等级制获取成绩等级，课程使用哪个等级制由 GradeManagementSystem::grading_scale_for 决定
+    pub fn get_grade_level<'a>(&self, scale: &'a GradingScale) -> &'a str {
+        scale.level_for(self.score)
+    }
+
+    // 按等级制的及格线判断是否及格
+    pub fn is_passing(&self, scale: &GradingScale) -> bool {
+        scale.is_passing(self.score)
     }
+}
 
-    // 判断是否及格
-    pub fn is_passing(&self) -> bool {
-        self.score >= 60.0
+// 解析成绩，只接受 0 到 100 之间的数
+pub fn parse_score(score: &str) -> GradeResult<f32> {
+    match score.parse::<f32>() {
+        Ok(value) if (0.0..=100.0).contains(&value) => Ok(value),
+        _ => Err(GradeError::InvalidScore(score.to_string())),
     }
 }
 
@@ -47,8 +51,8 @@ impl fmt::Display for Grade {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
-            "学号: {}, 科目: {}, 成绩: {:.1}, 学期: {}, 等级: {}",
-            self.student_id, self.subject, self.score, self.semester, self.get_grade_level()
+            "学号: {}, 科目: {}, 成绩: {:.1}, 学期: {}",
+            self.student_id, self.subject, self.score, self.semester
         )
     }
 }
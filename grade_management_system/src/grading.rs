@@ -0,0 +1,225 @@
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use crate::error::{GradeError, GradeResult};
+
+// 等级制中的一个等级，分数不低于 min_score 时属于该等级
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GradeLevel {
+    pub name: String,
+    pub min_score: f32,
+}
+
+impl GradeLevel {
+    pub fn new(name: &str, min_score: f32) -> Self {
+        GradeLevel { name: name.to_string(), min_score }
+    }
+}
+
+// 等级制：把百分制成绩换算为等级，可以全局设置，也可以为某门课程单独设置
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "GradingScaleRecord")]
+pub struct GradingScale {
+    pub name: String,
+    pub levels: Vec<GradeLevel>,    // 按最低分从高到低排列
+    pub pass_score: f32,            // 及格线
+}
+
+// 数据文件中的等级制，读入后经过 GradingScale::new 检查，手工改坏的文件不会绕过校验
+#[derive(Deserialize)]
+struct GradingScaleRecord {
+    name: String,
+    levels: Vec<GradeLevel>,
+    pass_score: f32,
+}
+
+impl TryFrom<GradingScaleRecord> for GradingScale {
+    type Error = GradeError;
+
+    fn try_from(record: GradingScaleRecord) -> GradeResult<Self> {
+        GradingScale::new(record.name, record.levels, record.pass_score)
+    }
+}
+
+// 内置的等级制，名称可用于命令行和界面中选择
+pub const PRESETS: [&str; 4] = ["letter", "letter-pm", "five-level", "pass-fail"];
+
+impl GradingScale {
+    // 创建等级制，等级按最低分从高到低排序。至少要有一个等级，最低分和等级名称都不能重复，
+    // 最低分和及格线都在 0 到 100 之间
+    pub fn new(name: String, mut levels: Vec<GradeLevel>, pass_score: f32) -> GradeResult<Self> {
+        levels.sort_by(|a, b| b.min_score.total_cmp(&a.min_score));
+        let in_range = |score: f32| (0.0..=100.0).contains(&score);
+        let valid = !levels.is_empty()
+            && in_range(pass_score)
+            && levels.iter().all(|level| in_range(level.min_score) && !level.name.is_empty())
+            && levels.windows(2).all(|pair| pair[0].min_score > pair[1].min_score)
+            && levels.iter().enumerate().all(|(i, level)| levels[..i].iter().all(|other| other.name != level.name));
+        if !valid {
+            return Err(GradeError::InvalidGradingScale(name));
+        }
+        Ok(GradingScale { name, levels, pass_score })
+    }
+
+    // A-F 五级，原来固定使用的等级制
+    pub fn letter() -> Self {
+        let levels = [("A", 90.0), ("B", 80.0), ("C", 70.0), ("D", 60.0), ("F", 0.0)];
+        Self::preset("letter", &levels, 60.0)
+    }
+
+    // 带正负号的字母等级
+    pub fn letter_plus_minus() -> Self {
+        let levels = [
+            ("A", 93.0), ("A-", 90.0),
+            ("B+", 87.0), ("B", 83.0), ("B-", 80.0),
+            ("C+", 77.0), ("C", 73.0), ("C-", 70.0),
+            ("D+", 67.0), ("D", 63.0), ("D-", 60.0),
+            ("F", 0.0),
+        ];
+        Self::preset("letter-pm", &levels, 60.0)
+    }
+
+    // 优、良、中、及格、不及格
+    pub fn five_level() -> Self {
+        let levels = [("优", 90.0), ("良", 80.0), ("中", 70.0), ("及格", 60.0), ("不及格", 0.0)];
+        Self::preset("five-level", &levels, 60.0)
+    }
+
+    // 通过、不通过
+    pub fn pass_fail() -> Self {
+        Self::preset("pass-fail", &[("通过", 60.0), ("不通过", 0.0)], 60.0)
+    }
+
+    fn preset(name: &str, levels: &[(&str, f32)], pass_score: f32) -> Self {
+        GradingScale {
+            name: name.to_string(),
+            levels: levels.iter().map(|(level, min)| GradeLevel::new(level, *min)).collect(),
+            pass_score,
+        }
+    }
+
+    // 按名称取内置的等级制
+    pub fn from_preset(name: &str) -> GradeResult<Self> {
+        match name {
+            "letter" => Ok(Self::letter()),
+            "letter-pm" => Ok(Self::letter_plus_minus()),
+            "five-level" => Ok(Self::five_level()),
+            "pass-fail" => Ok(Self::pass_fail()),
+            _ => Err(GradeError::UnknownGradingScale(name.to_string())),
+        }
+    }
+
+    // 成绩所属的等级，低于所有最低分时属于最后一个等级
+    pub fn level_for(&self, score: f32) -> &str {
+        self.levels
+            .iter()
+            .find(|level| score >= level.min_score)
+            .or(self.levels.last())
+            .map_or("", |level| level.name.as_str())
+    }
+
+    // 所有等级的名称，从高到低
+    pub fn level_names(&self) -> Vec<&str> {
+        self.levels.iter().map(|level| level.name.as_str()).collect()
+    }
+
+    pub fn is_passing(&self, score: f32) -> bool {
+        score >= self.pass_score
+    }
+}
+
+impl Default for GradingScale {
+    fn default() -> Self {
+        Self::letter()
+    }
+}
+
+// 实现显示特征，例如 "five-level（优 ≥90, 良 ≥80, ..., 及格线 60）"
+impl fmt::Display for GradingScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let levels: Vec<String> = self
+            .levels
+            .iter()
+            .map(|level| format!("{} ≥{}", level.name, level.min_score))
+            .collect();
+        write!(f, "{}（{}, 及格线 {}）", self.name, levels.join(", "), self.pass_score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn levels(levels: &[(&str, f32)]) -> Vec<GradeLevel> {
+        levels.iter().map(|(name, min)| GradeLevel::new(name, *min)).collect()
+    }
+
+    #[test]
+    fn test_new_sorts_levels() {
+        let scale = GradingScale::new("s".to_string(), levels(&[("B", 60.0), ("A", 85.0), ("C", 0.0)]), 60.0).unwrap();
+        assert_eq!(scale.level_names(), ["A", "B", "C"]);
+    }
+
+    #[test]
+    fn test_new_rejects_invalid_scales() {
+        let invalid = |levels: Vec<GradeLevel>, pass_score: f32| {
+            matches!(GradingScale::new("s".to_string(), levels, pass_score), Err(GradeError::InvalidGradingScale(name)) if name == "s")
+        };
+        assert!(invalid(Vec::new(), 60.0));
+        assert!(invalid(levels(&[("A", 90.0), ("A", 60.0)]), 60.0));
+        assert!(invalid(levels(&[("A", 90.0), ("B", 90.0)]), 60.0));
+        assert!(invalid(levels(&[("", 90.0)]), 60.0));
+        assert!(invalid(levels(&[("A", f32::NAN)]), 60.0));
+        assert!(invalid(levels(&[("A", 101.0)]), 60.0));
+        assert!(invalid(levels(&[("A", -1.0)]), 60.0));
+        assert!(invalid(levels(&[("A", 90.0)]), f32::NAN));
+        assert!(invalid(levels(&[("A", 90.0)]), 100.5));
+        assert!(invalid(levels(&[("A", 90.0)]), -0.5));
+    }
+
+    #[test]
+    fn test_presets_are_valid() {
+        for preset in PRESETS {
+            let scale = GradingScale::from_preset(preset).unwrap();
+            assert_eq!(scale.name, preset);
+            let checked = GradingScale::new(scale.name.clone(), scale.levels.clone(), scale.pass_score).unwrap();
+            assert_eq!(checked, scale);
+        }
+        assert!(matches!(GradingScale::from_preset("gpa"), Err(GradeError::UnknownGradingScale(name)) if name == "gpa"));
+        assert_eq!(GradingScale::default(), GradingScale::letter());
+    }
+
+    #[test]
+    fn test_level_for() {
+        let scale = GradingScale::letter_plus_minus();
+        assert_eq!(scale.level_for(100.0), "A");
+        assert_eq!(scale.level_for(92.9), "A-");
+        assert_eq!(scale.level_for(87.0), "B+");
+        assert_eq!(scale.level_for(60.0), "D-");
+        assert_eq!(scale.level_for(0.0), "F");
+        // 低于所有最低分时属于最后一个等级
+        let scale = GradingScale::new("s".to_string(), levels(&[("好", 80.0), ("差", 50.0)]), 50.0).unwrap();
+        assert_eq!(scale.level_for(30.0), "差");
+    }
+
+    #[test]
+    fn test_is_passing() {
+        let scale = GradingScale::new("s".to_string(), levels(&[("A", 90.0), ("F", 0.0)]), 75.0).unwrap();
+        assert!(scale.is_passing(75.0));
+        assert!(!scale.is_passing(74.9));
+    }
+
+    #[test]
+    fn test_deserialize_validates() {
+        let scale = GradingScale::five_level();
+        let json = serde_json::to_string(&scale).unwrap();
+        assert_eq!(serde_json::from_str::<GradingScale>(&json).unwrap(), scale);
+
+        let duplicate = r#"{"name": "s", "levels": [{"name": "A", "min_score": 90.0}, {"name": "A", "min_score": 60.0}], "pass_score": 60.0}"#;
+        assert!(serde_json::from_str::<GradingScale>(duplicate).is_err());
+        let pass_score = r#"{"name": "s", "levels": [{"name": "A", "min_score": 90.0}], "pass_score": 200.0}"#;
+        assert!(serde_json::from_str::<GradingScale>(pass_score).is_err());
+        // 读入时按最低分重新排序
+        let unsorted = r#"{"name": "s", "levels": [{"name": "B", "min_score": 0.0}, {"name": "A", "min_score": 90.0}], "pass_score": 60.0}"#;
+        assert_eq!(serde_json::from_str::<GradingScale>(unsorted).unwrap().level_names(), ["A", "B"]);
+    }
+}
@@ -1,8 +1,11 @@
 use eframe::egui;
-use crate::system::GradeManagementSystem;
-use crate::student::Student;
-use crate::grade::Grade;
-use crate::io::FileIO;
+use crate::fonts;
+use grade_management_system::system::GradeManagementSystem;
+use grade_management_system::student::Student;
+use grade_management_system::grade::{self, Grade};
+use grade_management_system::gpa::GpaScale;
+use grade_management_system::grading::{self, GradingScale};
+use grade_management_system::io::{FileIO, DEFAULT_DATA_FILE};
 use std::sync::Arc;
 use std::sync::Mutex;
 
@@ -15,6 +18,7 @@ pub struct GradeManagementApp {
     selected_subject: String,
     new_student: NewStudentState,
     new_grade: NewGradeState,
+    new_course: NewCourseState,
     message: Option<String>,
 }
 
@@ -26,6 +30,13 @@ struct NewStudentState {
     major: String,
 }
 
+#[derive(Default)]
+struct NewCourseState {
+    name: String,
+    credits: String,
+    grading_scale: Option<Option<String>>,  // None 表示保持课程原有的等级制，Some(None) 表示改用全局等级制
+}
+
 #[derive(Default)]
 struct NewGradeState {
     student_id: String,
@@ -36,32 +47,17 @@ struct NewGradeState {
 
 impl GradeManagementApp {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
-        // 配置字体
-        let mut fonts = egui::FontDefinitions::default();
-        
-        // 添加中文字体
-        fonts.font_data.insert(
-            "microsoft_yahei".to_owned(),
-            egui::FontData::from_static(include_bytes!("/System/Library/Fonts/PingFang.ttc")),
-        );
-
-        // 将中文字体设置为优先字体
-        fonts.families
-            .get_mut(&egui::FontFamily::Proportional)
-            .unwrap()
-            .insert(0, "microsoft_yahei".to_owned());
-
-        // 设置等宽字体
-        fonts.families
-            .get_mut(&egui::FontFamily::Monospace)
-            .unwrap()
-            .insert(0, "microsoft_yahei".to_owned());
-
-        // 应用字体配置
-        cc.egui_ctx.set_fonts(fonts);
+        // 配置中文字体，找不到时使用默认字体并提示
+        let font_warning = match fonts::install_cjk_font(&cc.egui_ctx) {
+            Ok(_) => None,
+            Err(e) => {
+                eprintln!("警告: {}，将使用默认字体", e);
+                Some(format!("警告: {}，中文可能无法正常显示", e))
+            }
+        };
 
         let system = GradeManagementSystem::new();
-        let file_io = FileIO::new("grades.json".to_string());
+        let file_io = FileIO::new(DEFAULT_DATA_FILE.to_string());
 
         // 尝试加载保存的数据
         if let Ok(loaded_system) = file_io.load_from_file() {
@@ -73,7 +69,8 @@ impl GradeManagementApp {
                 selected_subject: String::new(),
                 new_student: NewStudentState::default(),
                 new_grade: NewGradeState::default(),
-                message: None,
+                new_course: NewCourseState::default(),
+                message: font_warning,
             }
         } else {
             GradeManagementApp {
@@ -84,7 +81,8 @@ impl GradeManagementApp {
                 selected_subject: String::new(),
                 new_student: NewStudentState::default(),
                 new_grade: NewGradeState::default(),
-                message: None,
+                new_course: NewCourseState::default(),
+                message: font_warning,
             }
         }
     }
@@ -205,6 +203,106 @@ impl GradeManagementApp {
         });
     }
 
+    fn render_course_management(&mut self, ui: &mut egui::Ui) {
+        ui.heading("课程管理");
+
+        // 添加课程或修改学分
+        ui.group(|ui| {
+            ui.label("设置课程学分和等级制（未设置的科目按 1 学分、全局等级制计算）");
+            ui.horizontal(|ui| {
+                ui.label("课程:");
+                ui.text_edit_singleline(&mut self.new_course.name);
+            });
+            ui.horizontal(|ui| {
+                ui.label("学分:");
+                ui.text_edit_singleline(&mut self.new_course.credits);
+            });
+            let selected = match &self.new_course.grading_scale {
+                None => "保持不变",
+                Some(None) => "使用全局等级制",
+                Some(Some(preset)) => preset.as_str(),
+            };
+            egui::ComboBox::from_label("等级制")
+                .selected_text(selected.to_string())
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.new_course.grading_scale, None, "保持不变");
+                    ui.selectable_value(&mut self.new_course.grading_scale, Some(None), "使用全局等级制");
+                    for preset in grading::PRESETS {
+                        ui.selectable_value(&mut self.new_course.grading_scale, Some(Some(preset.to_string())), preset);
+                    }
+                });
+
+            if ui.button("设置").clicked() {
+                if let Ok(credits) = self.new_course.credits.parse::<f32>() {
+                    let name = self.new_course.name.clone();
+                    // 只在选择了等级制时才修改，修改学分时保留课程原有的（包括自定义的）等级制
+                    let scale = self
+                        .new_course
+                        .grading_scale
+                        .clone()
+                        .map(|preset| preset.as_deref().map(GradingScale::from_preset).transpose())
+                        .transpose();
+                    let result = scale.and_then(|scale| {
+                        let mut system = self.system.lock().unwrap();
+                        system.set_course_credits(&name, credits)?;
+                        if let Some(scale) = scale {
+                            system.set_course_grading_scale(&name, scale);
+                        }
+                        Ok(())
+                    });
+                    match result {
+                        Ok(()) => {
+                            self.show_message("课程设置成功".to_string());
+                            self.new_course = NewCourseState::default();
+                        }
+                        Err(e) => self.show_message(format!("设置失败: {}", e)),
+                    }
+                } else {
+                    self.show_message("学分格式不正确".to_string());
+                }
+            }
+        });
+
+        // 显示课程列表、绩点算法和全局等级制
+        ui.group(|ui| {
+            ui.label("课程列表");
+            let (courses, current_scale, current_grading) = {
+                let system = self.system.lock().unwrap();
+                let courses = system.get_all_courses().into_iter().map(|c| c.to_string()).collect::<Vec<_>>();
+                (courses, system.gpa_scale(), system.grading_scale().name.clone())
+            };
+            for course in courses {
+                ui.label(course);
+            }
+
+            let mut scale = current_scale;
+            egui::ComboBox::from_label("绩点算法")
+                .selected_text(scale.to_string())
+                .show_ui(ui, |ui| {
+                    for option in GpaScale::ALL {
+                        ui.selectable_value(&mut scale, option, option.to_string());
+                    }
+                });
+            if scale != current_scale {
+                self.system.lock().unwrap().set_gpa_scale(scale);
+            }
+
+            let mut grading = current_grading.clone();
+            egui::ComboBox::from_label("全局等级制")
+                .selected_text(grading.clone())
+                .show_ui(ui, |ui| {
+                    for preset in grading::PRESETS {
+                        ui.selectable_value(&mut grading, preset.to_string(), preset);
+                    }
+                });
+            if grading != current_grading {
+                if let Ok(scale) = GradingScale::from_preset(&grading) {
+                    self.system.lock().unwrap().set_grading_scale(scale);
+                }
+            }
+        });
+    }
+
     fn render_grade_management(&mut self, ui: &mut egui::Ui) {
         ui.heading("成绩管理");
 
@@ -229,7 +327,7 @@ impl GradeManagementApp {
             });
 
             if ui.button("添加").clicked() {
-                if let Ok(score) = self.new_grade.score.parse::<f32>() {
+                if let Ok(score) = grade::parse_score(&self.new_grade.score) {
                     let grade = Grade::new(
                         self.new_grade.student_id.clone(),
                         self.new_grade.subject.clone(),
@@ -249,7 +347,7 @@ impl GradeManagementApp {
                         Err(e) => self.show_message(format!("添加失败: {}", e)),
                     }
                 } else {
-                    self.show_message("成绩格式不正确".to_string());
+                    self.show_message("成绩必须是 0 到 100 之间的数".to_string());
                 }
             }
         });
@@ -258,22 +356,53 @@ impl GradeManagementApp {
         if !self.selected_student_id.is_empty() {
             ui.group(|ui| {
                 ui.label(format!("学号 {} 的成绩列表", self.selected_student_id));
-                let grades = {
+                let (grades, summary) = {
                     let system = self.system.lock().unwrap();
-                    system.get_student_grades(&self.selected_student_id)
+                    let student_id = &self.selected_student_id;
+                    let grades = system.get_student_grades(student_id)
                         .iter()
-                        .map(|g| (g.semester.clone(), g.subject.clone(), g.score, g.get_grade_level().to_string()))
-                        .collect::<Vec<_>>()
+                        .map(|g| (
+                            g.semester.clone(),
+                            g.subject.clone(),
+                            g.score,
+                            system.grade_level(g).to_string(),
+                            system.credits_for(&g.subject),
+                        ))
+                        .collect::<Vec<_>>();
+
+                    // 各学期和累计的加权平均成绩与绩点
+                    let mut summary = Vec::new();
+                    for semester in system.get_student_semesters(student_id) {
+                        if let (Some(average), Some(gpa)) = (
+                            system.calculate_semester_average(student_id, &semester),
+                            system.calculate_semester_gpa(student_id, &semester),
+                        ) {
+                            summary.push(format!("{} 学期: 加权平均 {:.1}, 绩点 {:.2}", semester, average, gpa));
+                        }
+                    }
+                    if let (Some(average), Some(gpa)) = (
+                        system.calculate_cumulative_average(student_id),
+                        system.calculate_cumulative_gpa(student_id),
+                    ) {
+                        summary.push(format!(
+                            "累计: 加权平均 {:.1}, 绩点 {:.2}（{}）",
+                            average, gpa, system.gpa_scale()
+                        ));
+                    }
+                    (grades, summary)
                 };
                 
-                for (semester, subject, score, level) in grades {
+                for (semester, subject, score, level, credits) in grades {
                     ui.horizontal(|ui| {
                         ui.label(format!(
-                            "学期: {}, 科目: {}, 成绩: {}, 等级: {}",
-                            semester, subject, score, level
+                            "学期: {}, 科目: {}, 成绩: {}, 等级: {}, 学分: {}",
+                            semester, subject, score, level, credits
                         ));
                     });
                 }
+                for line in summary {
+                    ui.label(line);
+                }
             });
         }
     }
@@ -289,17 +418,20 @@ impl GradeManagementApp {
         });
 
         if !self.selected_semester.is_empty() && !self.selected_subject.is_empty() {
-            let stats_map = {
+            let (statistics, scale_name) = {
                 let system = self.system.lock().unwrap();
-                system.get_subject_statistics(&self.selected_subject, &self.selected_semester)
+                let statistics = system.get_subject_statistics(&self.selected_subject, &self.selected_semester);
+                (statistics, system.grading_scale_for(&self.selected_subject).name.clone())
             };
             
             ui.group(|ui| {
-                ui.label(format!("{} 学期 {} 科目成绩分布", self.selected_semester, self.selected_subject));
-                // 按固定顺序显示等级
-                for grade in ["A", "B", "C", "D", "F"] {
-                    let count = stats_map.get(grade).copied().unwrap_or(0);
-                    ui.label(format!("{} 等级: {} 人", grade, count));
+                ui.label(format!(
+                    "{} 学期 {} 科目成绩分布（{}）",
+                    self.selected_semester, self.selected_subject, scale_name
+                ));
+                // 按等级从高到低显示
+                for (level, count) in statistics {
+                    ui.label(format!("{} 等级: {} 人", level, count));
                 }
             });
         }
@@ -322,6 +454,8 @@ impl eframe::App for GradeManagementApp {
             egui::ScrollArea::vertical().show(ui, |ui| {
                 self.render_student_management(ui);
                 ui.add_space(10.0);
+                self.render_course_management(ui);
+                ui.add_space(10.0);
                 self.render_grade_management(ui);
                 ui.add_space(10.0);
                 self.render_statistics(ui);
@@ -2,14 +2,27 @@ use std::fs;
 use std::path::Path;
 use serde::{Serialize, Deserialize};
 use crate::student::Student;
-use crate::grade::Grade;
+use crate::grade::{parse_score, Grade};
+use crate::course::Course;
+use crate::gpa::GpaScale;
+use crate::grading::GradingScale;
 use crate::system::GradeManagementSystem;
+use crate::error::{GradeError, GradeResult};
+
+// 默认的数据文件
+pub const DEFAULT_DATA_FILE: &str = "grades.json";
 
 // 用于序列化的数据结构
 #[derive(Serialize, Deserialize)]
 struct SystemData {
     students: Vec<Student>,
     grades: Vec<Grade>,
+    #[serde(default)]
+    courses: Vec<Course>,       // 旧数据文件中没有课程定义
+    #[serde(default)]
+    gpa_scale: GpaScale,
+    #[serde(default)]
+    grading_scale: GradingScale,   // 旧数据文件使用 A-F 等级制
 }
 
 impl SystemData {
@@ -18,12 +31,21 @@ impl SystemData {
         SystemData {
             students: system.get_all_students().into_iter().cloned().collect(),
             grades: system.get_all_grades().into_iter().cloned().collect(),
+            courses: system.get_all_courses().into_iter().cloned().collect(),
+            gpa_scale: system.gpa_scale(),
+            grading_scale: system.grading_scale().clone(),
         }
     }
 
     // 转换为系统
     fn into_system(self) -> GradeManagementSystem {
         let mut system = GradeManagementSystem::new();
+        system.set_gpa_scale(self.gpa_scale);
+        system.set_grading_scale(self.grading_scale);
+        // 学分在读入时已经检查过
+        for course in self.courses {
+            let _ = system.set_course(course);
+        }
         // 先添加所有学生
         for student in self.students {
             let _ = system.add_student(student);
@@ -47,49 +69,41 @@ impl FileIO {
     }
 
     // 保存系统数据到文件
-    pub fn save_to_file(&self, system: &GradeManagementSystem) -> Result<(), String> {
+    pub fn save_to_file(&self, system: &GradeManagementSystem) -> GradeResult<()> {
         let data = SystemData::from_system(system);
-        let json = serde_json::to_string_pretty(&data)
-            .map_err(|e| format!("序列化数据失败: {}", e))?;
-        
-        fs::write(&self.file_path, json)
-            .map_err(|e| format!("写入文件失败: {}", e))?;
-        
+        let json = serde_json::to_string_pretty(&data)?;
+        fs::write(&self.file_path, json)?;
         Ok(())
     }
 
     // 从文件加载系统数据
-    pub fn load_from_file(&self) -> Result<GradeManagementSystem, String> {
+    pub fn load_from_file(&self) -> GradeResult<GradeManagementSystem> {
         if !Path::new(&self.file_path).exists() {
             return Ok(GradeManagementSystem::new());
         }
 
-        let json = fs::read_to_string(&self.file_path)
-            .map_err(|e| format!("读取文件失败: {}", e))?;
-        
-        let data: SystemData = serde_json::from_str(&json)
-            .map_err(|e| format!("解析数据失败: {}", e))?;
-        
+        let json = fs::read_to_string(&self.file_path)?;
+        let data: SystemData = serde_json::from_str(&json)?;
+
         Ok(data.into_system())
     }
 
-    // 导出成绩单到CSV文件
+    // 导出成绩单到CSV文件。每条成绩之后附上各学期的加权平均成绩和绩点，
+    // 导出全部学期时再附上累计的一行；这些汇总行的学号为空，导入时跳过
     pub fn export_transcript_to_csv(
         &self,
         system: &GradeManagementSystem,
         student_id: &str,
         semester: Option<&str>,
         output_path: &str,
-    ) -> Result<(), String> {
-        let mut wtr = csv::Writer::from_path(output_path)
-            .map_err(|e| format!("创建CSV文件失败: {}", e))?;
+    ) -> GradeResult<()> {
+        let student = system.get_student(student_id)
+            .ok_or_else(|| GradeError::StudentNotFound(student_id.to_string()))?;
 
-        // 写入表头
-        wtr.write_record(&["学号", "姓名", "学期", "科目", "成绩", "等级"])
-            .map_err(|e| format!("写入CSV表头失败: {}", e))?;
+        let mut wtr = csv::Writer::from_path(output_path)?;
 
-        let student = system.get_student(student_id)
-            .ok_or_else(|| format!("未找到学号为 {} 的学生", student_id))?;
+        // 写入表头
+        wtr.write_record(["学号", "姓名", "学期", "科目", "成绩", "等级", "学分", "绩点"])?;
 
         let grades = if let Some(sem) = semester {
             system.get_student_semester_grades(student_id, sem)
@@ -98,19 +112,45 @@ impl FileIO {
         };
 
         // 写入成绩记录
-        for grade in grades {
-            wtr.write_record(&[
+        let scale = system.gpa_scale();
+        for grade in &grades {
+            wtr.write_record([
                 &student.id,
                 &student.name,
                 &grade.semester,
                 &grade.subject,
                 &grade.score.to_string(),
-                grade.get_grade_level(),
-            ])
-            .map_err(|e| format!("写入CSV记录失败: {}", e))?;
+                system.grade_level(grade),
+                &system.credits_for(&grade.subject).to_string(),
+                &format!("{:.2}", scale.points(grade.score)),
+            ])?;
         }
 
-        wtr.flush().map_err(|e| format!("保存CSV文件失败: {}", e))?;
+        // 写入汇总行
+        let semesters = match semester {
+            Some(sem) => vec![sem.to_string()],
+            None => system.get_student_semesters(student_id),
+        };
+        for sem in &semesters {
+            let semester_grades = system.get_student_semester_grades(student_id, sem);
+            if let (Some(average), Some(gpa)) = (
+                system.calculate_semester_average(student_id, sem),
+                system.calculate_semester_gpa(student_id, sem),
+            ) {
+                let credits = system.total_credits(&semester_grades);
+                write_summary(&mut wtr, sem, "学期加权平均", average, credits, gpa)?;
+            }
+        }
+        if semester.is_none() {
+            if let (Some(average), Some(gpa)) = (
+                system.calculate_cumulative_average(student_id),
+                system.calculate_cumulative_gpa(student_id),
+            ) {
+                write_summary(&mut wtr, "", "累计加权平均", average, system.total_credits(&grades), gpa)?;
+            }
+        }
+
+        wtr.flush()?;
         Ok(())
     }
 
@@ -119,21 +159,23 @@ impl FileIO {
         &self,
         system: &mut GradeManagementSystem,
         file_path: &str,
-    ) -> Result<(), String> {
-        let mut rdr = csv::Reader::from_path(file_path)
-            .map_err(|e| format!("打开CSV文件失败: {}", e))?;
+    ) -> GradeResult<()> {
+        let mut rdr = csv::Reader::from_path(file_path)?;
 
         for result in rdr.records() {
-            let record = result.map_err(|e| format!("读取CSV记录失败: {}", e))?;
+            let record = result?;
+            // 跳过导出时附加的汇总行
+            if record.get(0).is_some_and(|id| id.is_empty()) {
+                continue;
+            }
             if record.len() < 6 {
-                return Err("CSV记录格式不正确".to_string());
+                let line = record.position().map(|p| p.line()).unwrap_or_default();
+                return Err(GradeError::InvalidRecord { line });
             }
 
             let student_id = &record[0];
             let subject = &record[3];
-            let score = record[4]
-                .parse::<f32>()
-                .map_err(|_| "成绩格式不正确".to_string())?;
+            let score = parse_score(&record[4])?;
             let semester = &record[2];
 
             let grade = Grade::new(
@@ -143,10 +185,182 @@ impl FileIO {
                 semester.to_string(),
             );
 
-            system.add_grade(grade)
-                .map_err(|e| format!("导入成绩失败: {}", e))?;
+            system.add_grade(grade)?;
         }
 
         Ok(())
     }
 }
+
+// 成绩单中的汇总行，学号和姓名留空
+fn write_summary<W: std::io::Write>(
+    wtr: &mut csv::Writer<W>,
+    semester: &str,
+    label: &str,
+    average: f32,
+    credits: f32,
+    gpa: f32,
+) -> GradeResult<()> {
+    wtr.write_record([
+        "",
+        "",
+        semester,
+        label,
+        &format!("{:.2}", average),
+        "",
+        &credits.to_string(),
+        &format!("{:.2}", gpa),
+    ])?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // 测试用的临时文件，离开作用域时删除
+    pub(crate) struct TempFile(PathBuf);
+
+    impl TempFile {
+        pub(crate) fn new(extension: &str) -> Self {
+            static COUNTER: AtomicUsize = AtomicUsize::new(0);
+            let name = format!(
+                "grade_management_test_{}_{}.{}",
+                std::process::id(),
+                COUNTER.fetch_add(1, Ordering::Relaxed),
+                extension
+            );
+            TempFile(std::env::temp_dir().join(name))
+        }
+
+        pub(crate) fn path(&self) -> &str {
+            self.0.to_str().unwrap()
+        }
+    }
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = fs::remove_file(&self.0);
+        }
+    }
+
+    fn sample_system() -> GradeManagementSystem {
+        let mut system = GradeManagementSystem::new();
+        let student = Student::new("1".to_string(), "张三".to_string(), "1班".to_string(), "计算机".to_string());
+        system.add_student(student).unwrap();
+        system.set_course_credits("数学", 4.0).unwrap();
+        for (subject, score, semester) in [("数学", 90.0, "2024春"), ("英语", 70.0, "2024春"), ("物理", 80.0, "2024秋")] {
+            let grade = Grade::new("1".to_string(), subject.to_string(), score, semester.to_string());
+            system.add_grade(grade).unwrap();
+        }
+        system
+    }
+
+    #[test]
+    fn test_load_missing_file() {
+        let file = TempFile::new("json");
+        let system = FileIO::new(file.path().to_string()).load_from_file().unwrap();
+        assert!(system.get_all_students().is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let file = TempFile::new("json");
+        let file_io = FileIO::new(file.path().to_string());
+        let mut system = sample_system();
+        system.set_gpa_scale(GpaScale::Pku);
+        system.set_grading_scale(GradingScale::five_level());
+        system.set_course_grading_scale("英语", Some(GradingScale::pass_fail()));
+        file_io.save_to_file(&system).unwrap();
+
+        let loaded = file_io.load_from_file().unwrap();
+        assert_eq!(loaded.get_all_grades().len(), 3);
+        assert_eq!(loaded.credits_for("数学"), 4.0);
+        assert_eq!(loaded.gpa_scale(), GpaScale::Pku);
+        assert_eq!(loaded.grading_scale(), &GradingScale::five_level());
+        assert_eq!(loaded.grading_scale_for("英语"), &GradingScale::pass_fail());
+        assert_eq!(loaded.grading_scale_for("数学"), &GradingScale::five_level());
+        assert_eq!(loaded.calculate_cumulative_gpa("1"), system.calculate_cumulative_gpa("1"));
+    }
+
+    // 加入课程和绩点算法之前的数据文件只有学生和成绩
+    #[test]
+    fn test_load_old_file() {
+        let file = TempFile::new("json");
+        let json = r#"{
+            "students": [{"id": "1", "name": "张三", "class": "1班", "major": "计算机"}],
+            "grades": [{"student_id": "1", "subject": "数学", "score": 85.0, "semester": "2024春"}]
+        }"#;
+        fs::write(file.path(), json).unwrap();
+
+        let system = FileIO::new(file.path().to_string()).load_from_file().unwrap();
+        assert_eq!(system.get_student("1").unwrap().name, "张三");
+        assert_eq!(system.get_all_grades().len(), 1);
+        assert!(system.get_all_courses().is_empty());
+        assert_eq!(system.gpa_scale(), GpaScale::FourPoint);
+        assert_eq!(system.grading_scale(), &GradingScale::letter());
+        assert_eq!(system.calculate_cumulative_average("1"), Some(85.0));
+    }
+
+    #[test]
+    fn test_load_invalid_file() {
+        let file = TempFile::new("json");
+        fs::write(file.path(), "{").unwrap();
+        assert!(matches!(FileIO::new(file.path().to_string()).load_from_file(), Err(GradeError::Parse(_))));
+
+        // 学分不正确的课程使整个文件加载失败，而不是悄悄按默认学分计算
+        for credits in ["0.0", "-2.0"] {
+            let json = format!(r#"{{"students": [], "grades": [], "courses": [{{"name": "数学", "credits": {}}}]}}"#, credits);
+            fs::write(file.path(), json).unwrap();
+            let result = FileIO::new(file.path().to_string()).load_from_file();
+            assert!(matches!(result, Err(GradeError::Parse(e)) if e.to_string().contains("学分")), "{}", credits);
+        }
+
+        // 手工改坏的等级制不能绕过校验
+        let json = r#"{"students": [], "grades": [], "grading_scale": {"name": "s", "levels": [], "pass_score": 60.0}}"#;
+        fs::write(file.path(), json).unwrap();
+        assert!(matches!(FileIO::new(file.path().to_string()).load_from_file(), Err(GradeError::Parse(_))));
+    }
+
+    // 导出的成绩单带有学期和累计的汇总行，再导入时只导入成绩
+    #[test]
+    fn test_export_then_import_skips_summary_rows() {
+        let csv_file = TempFile::new("csv");
+        let file_io = FileIO::new(String::new());
+        let system = sample_system();
+        file_io.export_transcript_to_csv(&system, "1", None, csv_file.path()).unwrap();
+
+        let exported = fs::read_to_string(csv_file.path()).unwrap();
+        // 表头、3 条成绩、2 个学期的汇总和累计汇总
+        assert_eq!(exported.lines().count(), 7);
+        assert!(exported.contains("累计加权平均"));
+
+        let mut imported = GradeManagementSystem::new();
+        imported.add_student(system.get_student("1").unwrap().clone()).unwrap();
+        file_io.import_grades_from_csv(&mut imported, csv_file.path()).unwrap();
+        assert_eq!(imported.get_all_grades().len(), 3);
+        assert_eq!(imported.get_student_semester_grades("1", "2024秋")[0].score, 80.0);
+    }
+
+    #[test]
+    fn test_import_rejects_bad_rows() {
+        let csv_file = TempFile::new("csv");
+        let file_io = FileIO::new(String::new());
+        let mut system = sample_system();
+
+        fs::write(csv_file.path(), "学号,姓名,学期,科目,成绩,等级\n1,张三,2025春,数学,120,A\n").unwrap();
+        assert!(matches!(
+            file_io.import_grades_from_csv(&mut system, csv_file.path()),
+            Err(GradeError::InvalidScore(score)) if score == "120"
+        ));
+
+        fs::write(csv_file.path(), "学号,姓名,学期\n1,张三,2025春\n").unwrap();
+        assert!(matches!(
+            file_io.import_grades_from_csv(&mut system, csv_file.path()),
+            Err(GradeError::InvalidRecord { line: 2 })
+        ));
+        assert_eq!(system.get_all_grades().len(), 3);
+    }
+}
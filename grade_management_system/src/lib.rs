@@ -0,0 +1,19 @@
+pub mod student;
+pub mod grade;
+pub mod course;
+pub mod gpa;
+pub mod grading;
+pub mod system;
+pub mod io;
+pub mod error;
+pub mod messages;
+pub mod cli;
+
+pub use course::Course;
+pub use error::{GradeError, GradeResult};
+pub use gpa::GpaScale;
+pub use grading::{GradeLevel, GradingScale};
+pub use grade::Grade;
+pub use io::FileIO;
+pub use student::Student;
+pub use system::GradeManagementSystem;
@@ -1,14 +1,48 @@
-mod student;
-mod grade;
-mod system;
-mod io;
+use grade_management_system::cli;
+use grade_management_system::error::GradeError;
+
+#[cfg(feature = "gui")]
+mod fonts;
+#[cfg(feature = "gui")]
 mod gui;
 
-fn main() -> eframe::Result<()> {
+fn main() {
+    // 带参数时以命令行模式运行，便于脚本和批处理调用
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if !args.is_empty() {
+        match cli::run(&args) {
+            Ok(output) => println!("{}", output),
+            Err(e) => {
+                // 错误在这里转换为给用户看的文字，命令写错时附上用法说明
+                eprintln!("错误: {}", e);
+                if matches!(e, GradeError::MissingCommand | GradeError::UnknownCommand(_)) {
+                    eprintln!("\n{}", cli::USAGE);
+                }
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if let Err(e) = run_gui() {
+        eprintln!("错误: {}", e);
+        std::process::exit(1);
+    }
+}
+
+#[cfg(feature = "gui")]
+fn run_gui() -> Result<(), String> {
     let native_options = eframe::NativeOptions::default();
     eframe::run_native(
         "成绩管理系统",
         native_options,
         Box::new(|cc| Box::new(gui::GradeManagementApp::new(cc)))
     )
+    .map_err(|e| e.to_string())
+}
+
+// 没有启用 gui 特性时只提供命令行模式
+#[cfg(not(feature = "gui"))]
+fn run_gui() -> Result<(), String> {
+    Err(format!("未启用图形界面，请使用命令行模式\n\n{}", cli::USAGE))
 }
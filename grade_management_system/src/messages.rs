@@ -0,0 +1,36 @@
+// 显示给用户的文字，与错误类型本身分开，方便以后替换为其他语言
+use crate::error::GradeError;
+
+// 错误的中文描述
+pub fn error(error: &GradeError) -> String {
+    match error {
+        GradeError::StudentExists(id) => format!("学号 {} 已存在", id),
+        GradeError::StudentNotFound(id) => format!("学号 {} 不存在", id),
+        GradeError::GradeExists { student_id, subject, semester } => {
+            format!("学号 {} 的 {} 学期 {} 科目成绩已存在", student_id, semester, subject)
+        }
+        GradeError::GradeNotFound { student_id, subject, semester } => {
+            format!("未找到学号 {} 的 {} 学期 {} 科目成绩", student_id, semester, subject)
+        }
+        GradeError::CourseNotFound(name) => format!("课程 {} 不存在", name),
+        GradeError::InvalidCredits(credits) => format!("学分必须是大于 0 的数: {}", credits),
+        GradeError::UnknownGpaScale(scale) => format!("无法识别的绩点算法: {}（可选 4.0、5.0、pku）", scale),
+        GradeError::UnknownGradingScale(name) => {
+            format!("没有名为 {} 的等级制（可选 letter、letter-pm、five-level、pass-fail）", name)
+        }
+        GradeError::InvalidGradingScale(name) => {
+            format!("等级制 {} 的定义不正确：至少需要一个等级，等级名称和最低分都不能重复，最低分和及格线都要在 0 到 100 之间", name)
+        }
+        GradeError::InvalidScore(score) => format!("成绩必须是 0 到 100 之间的数: {}", score),
+        GradeError::InvalidLevel(level) => format!("等级定义应为 等级=最低分: {}", level),
+        GradeError::InvalidRecord { line } => format!("CSV 第 {} 行记录格式不正确", line),
+        GradeError::MissingCommand => "缺少命令".to_string(),
+        GradeError::UnknownCommand(command) => format!("无法识别的命令: {}", command),
+        GradeError::MissingOption(name) => format!("选项 {} 缺少参数", name),
+        GradeError::UnsupportedOption(name) => format!("该命令不支持选项 {}", name),
+        GradeError::DuplicateOption(name) => format!("选项 {} 只能出现一次", name),
+        GradeError::Io(e) => format!("读写文件失败: {}", e),
+        GradeError::Parse(e) => format!("解析数据失败: {}", e),
+        GradeError::Csv(e) => format!("读写 CSV 文件失败: {}", e),
+    }
+}
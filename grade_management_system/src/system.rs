@@ -1,35 +1,39 @@
 use std::collections::HashMap;
 use crate::student::Student;
 use crate::grade::Grade;
+use crate::course::{check_credits, Course, DEFAULT_CREDITS};
+use crate::gpa::GpaScale;
+use crate::grading::GradingScale;
+use crate::error::{GradeError, GradeResult};
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 pub struct GradeManagementSystem {
     students: HashMap<String, Student>,  // 学号 -> 学生信息
     grades: Vec<Grade>,                 // 所有成绩记录
+    courses: HashMap<String, Course>,   // 课程名称 -> 课程信息
+    gpa_scale: GpaScale,                // 绩点的计算方法
+    grading_scale: GradingScale,        // 全局等级制，课程可以单独设置
 }
 
 impl GradeManagementSystem {
     // 创建新的成绩管理系统
     pub fn new() -> Self {
-        GradeManagementSystem {
-            students: HashMap::new(),
-            grades: Vec::new(),
-        }
+        Self::default()
     }
 
     // 添加学生
-    pub fn add_student(&mut self, student: Student) -> Result<(), String> {
+    pub fn add_student(&mut self, student: Student) -> GradeResult<()> {
         if self.students.contains_key(&student.id) {
-            return Err(format!("学号 {} 已存在", student.id));
+            return Err(GradeError::StudentExists(student.id));
         }
         self.students.insert(student.id.clone(), student);
         Ok(())
     }
 
     // 删除学生
-    pub fn remove_student(&mut self, student_id: &str) -> Result<(), String> {
+    pub fn remove_student(&mut self, student_id: &str) -> GradeResult<()> {
         if !self.students.contains_key(student_id) {
-            return Err(format!("学号 {} 不存在", student_id));
+            return Err(GradeError::StudentNotFound(student_id.to_string()));
         }
         self.students.remove(student_id);
         // 同时删除该学生的所有成绩记录
@@ -38,9 +42,9 @@ impl GradeManagementSystem {
     }
 
     // 添加成绩
-    pub fn add_grade(&mut self, grade: Grade) -> Result<(), String> {
+    pub fn add_grade(&mut self, grade: Grade) -> GradeResult<()> {
         if !self.students.contains_key(&grade.student_id) {
-            return Err(format!("学号 {} 不存在", grade.student_id));
+            return Err(GradeError::StudentNotFound(grade.student_id));
         }
         // 检查是否已存在相同学期相同科目的成绩
         if self.grades.iter().any(|g| 
@@ -48,17 +52,18 @@ impl GradeManagementSystem {
             g.subject == grade.subject && 
             g.semester == grade.semester
         ) {
-            return Err(format!(
-                "学号 {} 的 {} 学期 {} 科目成绩已存在",
-                grade.student_id, grade.semester, grade.subject
-            ));
+            return Err(GradeError::GradeExists {
+                student_id: grade.student_id,
+                subject: grade.subject,
+                semester: grade.semester,
+            });
         }
         self.grades.push(grade);
         Ok(())
     }
 
     // 更新成绩
-    pub fn update_grade(&mut self, student_id: &str, subject: &str, semester: &str, new_score: f32) -> Result<(), String> {
+    pub fn update_grade(&mut self, student_id: &str, subject: &str, semester: &str, new_score: f32) -> GradeResult<()> {
         if let Some(grade) = self.grades.iter_mut().find(|g| 
             g.student_id == student_id && 
             g.subject == subject && 
@@ -67,10 +72,11 @@ impl GradeManagementSystem {
             grade.update_score(new_score);
             Ok(())
         } else {
-            Err(format!(
-                "未找到学号 {} 的 {} 学期 {} 科目成绩",
-                student_id, semester, subject
-            ))
+            Err(GradeError::GradeNotFound {
+                student_id: student_id.to_string(),
+                subject: subject.to_string(),
+                semester: semester.to_string(),
+            })
         }
     }
 
@@ -95,14 +101,139 @@ impl GradeManagementSystem {
             .collect()
     }
 
-    // 计算学生某学期的平均成绩
-    pub fn calculate_semester_average(&self, student_id: &str, semester: &str) -> Option<f32> {
-        let semester_grades: Vec<&Grade> = self.get_student_semester_grades(student_id, semester);
-        if semester_grades.is_empty() {
-            return None;
+    // 添加或修改课程
+    pub fn set_course(&mut self, course: Course) -> GradeResult<()> {
+        check_credits(course.credits)?;
+        self.courses.insert(course.name.clone(), course);
+        Ok(())
+    }
+
+    // 修改课程学分，保留课程的等级制；课程不存在时新建
+    pub fn set_course_credits(&mut self, name: &str, credits: f32) -> GradeResult<()> {
+        let mut course = Course::new(name.to_string(), credits);
+        course.grading_scale = self.courses.get(name).and_then(|course| course.grading_scale.clone());
+        self.set_course(course)
+    }
+
+    // 设置课程的等级制，None 表示使用全局等级制；课程不存在时按默认学分新建
+    pub fn set_course_grading_scale(&mut self, name: &str, scale: Option<GradingScale>) {
+        self.courses
+            .entry(name.to_string())
+            .or_insert_with(|| Course::new(name.to_string(), DEFAULT_CREDITS))
+            .grading_scale = scale;
+    }
+
+    // 删除课程，该课程的成绩保留，之后按默认学分计算
+    pub fn remove_course(&mut self, name: &str) -> GradeResult<()> {
+        self.courses
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| GradeError::CourseNotFound(name.to_string()))
+    }
+
+    // 获取课程信息
+    pub fn get_course(&self, name: &str) -> Option<&Course> {
+        self.courses.get(name)
+    }
+
+    // 获取所有课程，按名称排序
+    pub fn get_all_courses(&self) -> Vec<&Course> {
+        let mut courses: Vec<&Course> = self.courses.values().collect();
+        courses.sort_by(|a, b| a.name.cmp(&b.name));
+        courses
+    }
+
+    // 某科目的学分，没有定义课程时为默认学分
+    pub fn credits_for(&self, subject: &str) -> f32 {
+        self.courses.get(subject).map_or(DEFAULT_CREDITS, |course| course.credits)
+    }
+
+    // 当前使用的绩点算法
+    pub fn gpa_scale(&self) -> GpaScale {
+        self.gpa_scale
+    }
+
+    pub fn set_gpa_scale(&mut self, scale: GpaScale) {
+        self.gpa_scale = scale;
+    }
+
+    // 全局等级制
+    pub fn grading_scale(&self) -> &GradingScale {
+        &self.grading_scale
+    }
+
+    pub fn set_grading_scale(&mut self, scale: GradingScale) {
+        self.grading_scale = scale;
+    }
+
+    // 某科目使用的等级制：课程单独设置的优先，否则为全局等级制
+    pub fn grading_scale_for(&self, subject: &str) -> &GradingScale {
+        self.courses
+            .get(subject)
+            .and_then(|course| course.grading_scale.as_ref())
+            .unwrap_or(&self.grading_scale)
+    }
+
+    // 成绩的等级
+    pub fn grade_level(&self, grade: &Grade) -> &str {
+        grade.get_grade_level(self.grading_scale_for(&grade.subject))
+    }
+
+    // 成绩是否及格
+    pub fn is_passing(&self, grade: &Grade) -> bool {
+        grade.is_passing(self.grading_scale_for(&grade.subject))
+    }
+
+    // 按学分加权的平均值，没有成绩时为 None
+    fn weighted_mean(&self, grades: &[&Grade], value: impl Fn(&Grade) -> f32) -> Option<f32> {
+        let (sum, credits) = grades.iter().fold((0.0, 0.0), |(sum, credits), grade| {
+            let weight = self.credits_for(&grade.subject);
+            (sum + value(grade) * weight, credits + weight)
+        });
+        if credits > 0.0 {
+            Some(sum / credits)
+        } else {
+            None
         }
-        let sum: f32 = semester_grades.iter().map(|grade| grade.score).sum();
-        Some(sum / semester_grades.len() as f32)
+    }
+
+    // 计算学生某学期按学分加权的平均成绩
+    pub fn calculate_semester_average(&self, student_id: &str, semester: &str) -> Option<f32> {
+        self.weighted_mean(&self.get_student_semester_grades(student_id, semester), |grade| grade.score)
+    }
+
+    // 计算学生所有学期按学分加权的平均成绩
+    pub fn calculate_cumulative_average(&self, student_id: &str) -> Option<f32> {
+        self.weighted_mean(&self.get_student_grades(student_id), |grade| grade.score)
+    }
+
+    // 计算学生某学期的绩点
+    pub fn calculate_semester_gpa(&self, student_id: &str, semester: &str) -> Option<f32> {
+        let scale = self.gpa_scale;
+        self.weighted_mean(&self.get_student_semester_grades(student_id, semester), |grade| scale.points(grade.score))
+    }
+
+    // 计算学生所有学期的累计绩点
+    pub fn calculate_cumulative_gpa(&self, student_id: &str) -> Option<f32> {
+        let scale = self.gpa_scale;
+        self.weighted_mean(&self.get_student_grades(student_id), |grade| scale.points(grade.score))
+    }
+
+    // 一组成绩的总学分
+    pub fn total_credits(&self, grades: &[&Grade]) -> f32 {
+        grades.iter().map(|grade| self.credits_for(&grade.subject)).sum()
+    }
+
+    // 学生有成绩的所有学期，按名称排序
+    pub fn get_student_semesters(&self, student_id: &str) -> Vec<String> {
+        let mut semesters: Vec<String> = self
+            .get_student_grades(student_id)
+            .into_iter()
+            .map(|grade| grade.semester.clone())
+            .collect();
+        semesters.sort();
+        semesters.dedup();
+        semesters
     }
 
     // 获取所有学生列表
@@ -124,17 +255,17 @@ impl GradeManagementSystem {
     }
 
     // 统计某门课程的成绩分布
-    pub fn get_subject_statistics(&self, subject: &str, semester: &str) -> HashMap<String, i32> {
-        let mut statistics = HashMap::new();
-        statistics.insert("A".to_string(), 0);
-        statistics.insert("B".to_string(), 0);
-        statistics.insert("C".to_string(), 0);
-        statistics.insert("D".to_string(), 0);
-        statistics.insert("F".to_string(), 0);
+    // 按课程的等级制统计，结果按等级从高到低排列，包括人数为 0 的等级
+    pub fn get_subject_statistics(&self, subject: &str, semester: &str) -> Vec<(String, i32)> {
+        let scale = self.grading_scale_for(subject);
+        let mut statistics: Vec<(String, i32)> =
+            scale.level_names().into_iter().map(|level| (level.to_string(), 0)).collect();
 
         for grade in self.grades.iter().filter(|g| g.subject == subject && g.semester == semester) {
-            let level = grade.get_grade_level().to_string();
-            *statistics.get_mut(&level).unwrap() += 1;
+            let level = grade.get_grade_level(scale);
+            if let Some(entry) = statistics.iter_mut().find(|(name, _)| name == level) {
+                entry.1 += 1;
+            }
         }
 
         statistics
@@ -145,3 +276,178 @@ impl GradeManagementSystem {
         self.grades.iter().collect()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grading::GradeLevel;
+    use std::error::Error;
+
+    fn student(id: &str) -> Student {
+        Student::new(id.to_string(), format!("学生{}", id), "1班".to_string(), "计算机".to_string())
+    }
+
+    fn grade(student_id: &str, subject: &str, score: f32, semester: &str) -> Grade {
+        Grade::new(student_id.to_string(), subject.to_string(), score, semester.to_string())
+    }
+
+    #[test]
+    fn test_student_errors() {
+        let mut system = GradeManagementSystem::new();
+        system.add_student(student("1")).unwrap();
+        assert!(matches!(system.add_student(student("1")), Err(GradeError::StudentExists(id)) if id == "1"));
+        assert!(matches!(system.remove_student("2"), Err(GradeError::StudentNotFound(id)) if id == "2"));
+        assert!(matches!(
+            system.add_grade(grade("2", "数学", 90.0, "2024春")),
+            Err(GradeError::StudentNotFound(id)) if id == "2"
+        ));
+    }
+
+    #[test]
+    fn test_grade_errors() {
+        let mut system = GradeManagementSystem::new();
+        system.add_student(student("1")).unwrap();
+        system.add_grade(grade("1", "数学", 90.0, "2024春")).unwrap();
+        assert!(matches!(
+            system.add_grade(grade("1", "数学", 80.0, "2024春")),
+            Err(GradeError::GradeExists { subject, .. }) if subject == "数学"
+        ));
+        // 不同学期的同一科目可以添加
+        system.add_grade(grade("1", "数学", 80.0, "2024秋")).unwrap();
+        assert!(matches!(
+            system.update_grade("1", "英语", "2024春", 70.0),
+            Err(GradeError::GradeNotFound { subject, .. }) if subject == "英语"
+        ));
+        system.update_grade("1", "数学", "2024春", 95.0).unwrap();
+        assert_eq!(system.get_student_semester_grades("1", "2024春")[0].score, 95.0);
+    }
+
+    #[test]
+    fn test_remove_student_removes_grades() {
+        let mut system = GradeManagementSystem::new();
+        system.add_student(student("1")).unwrap();
+        system.add_student(student("2")).unwrap();
+        system.add_grade(grade("1", "数学", 90.0, "2024春")).unwrap();
+        system.add_grade(grade("2", "数学", 80.0, "2024春")).unwrap();
+        system.remove_student("1").unwrap();
+        assert!(system.get_student("1").is_none());
+        assert_eq!(system.get_all_grades().len(), 1);
+        assert_eq!(system.get_all_grades()[0].student_id, "2");
+    }
+
+    #[test]
+    fn test_course_errors() {
+        let mut system = GradeManagementSystem::new();
+        assert!(matches!(system.set_course_credits("数学", 0.0), Err(GradeError::InvalidCredits(_))));
+        assert!(matches!(system.set_course_credits("数学", f32::NAN), Err(GradeError::InvalidCredits(_))));
+        assert!(matches!(system.set_course_credits("数学", f32::INFINITY), Err(GradeError::InvalidCredits(_))));
+        assert!(system.get_course("数学").is_none());
+        assert!(matches!(system.remove_course("数学"), Err(GradeError::CourseNotFound(name)) if name == "数学"));
+    }
+
+    // 数学 4 学分、英语 2 学分，体育没有定义课程按 1 学分计算
+    fn weighted_system() -> GradeManagementSystem {
+        let mut system = GradeManagementSystem::new();
+        system.add_student(student("1")).unwrap();
+        system.set_course_credits("数学", 4.0).unwrap();
+        system.set_course_credits("英语", 2.0).unwrap();
+        system.set_course_credits("物理", 3.0).unwrap();
+        system.add_grade(grade("1", "数学", 90.0, "2024春")).unwrap();
+        system.add_grade(grade("1", "英语", 60.0, "2024春")).unwrap();
+        system.add_grade(grade("1", "体育", 75.0, "2024春")).unwrap();
+        system.add_grade(grade("1", "物理", 80.0, "2024秋")).unwrap();
+        system
+    }
+
+    fn assert_close(actual: Option<f32>, expected: f32) {
+        let actual = actual.unwrap();
+        assert!((actual - expected).abs() < 1e-4, "{} != {}", actual, expected);
+    }
+
+    #[test]
+    fn test_weighted_average() {
+        let system = weighted_system();
+        assert_close(system.calculate_semester_average("1", "2024春"), 555.0 / 7.0);
+        assert_close(system.calculate_semester_average("1", "2024秋"), 80.0);
+        assert_close(system.calculate_cumulative_average("1"), 79.5);
+        assert_eq!(system.total_credits(&system.get_student_grades("1")), 10.0);
+        assert!(system.calculate_semester_average("1", "2025春").is_none());
+        assert!(system.calculate_cumulative_gpa("2").is_none());
+    }
+
+    #[test]
+    fn test_weighted_gpa() {
+        let mut system = weighted_system();
+        assert_eq!(system.gpa_scale(), GpaScale::FourPoint);
+        assert_close(system.calculate_semester_gpa("1", "2024春"), 20.0 / 7.0);
+        assert_close(system.calculate_cumulative_gpa("1"), 2.9);
+        system.set_gpa_scale(GpaScale::FivePoint);
+        assert_close(system.calculate_cumulative_gpa("1"), 2.95);
+        system.set_gpa_scale(GpaScale::Pku);
+        assert_close(system.calculate_cumulative_gpa("1"), 2.9828125);
+    }
+
+    #[test]
+    fn test_course_grading_scale_overrides_global() {
+        let mut system = GradeManagementSystem::new();
+        let math = grade("1", "数学", 65.0, "2024春");
+        let sports = grade("1", "体育", 65.0, "2024春");
+        assert_eq!(system.grade_level(&math), "D");
+
+        system.set_grading_scale(GradingScale::five_level());
+        system.set_course_grading_scale("体育", Some(GradingScale::pass_fail()));
+        assert_eq!(system.grading_scale_for("数学").name, "five-level");
+        assert_eq!(system.grading_scale_for("体育").name, "pass-fail");
+        assert_eq!(system.grade_level(&math), "及格");
+        assert_eq!(system.grade_level(&sports), "通过");
+
+        // 课程单独的及格线
+        let strict = GradingScale::new("strict".to_string(), vec![GradeLevel::new("A", 0.0)], 70.0).unwrap();
+        system.set_course_grading_scale("数学", Some(strict));
+        assert!(!system.is_passing(&math));
+        assert!(system.is_passing(&sports));
+
+        // 改回全局等级制
+        system.set_course_grading_scale("数学", None);
+        assert_eq!(system.grading_scale_for("数学").name, "five-level");
+    }
+
+    #[test]
+    fn test_statistics_use_course_scale() {
+        let mut system = GradeManagementSystem::new();
+        for (id, score) in [("1", 95.0), ("2", 65.0), ("3", 40.0)] {
+            system.add_student(student(id)).unwrap();
+            system.add_grade(grade(id, "体育", score, "2024春")).unwrap();
+        }
+        assert_eq!(
+            system.get_subject_statistics("体育", "2024春"),
+            [("A", 1), ("B", 0), ("C", 0), ("D", 1), ("F", 1)].map(|(level, count)| (level.to_string(), count))
+        );
+        system.set_course_grading_scale("体育", Some(GradingScale::pass_fail()));
+        assert_eq!(
+            system.get_subject_statistics("体育", "2024春"),
+            [("通过".to_string(), 2), ("不通过".to_string(), 1)]
+        );
+    }
+
+    #[test]
+    fn test_course_credits_keep_grading_scale() {
+        let mut system = GradeManagementSystem::new();
+        system.set_course_grading_scale("数学", Some(GradingScale::pass_fail()));
+        system.set_course_credits("数学", 3.0).unwrap();
+        let course = system.get_course("数学").unwrap();
+        assert_eq!(course.credits, 3.0);
+        assert_eq!(course.grading_scale, Some(GradingScale::pass_fail()));
+        system.remove_course("数学").unwrap();
+        assert_eq!(system.credits_for("数学"), DEFAULT_CREDITS);
+    }
+
+    #[test]
+    fn test_error_display_and_source() {
+        assert_eq!(GradeError::StudentNotFound("1".to_string()).to_string(), "学号 1 不存在");
+        assert!(GradeError::StudentNotFound("1".to_string()).source().is_none());
+        let error = GradeError::from(std::io::Error::new(std::io::ErrorKind::NotFound, "missing"));
+        assert!(error.to_string().starts_with("读写文件失败"));
+        assert!(error.source().is_some());
+    }
+}